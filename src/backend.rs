@@ -0,0 +1,580 @@
+use crate::{
+    events::Failure,
+    health_monitor, mission_log, mission_state,
+    msgs::{
+        detection_msgs::{DetectedObject, DetectedObjects},
+        diagnostic_msgs::DiagnosticArray,
+        geometry_msgs::{Point, PointStamped, Pose, PoseStamped, Vector3},
+        mavros_msgs::PositionTarget,
+        nav_msgs::Path,
+        nodes_monitor_msgs::Status,
+        qr_detector_msgs::QRCodeArray,
+        sensor_msgs::BatteryState,
+        std_msgs,
+        task_manager::{DroneStatus, MissionEvent},
+    },
+    service_clients::{self, ServiceError},
+    task_manager::{self, TaskType},
+    topic_publishers, topic_subscribers,
+};
+use lazy_static::lazy_static;
+use rosrust::{Duration, Subscriber, Time};
+use serde_json::{json, Value};
+use std::{
+    cell::Cell,
+    collections::HashSet,
+    sync::{Arc, Mutex, RwLock},
+    thread,
+};
+
+lazy_static! {
+    /// Активная реализация взаимодействия с дроном и окружающими его узлами. По умолчанию - ROS.
+    static ref BACKEND: RwLock<Arc<dyn DroneBackend>> = RwLock::new(Arc::new(RosBackend::new()));
+    /// Сервисы, об ошибке вызова которых уже сообщено активному заданию
+    static ref FAILED_SERVICES: Mutex<HashSet<&'static str>> = Mutex::new(HashSet::new());
+}
+
+/// Обработчик завершения цели FastPlanner.
+pub type GoalDoneCallback = Box<dyn Fn(GoalOutcome) + Send + Sync>;
+
+/// Результат завершения цели FastPlanner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GoalOutcome {
+    /// Цель достигнута
+    Succeeded,
+    /// Цель отменена или вытеснена новой целью
+    Canceled,
+    /// Цель не достигнута по иной причине, например отклонена планировщиком
+    Failed,
+}
+
+impl GoalOutcome {
+    /// Возвращает результат, соответствующий названию конечного состояния цели actionlib, например "SUCCEEDED" или
+    /// "Preempted".
+    pub fn from_state_name(name: &str) -> GoalOutcome {
+        match name.to_lowercase().as_str() {
+            "succeeded" => GoalOutcome::Succeeded,
+            "preempted" | "recalled" => GoalOutcome::Canceled,
+            _ => GoalOutcome::Failed,
+        }
+    }
+}
+
+/// Обработчик обнаруженных QR-кодов.
+pub type QrCodesCallback = Box<dyn Fn(QRCodeArray) + Send + Sync>;
+
+/// Обработчик точек обнаруженной линии.
+pub type LinePointsCallback = Box<dyn Fn(Path) + Send + Sync>;
+
+/// Планировщик маршрутов: FastPlanner, FUEL и виртуальные стены.
+pub trait Planner {
+    /// Отправляет FastPlanner цель - позу `pose` в СК "map". Если указан `on_done`, он вызывается с результатом по
+    /// завершении цели, в том числе при её отмене.
+    fn send_goal(&self, pose: Pose, on_done: Option<GoalDoneCallback>);
+    /// Отменяет все цели FastPlanner.
+    fn cancel_all_goals(&self);
+    /// Начинает автономное исследование, переключает FastPlanner на FUEL.
+    fn start_exploration(&self);
+    /// Останавливает автономное исследование, переключая FUEL на FastPlanner.
+    fn pause_exploration(&self);
+    /// Сбрасывает карту и ранее построенные маршруты FUEL.
+    fn reset_exploration(&self);
+    /// Добавляет виртуальную стену `wall` в FastPlanner и FUEL. `wall` должен подаваться в СК "map".
+    fn add_virtual_wall(&self, wall: DetectedObject);
+    /// Включает или отключает виртуальные стены в FastPlanner и FUEL.
+    fn set_virtual_walls_enabled(&self, are_enabled: bool);
+}
+
+/// Восприятие: обнаруженные проёмы, кубы, QR-коды и линии.
+pub trait Perception {
+    /// Возвращает ближайший к дрону обнаруженный проём. Если ещё не найден ни один проём, возвращает `None`.
+    fn get_closest_hole(&self) -> Option<DetectedObject>;
+    /// Возвращает все обнаруженные проёмы.
+    fn get_holes(&self) -> DetectedObjects;
+    /// Возвращает количество обнаруженных проёмов.
+    fn count_holes(&self) -> usize;
+    /// Возвращает все обнаруженные кубы.
+    fn get_cubes(&self) -> DetectedObjects;
+    /// Возвращает количество обнаруженных кубов.
+    fn count_cubes(&self) -> usize;
+    /// Подписывает `callback` на обнаруженные QR-коды.
+    fn subscribe_qr_codes(&self, callback: QrCodesCallback);
+    /// Подписывает `callback` на точки обнаруженной линии.
+    fn subscribe_line_points(&self, callback: LinePointsCallback);
+}
+
+/// Управление движением дрона в обход планировщика.
+pub trait Motion {
+    /// Подаёт дрону команду на взлёт. Блокирует вызывающий поток, пока дрон не достигнет высоты `height`.
+    fn takeoff(&self, height: f32);
+    /// Подаёт дрону команду на приземление в текущей точке. Блокирует вызывающий поток, пока дрон не приземлится.
+    fn land(&self);
+    /// Вращает дрона вокруг оси Z `laps_count` полных оборотов. Не блокирует вызывающий поток.
+    fn spin(&self, laps_count: i32, altitude: f32, angular_velocity: f32);
+    /// См. `Motion::spin`. Блокирует вызывающий поток, пока дрон не прекратит вращение.
+    fn spin_and_wait(&self, laps_count: i32, altitude: f32, angular_velocity: f32);
+    /// Останавливает вращение.
+    fn stop_spinning(&self);
+    /// Отправляет дрону уставку положения.
+    fn send_setpoint(&self, setpoint: PositionTarget);
+}
+
+/// Преобразования между системами координат.
+pub trait Transforms {
+    /// Возвращает точку `point` в указанной СК `target_frame_id` или `None`, если преобразование невозможно.
+    fn transform_point(&self, point: PointStamped, target_frame_id: &str) -> Option<PointStamped>;
+    /// Возвращает позу `pose` в указанной СК `target_frame_id` или `None`, если преобразование невозможно.
+    fn transform_pose(&self, pose: PoseStamped, target_frame_id: &str) -> Option<PoseStamped>;
+}
+
+/// Телеметрия дрона.
+pub trait Telemetry {
+    /// Возвращает текущую позу дрона в СК map.
+    fn get_current_drone_pose(&self) -> PoseStamped;
+    /// Возвращает `true`, если текущая поза дрона не устарела. Реализации без задержки данных всегда возвращают `true`.
+    fn is_drone_pose_fresh(&self) -> bool {
+        true
+    }
+    /// Возвращает скорость дрона (м/с) в СК map, если она известна.
+    fn get_current_drone_velocity(&self) -> Option<Vector3> {
+        None
+    }
+    /// Возвращает текущий вольтаж аккумулятора.
+    fn get_current_battery_voltage(&self) -> f32;
+    /// Возвращает текущее состояние аккумулятора. Неизвестные ток и заряд равны NaN.
+    fn get_current_battery_state(&self) -> BatteryState {
+        BatteryState {
+            voltage: self.get_current_battery_voltage(),
+            current: f32::NAN,
+            percentage: f32::NAN,
+            ..BatteryState::default()
+        }
+    }
+}
+
+/// Публикация результатов работы узла.
+pub trait Reporter {
+    /// Публикует название произошедшего события.
+    fn publish_event(&self, event_name: &str);
+    /// Публикует произошедшее событие с его данными.
+    fn publish_mission_event(&self, event: MissionEvent);
+    /// Публикует изменение статуса дрона.
+    fn publish_status(&self, status: DroneStatus);
+    /// Публикует данные мониторинга узла.
+    fn publish_nodes_monitor_status(&self, status: Status);
+    /// Публикует результат проверки топиков и сервисов, необходимых заданию.
+    fn publish_diagnostics(&self, diagnostics: DiagnosticArray);
+    /// Публикует точку - центр обнаруженного куба.
+    fn publish_detected_cube(&self, position: Point);
+    /// Публикует построенный путь.
+    fn publish_global_path(&self, path: Path);
+}
+
+/// Часы, по которым работают конечные автоматы заданий.
+pub trait Clock {
+    /// Возвращает текущее время.
+    fn now(&self) -> Time;
+    /// Блокирует вызывающий поток на `duration`.
+    fn sleep(&self, duration: Duration);
+    /// Возвращает `false`, если узел завершает работу.
+    fn is_ok(&self) -> bool;
+}
+
+/// Всё, что необходимо конечным автоматам заданий для управления дроном.
+pub trait DroneBackend:
+    Planner + Perception + Motion + Transforms + Telemetry + Reporter + Clock + Send + Sync
+{
+    /// Ожидает и инициализирует всё, что необходимо для выполнения задания `task_type`. Подписки, оформленные
+    /// предыдущим заданием, снимаются.
+    fn init(&self, task_type: TaskType);
+}
+
+/// Возвращает активную реализацию `DroneBackend`.
+pub fn backend() -> Arc<dyn DroneBackend> {
+    BACKEND.read().unwrap().clone()
+}
+
+/// Заменяет активную реализацию `DroneBackend`. Вызывается до запуска задания.
+pub fn set_backend(backend: Arc<dyn DroneBackend>) {
+    *BACKEND.write().unwrap() = backend;
+}
+
+/// Аналог `rosrust::Rate`, работающий по часам активной реализации `DroneBackend`.
+pub struct Rate {
+    backend: Arc<dyn DroneBackend>,
+    period: Duration,
+    next: Cell<Time>,
+}
+
+impl Rate {
+    /// Блокирует вызывающий поток до начала следующего периода.
+    pub fn sleep(&self) {
+        let now = self.backend.now();
+        let next = self.next.get();
+        if next.nanos() > now.nanos() {
+            self.backend
+                .sleep(Duration::from_nanos(next.nanos() - now.nanos()));
+            self.next
+                .set(Time::from_nanos(next.nanos() + self.period.nanos()));
+        } else {
+            self.next
+                .set(Time::from_nanos(now.nanos() + self.period.nanos()));
+        }
+    }
+}
+
+/// Создаёт `Rate` с частотой `hz` Гц.
+pub fn rate(hz: f64) -> Rate {
+    let backend = backend();
+    let period = Duration::from_nanos((1_000_000_000.0 / hz) as i64);
+    let next = Time::from_nanos(backend.now().nanos() + period.nanos());
+    Rate {
+        backend,
+        period,
+        next: Cell::new(next),
+    }
+}
+
+/// Реализация `DroneBackend` через сервисы, топики и серверы actionlib ROS.
+pub struct RosBackend {
+    subscribers: Mutex<Vec<Subscriber>>,
+    raw_point_publisher: Mutex<Option<rosrust::Publisher<PositionTarget>>>,
+    global_path_publisher: Mutex<Option<rosrust::Publisher<Path>>>,
+}
+
+impl RosBackend {
+    pub fn new() -> RosBackend {
+        RosBackend {
+            subscribers: Mutex::new(Vec::new()),
+            raw_point_publisher: Mutex::new(None),
+            global_path_publisher: Mutex::new(None),
+        }
+    }
+}
+
+/// Возвращает результат вызова сервиса `result` или, если вызов завершился ошибкой, значение `fallback`. О первой
+/// ошибке каждого сервиса сообщает активному заданию событием `Failure` через `task_manager::report_failure`, так как
+/// ошибка может произойти во время перехода конечного автомата задания.
+pub(crate) fn unwrap_or_report<T>(
+    result: Result<T, ServiceError>,
+    fallback: impl FnOnce() -> T,
+) -> T {
+    let error = match result {
+        Ok(value) => return value,
+        Err(error) => error,
+    };
+    rosrust::ros_err!("{}", error);
+    if let Some(task_type) = task_manager::get_active_task() {
+        if FAILED_SERVICES.lock().unwrap().insert(error.service()) {
+            task_manager::report_failure(task_type, Failure::new_service_failure(&error));
+        }
+    }
+    fallback()
+}
+
+/// Забывает сервисы, об ошибке вызова которых уже сообщено, перед запуском задания.
+pub(crate) fn clear_failed_services() {
+    FAILED_SERVICES.lock().unwrap().clear();
+}
+
+/// Возвращает результат преобразования СК `result` или `None`, если преобразование невозможно. Сервис преобразований
+/// отклоняет запрос, если преобразование невозможно, и это не сбой: затронутые данные пропускаются. О недоступности
+/// сервиса после всех повторных попыток и об отсутствии ответа сообщается, как в `unwrap_or_report`.
+pub(crate) fn unwrap_transform<T>(result: Result<T, ServiceError>) -> Option<T> {
+    match result {
+        Err(ServiceError::Rejected { service, message }) => {
+            rosrust::ros_warn!("Transform is impossible, \"{}\": {}", service, message);
+            None
+        }
+        result => unwrap_or_report(result.map(Some), || None),
+    }
+}
+
+impl Planner for RosBackend {
+    fn send_goal(&self, pose: Pose, on_done: Option<GoalDoneCallback>) {
+        service_clients::send_goal(pose, on_done);
+    }
+
+    fn cancel_all_goals(&self) {
+        unwrap_or_report(service_clients::cancel_all_goals(), || ());
+    }
+
+    fn start_exploration(&self) {
+        unwrap_or_report(service_clients::start_exploration(), || ());
+    }
+
+    fn pause_exploration(&self) {
+        unwrap_or_report(service_clients::pause_exploration(), || ());
+    }
+
+    fn reset_exploration(&self) {
+        unwrap_or_report(service_clients::reset_fuel(), || ());
+    }
+
+    fn add_virtual_wall(&self, wall: DetectedObject) {
+        unwrap_or_report(service_clients::add_virtual_wall(wall), || ());
+    }
+
+    fn set_virtual_walls_enabled(&self, are_enabled: bool) {
+        if are_enabled {
+            unwrap_or_report(service_clients::enable_virtual_walls(), || ());
+        } else {
+            unwrap_or_report(service_clients::disable_virtual_walls(), || ());
+        }
+    }
+}
+
+/// Записывает в журнал задания результат `result` запроса к восприятию `method`.
+pub(crate) fn record_perception(method: &str, result: Value) {
+    mission_log::record(
+        "perception",
+        json!({
+            "method": method,
+            "result": result,
+        }),
+    );
+}
+
+/// Записывает в журнал задания обнаруженные QR-коды `qr_codes`, если они есть.
+pub(crate) fn record_qr_codes(qr_codes: &QRCodeArray) {
+    if qr_codes.qr_codes.is_empty() {
+        return;
+    }
+    let recorded_qr_codes: Vec<Value> = qr_codes
+        .qr_codes
+        .iter()
+        .map(|qr| {
+            json!({
+                "data": qr.data,
+                "frame_id": qr.header.frame_id,
+                "position": { "x": qr.position.x, "y": qr.position.y, "z": qr.position.z },
+            })
+        })
+        .collect();
+    mission_log::record("qr_codes", json!({ "qr_codes": recorded_qr_codes }));
+}
+
+/// Возвращает обнаруженные объекты `objects` в формате JSON.
+pub(crate) fn detected_objects_to_json(objects: &DetectedObjects) -> Value {
+    objects
+        .detected_objects
+        .iter()
+        .map(mission_state::detected_object_to_json)
+        .collect()
+}
+
+impl Perception for RosBackend {
+    fn get_closest_hole(&self) -> Option<DetectedObject> {
+        let hole = unwrap_or_report(service_clients::get_closest_hole(), || None);
+        record_perception(
+            "get_closest_hole",
+            hole.as_ref()
+                .map(mission_state::detected_object_to_json)
+                .unwrap_or(Value::Null),
+        );
+        hole
+    }
+
+    fn get_holes(&self) -> DetectedObjects {
+        let holes = unwrap_or_report(service_clients::get_holes(), DetectedObjects::default);
+        record_perception("get_holes", detected_objects_to_json(&holes));
+        holes
+    }
+
+    fn count_holes(&self) -> usize {
+        let count = unwrap_or_report(service_clients::count_holes(), || 0);
+        record_perception("count_holes", json!(count));
+        count
+    }
+
+    fn get_cubes(&self) -> DetectedObjects {
+        let cubes = unwrap_or_report(service_clients::get_cubes(), DetectedObjects::default);
+        record_perception("get_cubes", detected_objects_to_json(&cubes));
+        cubes
+    }
+
+    fn count_cubes(&self) -> usize {
+        let count = unwrap_or_report(service_clients::count_cubes(), || 0);
+        record_perception("count_cubes", json!(count));
+        count
+    }
+
+    fn subscribe_qr_codes(&self, callback: QrCodesCallback) {
+        let subscriber =
+            topic_subscribers::subscribe_qr_codes(Box::new(move |qr_codes: QRCodeArray| {
+                record_qr_codes(&qr_codes);
+                callback(qr_codes);
+            }));
+        self.subscribers.lock().unwrap().push(subscriber);
+    }
+
+    fn subscribe_line_points(&self, callback: LinePointsCallback) {
+        let subscriber = topic_subscribers::subscribe_line_points(callback);
+        self.subscribers.lock().unwrap().push(subscriber);
+    }
+}
+
+impl Motion for RosBackend {
+    fn takeoff(&self, height: f32) {
+        unwrap_or_report(service_clients::takeoff(height), || ());
+    }
+
+    fn land(&self) {
+        unwrap_or_report(service_clients::land(), || ());
+    }
+
+    fn spin(&self, laps_count: i32, altitude: f32, angular_velocity: f32) {
+        thread::spawn(move || {
+            unwrap_or_report(
+                service_clients::spin_and_wait(laps_count, altitude, angular_velocity),
+                || (),
+            )
+        });
+    }
+
+    fn spin_and_wait(&self, laps_count: i32, altitude: f32, angular_velocity: f32) {
+        unwrap_or_report(
+            service_clients::spin_and_wait(laps_count, altitude, angular_velocity),
+            || (),
+        );
+    }
+
+    fn stop_spinning(&self) {
+        unwrap_or_report(service_clients::stop_spinning(), || ());
+    }
+
+    fn send_setpoint(&self, setpoint: PositionTarget) {
+        let mut publisher = self.raw_point_publisher.lock().unwrap();
+        if publisher.is_none() {
+            *publisher = Some(rosrust::publish("/mavros/setpoint_raw/local", 10).unwrap());
+        }
+        publisher.as_ref().unwrap().send(setpoint).unwrap();
+    }
+}
+
+impl Transforms for RosBackend {
+    fn transform_point(&self, point: PointStamped, target_frame_id: &str) -> Option<PointStamped> {
+        let source_point = mission_state::point_to_json(&point.point);
+        let source_frame_id = point.header.frame_id.clone();
+        let result = unwrap_transform(service_clients::transform_point(point, target_frame_id));
+        mission_log::record(
+            "transform",
+            json!({
+                "method": "transform_point",
+                "source_frame_id": source_frame_id,
+                "target_frame_id": target_frame_id,
+                "source": source_point,
+                "result": result.as_ref().map(|result| mission_state::point_to_json(&result.point)),
+            }),
+        );
+        result
+    }
+
+    fn transform_pose(&self, pose: PoseStamped, target_frame_id: &str) -> Option<PoseStamped> {
+        let source_pose = mission_state::pose_to_json(&pose.pose);
+        let source_frame_id = pose.header.frame_id.clone();
+        let result = unwrap_transform(service_clients::transform_pose(pose, target_frame_id));
+        mission_log::record(
+            "transform",
+            json!({
+                "method": "transform_pose",
+                "source_frame_id": source_frame_id,
+                "target_frame_id": target_frame_id,
+                "source": source_pose,
+                "result": result.as_ref().map(|result| mission_state::pose_to_json(&result.pose)),
+            }),
+        );
+        result
+    }
+}
+
+impl Telemetry for RosBackend {
+    fn get_current_drone_pose(&self) -> PoseStamped {
+        topic_subscribers::get_current_drone_pose()
+    }
+
+    fn is_drone_pose_fresh(&self) -> bool {
+        topic_subscribers::is_drone_pose_fresh()
+    }
+
+    fn get_current_drone_velocity(&self) -> Option<Vector3> {
+        topic_subscribers::get_current_drone_velocity()
+    }
+
+    fn get_current_battery_voltage(&self) -> f32 {
+        topic_subscribers::get_current_battery_voltage()
+    }
+
+    fn get_current_battery_state(&self) -> BatteryState {
+        topic_subscribers::get_current_battery_state()
+    }
+}
+
+impl Reporter for RosBackend {
+    fn publish_event(&self, event_name: &str) {
+        topic_publishers::DRONE_EVENT_PUBLISHER
+            .send(std_msgs::String {
+                data: event_name.to_string(),
+            })
+            .unwrap();
+    }
+
+    fn publish_mission_event(&self, event: MissionEvent) {
+        topic_publishers::MISSION_EVENT_PUBLISHER
+            .send(event)
+            .unwrap();
+    }
+
+    fn publish_status(&self, status: DroneStatus) {
+        topic_publishers::DRONE_STATUS_PUBLISHER
+            .send(status)
+            .unwrap();
+    }
+
+    fn publish_nodes_monitor_status(&self, status: Status) {
+        topic_publishers::NODES_MONITOR_PUBLISHER
+            .send(status)
+            .unwrap();
+    }
+
+    fn publish_diagnostics(&self, diagnostics: DiagnosticArray) {
+        let _ = topic_publishers::DIAGNOSTICS_PUBLISHER.send(diagnostics);
+    }
+
+    fn publish_detected_cube(&self, position: Point) {
+        topic_publishers::publish_new_detected_cube(position);
+    }
+
+    fn publish_global_path(&self, path: Path) {
+        let mut publisher = self.global_path_publisher.lock().unwrap();
+        if publisher.is_none() {
+            *publisher = Some(rosrust::publish("global_path", 1).unwrap());
+        }
+        publisher.as_ref().unwrap().send(path).unwrap();
+    }
+}
+
+impl Clock for RosBackend {
+    fn now(&self) -> Time {
+        rosrust::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        rosrust::sleep(duration);
+    }
+
+    fn is_ok(&self) -> bool {
+        rosrust::is_ok()
+    }
+}
+
+impl DroneBackend for RosBackend {
+    fn init(&self, task_type: TaskType) {
+        clear_failed_services();
+        self.subscribers.lock().unwrap().clear();
+        service_clients::init(task_type);
+        topic_publishers::init(task_type);
+        topic_subscribers::init(task_type);
+        health_monitor::start();
+    }
+}
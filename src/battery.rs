@@ -0,0 +1,328 @@
+use crate::{
+    backend::{self, backend},
+    events::{log_event, EventKind, EventPayload, Failure},
+    msgs::sensor_msgs::BatteryState,
+    task_manager::{self, TaskType},
+};
+use lazy_static::lazy_static;
+use std::{collections::VecDeque, fmt, sync::Mutex};
+
+lazy_static! {
+    /// Состояние аккумулятора, отслеживаемое во время выполнения задания
+    static ref BATTERY_MONITOR: Mutex<BatteryMonitor> = Mutex::new(BatteryMonitor::new());
+}
+
+/// Уровень заряда аккумулятора
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BatteryLevel {
+    Normal,
+    /// Заряд опустился ниже порога предупреждения
+    Warning,
+    /// Заряд опустился ниже допустимого предела, задание необходимо завершать
+    Critical,
+}
+
+/// Отфильтрованное состояние аккумулятора
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BatteryStatus {
+    /// Средний по окну вольтаж (В) с поправкой на просадку под нагрузкой
+    pub voltage: f32,
+    /// Последнее значение тока (А), если оно известно
+    pub current: Option<f32>,
+    /// Средний по окну заряд (от 0 до 1), если он известен
+    pub percentage: Option<f32>,
+    pub level: BatteryLevel,
+}
+
+impl fmt::Display for BatteryStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.2} V", self.voltage)?;
+        if let Some(percentage) = self.percentage {
+            write!(f, ", {:.0} %", percentage * 100.0)?;
+        }
+        if let Some(current) = self.current {
+            write!(f, ", {:.1} A", current)?;
+        }
+        Ok(())
+    }
+}
+
+/// Фильтр состояния аккумулятора: усредняет вольтаж и заряд по окну последних измерений и определяет уровень заряда с
+/// гистерезисом, чтобы кратковременная просадка вольтажа при резком манёвре не завершала задание.
+pub struct BatteryMonitor {
+    voltages: VecDeque<f32>,
+    percentages: VecDeque<f32>,
+    current: Option<f32>,
+    level: BatteryLevel,
+}
+
+impl BatteryMonitor {
+    pub fn new() -> BatteryMonitor {
+        BatteryMonitor {
+            voltages: VecDeque::new(),
+            percentages: VecDeque::new(),
+            current: None,
+            level: BatteryLevel::Normal,
+        }
+    }
+
+    /// Добавляет измерение `state`. Возвращает новый уровень заряда, если он изменился.
+    pub fn add_sample(&mut self, state: &BatteryState) -> Option<BatteryLevel> {
+        let options = task_manager::options().battery;
+        self.current = if state.current.is_finite() {
+            Some(state.current)
+        } else {
+            None
+        };
+        let voltage =
+            state.voltage + self.current.map(f32::abs).unwrap_or(0.0) * options.internal_resistance;
+        push_sample(&mut self.voltages, voltage, options.window_size);
+        if state.percentage.is_finite() && state.percentage >= 0.0 && state.percentage <= 1.0 {
+            push_sample(&mut self.percentages, state.percentage, options.window_size);
+        }
+        let level = self.get_next_level();
+        if level == self.level {
+            return None;
+        }
+        self.level = level;
+        Some(level)
+    }
+
+    /// Возвращает отфильтрованное состояние аккумулятора. Если измерений ещё не было, возвращает `None`.
+    pub fn get_status(&self) -> Option<BatteryStatus> {
+        Some(BatteryStatus {
+            voltage: get_average(&self.voltages)?,
+            current: self.current,
+            percentage: get_average(&self.percentages),
+            level: self.level,
+        })
+    }
+
+    /// Возвращает уровень заряда по отфильтрованному заряду, если он известен, иначе - по вольтажу. Чтобы вернуться на
+    /// более высокий уровень, значение должно превысить порог на величину гистерезиса.
+    fn get_next_level(&self) -> BatteryLevel {
+        let options = task_manager::options().battery;
+        let (value, warning, critical, hysteresis) = match get_average(&self.percentages) {
+            Some(percentage) => (
+                percentage,
+                options.warning_percentage,
+                options.critical_percentage,
+                options.hysteresis_percentage,
+            ),
+            None => (
+                get_average(&self.voltages).unwrap(),
+                options.warning_voltage,
+                task_manager::options().min_battery_voltage,
+                options.hysteresis_voltage,
+            ),
+        };
+        match self.level {
+            _ if value <= critical => BatteryLevel::Critical,
+            BatteryLevel::Critical if value <= critical + hysteresis => BatteryLevel::Critical,
+            _ if value <= warning => BatteryLevel::Warning,
+            BatteryLevel::Critical | BatteryLevel::Warning if value <= warning + hysteresis => {
+                BatteryLevel::Warning
+            }
+            _ => BatteryLevel::Normal,
+        }
+    }
+}
+
+fn push_sample(samples: &mut VecDeque<f32>, sample: f32, window_size: usize) {
+    samples.push_back(sample);
+    while samples.len() > window_size.max(1) {
+        samples.pop_front();
+    }
+}
+
+fn get_average(samples: &VecDeque<f32>) -> Option<f32> {
+    if samples.is_empty() {
+        None
+    } else {
+        Some(samples.iter().sum::<f32>() / samples.len() as f32)
+    }
+}
+
+/// Возвращает отфильтрованное состояние аккумулятора. Если измерений ещё не было, возвращает `None`.
+pub fn get_battery_status() -> Option<BatteryStatus> {
+    BATTERY_MONITOR.lock().unwrap().get_status()
+}
+
+/// Возвращает отфильтрованный вольтаж аккумулятора (В), а если измерений ещё не было - текущий вольтаж.
+pub fn get_filtered_voltage() -> f32 {
+    get_battery_status()
+        .map(|status| status.voltage)
+        .unwrap_or_else(|| backend().get_current_battery_voltage())
+}
+
+/// Запускает поток, который с частотой `options().battery.rate` фильтрует состояние аккумулятора, публикует изменения
+/// уровня заряда как события и при первом переходе на критический уровень передаёт заданию `task_type` событие
+/// `Failure::LowVoltageDetected`.
+pub fn start_monitoring(task_type: TaskType) {
+    *BATTERY_MONITOR.lock().unwrap() = BatteryMonitor::new();
+    let generation = task_manager::get_mission_generation();
+    std::thread::spawn(move || {
+        let rate = backend::rate(task_manager::options().battery.rate);
+        let mut has_failure_been_handled = false;
+        while task_manager::is_mission_current(generation) {
+            let state = backend().get_current_battery_state();
+            let (level, status) = {
+                let mut monitor = BATTERY_MONITOR.lock().unwrap();
+                (monitor.add_sample(&state), monitor.get_status().unwrap())
+            };
+            if let Some(level) = level {
+                log_event(
+                    EventKind::BatteryLevelChanged,
+                    &format!("Battery{:?} ({})", level, status),
+                    EventPayload::Details(format!("{:?}", level)),
+                );
+                if level == BatteryLevel::Critical
+                    && !has_failure_been_handled
+                    && task_manager::is_mission_current(generation)
+                {
+                    has_failure_been_handled = true;
+                    task_manager::handle_failure(task_type, Failure::new_low_voltage_detected());
+                }
+            }
+            rate.sleep();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BatteryLevel, BatteryMonitor};
+    use crate::{
+        fake_backend::testing,
+        geometry,
+        msgs::{geometry_msgs::Pose, sensor_msgs::BatteryState},
+        task_manager::{self, TaskType},
+    };
+    use std::{thread, time::Duration};
+
+    fn voltage_sample(voltage: f32) -> BatteryState {
+        BatteryState {
+            voltage,
+            current: f32::NAN,
+            percentage: f32::NAN,
+            ..BatteryState::default()
+        }
+    }
+
+    fn percentage_sample(percentage: f32) -> BatteryState {
+        BatteryState {
+            voltage: 12.0,
+            current: f32::NAN,
+            percentage,
+            ..BatteryState::default()
+        }
+    }
+
+    /// Добавляет измерение `state` `count` раз и возвращает все изменения уровня заряда.
+    fn add_samples(
+        monitor: &mut BatteryMonitor,
+        state: &BatteryState,
+        count: usize,
+    ) -> Vec<BatteryLevel> {
+        (0..count)
+            .filter_map(|_| monitor.add_sample(state))
+            .collect()
+    }
+
+    #[test]
+    fn changes_voltage_level_with_hysteresis() {
+        // Окно - 10 измерений, предупреждение - 10.5 В, критический уровень - 10 В, гистерезис - 0.2 В
+        let mut monitor = BatteryMonitor::new();
+        assert!(monitor.get_status().is_none());
+        assert!(add_samples(&mut monitor, &voltage_sample(12.0), 10).is_empty());
+        assert_eq!(monitor.get_status().unwrap().level, BatteryLevel::Normal);
+
+        assert_eq!(
+            add_samples(&mut monitor, &voltage_sample(10.5), 10),
+            vec![BatteryLevel::Warning]
+        );
+        assert!(add_samples(&mut monitor, &voltage_sample(10.6), 10).is_empty());
+        assert_eq!(
+            add_samples(&mut monitor, &voltage_sample(10.8), 10),
+            vec![BatteryLevel::Normal]
+        );
+
+        assert_eq!(
+            add_samples(&mut monitor, &voltage_sample(10.0), 10),
+            vec![BatteryLevel::Warning, BatteryLevel::Critical]
+        );
+        assert!(add_samples(&mut monitor, &voltage_sample(10.15), 10).is_empty());
+        assert_eq!(
+            add_samples(&mut monitor, &voltage_sample(10.3), 10),
+            vec![BatteryLevel::Warning]
+        );
+    }
+
+    #[test]
+    fn averages_voltage_over_window() {
+        let mut monitor = BatteryMonitor::new();
+        add_samples(&mut monitor, &voltage_sample(12.0), 10);
+        // Кратковременная просадка не меняет уровень заряда
+        assert_eq!(monitor.add_sample(&voltage_sample(9.0)), None);
+        let status = monitor.get_status().unwrap();
+        assert!((status.voltage - 11.7).abs() < 1e-4);
+        assert_eq!(status.level, BatteryLevel::Normal);
+        assert_eq!(status.percentage, None);
+    }
+
+    #[test]
+    fn prefers_percentage_over_voltage() {
+        // Предупреждение - 30 %, критический уровень - 15 %, гистерезис - 5 %
+        let mut monitor = BatteryMonitor::new();
+        let state = BatteryState {
+            voltage: 9.0,
+            ..percentage_sample(0.5)
+        };
+        assert!(add_samples(&mut monitor, &state, 10).is_empty());
+        assert_eq!(
+            add_samples(&mut monitor, &percentage_sample(0.14), 10),
+            vec![BatteryLevel::Warning, BatteryLevel::Critical]
+        );
+        assert!(add_samples(&mut monitor, &percentage_sample(0.18), 10).is_empty());
+        assert_eq!(
+            add_samples(&mut monitor, &percentage_sample(0.25), 10),
+            vec![BatteryLevel::Warning]
+        );
+        // Некорректный заряд не попадает в окно
+        assert!(add_samples(&mut monitor, &percentage_sample(1.5), 10).is_empty());
+        assert!((monitor.get_status().unwrap().percentage.unwrap() - 0.25).abs() < 1e-4);
+    }
+
+    #[test]
+    fn reports_critical_level_once() {
+        let mut options = task_manager::OPTIONS.clone();
+        options.mission_log.directory = String::new();
+        options.battery.rate = 50.0;
+        let home_pose = Pose {
+            position: geometry::new_point(0.0, 0.0, 0.0),
+            orientation: geometry::default_quaternion(),
+        };
+        let mission = testing::start_with_options(TaskType::Two, home_pose, options);
+        mission.wait_for_state("Exploring", 5);
+        let count = |name: &str| {
+            mission
+                .events()
+                .iter()
+                .filter(|event| *event == name)
+                .count()
+        };
+
+        mission.fake.set_battery_voltage(9.0);
+        mission.wait_until("critical battery level", 5, |_| {
+            count("BatteryCritical") == 1
+        });
+        mission.fake.set_battery_voltage(12.6);
+        mission.wait_until("normal battery level", 5, |_| count("BatteryNormal") == 1);
+        mission.fake.set_battery_voltage(9.0);
+        mission.wait_until("second critical battery level", 5, |_| {
+            count("BatteryCritical") == 2
+        });
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(count("LowVoltageDetected"), 1);
+    }
+}
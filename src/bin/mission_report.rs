@@ -0,0 +1,32 @@
+use std::{path::Path, process};
+use task_manager::report;
+
+/// Создаёт отчёт о задании по журналу задания, путь к которому передаётся первым аргументом. Отчёт и итоги задания
+/// записываются рядом с журналом.
+/// ### Пример запуска:
+/// ```bash
+/// cargo run --bin mission_report -- mission_logs/mission_1700000000000_task2.jsonl
+/// ```
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("Mission log path is not specified.");
+            process::exit(2);
+        }
+    };
+    let path = Path::new(&path);
+    match report::generate(path) {
+        Ok(summary) => {
+            println!(
+                "Task {}: {} after {:.1} s, {:.2} m flown",
+                summary.task, summary.final_state, summary.duration_seconds, summary.distance_flown
+            );
+            println!("Report: {}", path.with_extension("md").display());
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::{path::Path, process};
+use task_manager::replay::*;
+
+/// Воспроизводит журнал задания, путь к которому передаётся первым аргументом, через конечный автомат 1 или 2
+/// задания без ROS и проверяет, что задание прошло те же состояния, что и при записи. При несовпадении процесс
+/// завершается с кодом 1.
+/// ### Пример запуска:
+/// ```bash
+/// cargo run --bin replay_mission -- mission_logs/mission_1700000000000_task2.jsonl
+/// ```
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("Mission log path is not specified.");
+            process::exit(2);
+        }
+    };
+    let recording = match MissionRecording::load(Path::new(&path)) {
+        Ok(recording) => recording,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(2);
+        }
+    };
+    let result = replay_mission(recording, ReplayOptions::default());
+    println!(
+        "Recorded transitions: {}",
+        result.expected_transitions.join(" -> ")
+    );
+    println!(
+        "Replayed transitions: {}",
+        result.actual_transitions.join(" -> ")
+    );
+    println!("Recorded events: {}", result.expected_events.join(", "));
+    println!("Replayed events: {}", result.actual_events.join(", "));
+    if result.is_matching() {
+        println!("[PASS] transitions match");
+    } else {
+        println!("[FAIL] transitions differ");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    env,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+use task_manager::{scenario::*, simulator::run_mission};
+
+/// Флаг, с которым запускается дочерний процесс для выполнения одного сценария.
+const SINGLE_SCENARIO_FLAG: &str = "--single";
+
+/// Загружает все сценарии (`*.yaml`, `*.yml`, `*.toml`) из директории, переданной первым аргументом (по умолчанию -
+/// `scenarios`), выполняет каждый из них в кинематическом симуляторе и выводит результат проверки каждого ожидания.
+/// Каждый сценарий выполняется в отдельном процессе, так как состояние конечных автоматов заданий глобально.
+/// Если хотя бы один сценарий не пройден, процесс завершается с кодом 1.
+/// ### Пример запуска:
+/// ```bash
+/// cargo run --bin run_scenarios -- scenarios
+/// ```
+fn main() {
+    let args = env::args().collect::<Vec<_>>();
+    if args.get(1).map(String::as_str) == Some(SINGLE_SCENARIO_FLAG) {
+        match args.get(2) {
+            Some(path) => process::exit(run_single_scenario(Path::new(path))),
+            None => {
+                eprintln!("Scenario path is not specified.");
+                process::exit(2);
+            }
+        }
+    }
+    let directory = PathBuf::from(args.get(1).map(String::as_str).unwrap_or("scenarios"));
+    let paths = match find_scenarios(&directory) {
+        Ok(paths) => paths,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(2);
+        }
+    };
+    let executable = env::current_exe().unwrap();
+    let mut failed_paths = Vec::new();
+    for path in &paths {
+        println!("=== {}", path.display());
+        let is_passed = Command::new(&executable)
+            .arg(SINGLE_SCENARIO_FLAG)
+            .arg(path)
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false);
+        if !is_passed {
+            failed_paths.push(path);
+        }
+    }
+    println!(
+        "\n{} of {} scenario(s) passed.",
+        paths.len() - failed_paths.len(),
+        paths.len()
+    );
+    for path in &failed_paths {
+        println!("[FAIL] {}", path.display());
+    }
+    if !failed_paths.is_empty() {
+        process::exit(1);
+    }
+}
+
+/// Выполняет сценарий `path` и возвращает код завершения процесса.
+fn run_single_scenario(path: &Path) -> i32 {
+    let scenario = match Scenario::load(path) {
+        Ok(scenario) => scenario,
+        Err(error) => {
+            println!("[FAIL] {}", error);
+            return 2;
+        }
+    };
+    let task_type = match scenario.get_task_type() {
+        Ok(task_type) => task_type,
+        Err(error) => {
+            println!("[FAIL] {}: {}", scenario.name, error);
+            return 2;
+        }
+    };
+    println!("{} (task {})", scenario.name, scenario.task);
+    let result = run_mission(
+        task_type,
+        scenario.get_world(),
+        scenario.get_simulator_options(),
+        scenario.max_seconds,
+    );
+    println!(
+        "Simulated {:.0} s, final state {}, events: {}",
+        result.simulated_seconds,
+        result.final_state,
+        result.events.join(", ")
+    );
+    let mut is_ok = true;
+    for expectation in scenario.check(&result) {
+        println!(
+            "[{}] {}: {}",
+            if expectation.is_passed {
+                "PASS"
+            } else {
+                "FAIL"
+            },
+            expectation.name,
+            expectation.details
+        );
+        is_ok &= expectation.is_passed;
+    }
+    if is_ok {
+        0
+    } else {
+        1
+    }
+}
@@ -0,0 +1,60 @@
+use std::process;
+use task_manager::{geometry, simulator::*, task_manager::TaskType};
+
+/// Выполняет 1 или 2 задание целиком в кинематическом симуляторе, без ROS и остальных узлов Аэробота, и проверяет
+/// результат. Номер задания передаётся первым аргументом (по умолчанию - 1). При невыполнении хотя бы одной проверки
+/// процесс завершается с кодом 1.
+/// ### Пример запуска:
+/// ```bash
+/// cargo run --bin simulate_mission -- 2
+/// ```
+fn main() {
+    let task_type = match std::env::args().nth(1).as_deref() {
+        None | Some("1") => TaskType::One,
+        Some("2") => TaskType::Two,
+        Some(task) => {
+            eprintln!("Task {} can not be simulated.", task);
+            process::exit(2);
+        }
+    };
+    let world = match task_type {
+        TaskType::Two => create_task2_world(),
+        _ => create_task1_world(),
+    };
+    let result = run_mission(task_type, world.clone(), SimulatorOptions::default(), 900.0);
+    println!("{:#?}", result);
+
+    let mut checks = vec![
+        ("final state is Landing", result.final_state == "Landing"),
+        ("drone has landed", result.has_landed),
+    ];
+    match task_type {
+        TaskType::One => {
+            checks.push((
+                "all cubes are published",
+                result.published_cubes.len() == world.cubes.len(),
+            ));
+            checks.push((
+                "drone has landed near the start point",
+                geometry::get_distance_between_points(
+                    &result.final_pose.position,
+                    &world.start_pose.position,
+                ) <= 0.3,
+            ));
+        }
+        _ => {
+            checks.push((
+                "rooms are passed in order \"1 2\"",
+                result.passed_rooms.join(" ") == "1 2",
+            ));
+        }
+    }
+    let mut is_ok = true;
+    for (name, is_passed) in checks {
+        println!("[{}] {}", if is_passed { "PASS" } else { "FAIL" }, name);
+        is_ok &= is_passed;
+    }
+    if !is_ok {
+        process::exit(1);
+    }
+}
@@ -30,14 +30,14 @@ fn main() {
 
     thread::spawn(move || {
         rosrust::ros_info!("Looking for entry...");
-        let mut entry = get_closest_hole();
+        let mut entry = get_closest_hole().unwrap();
         while entry.is_none() {
             rosrust::sleep(rosrust::Duration::from_seconds(1));
-            entry = get_closest_hole();
+            entry = get_closest_hole().unwrap();
         }
         rosrust::ros_info!("Found entry.");
         let entry = entry.unwrap();
-        stop_spinning();
+        stop_spinning().unwrap();
         cancel_all_goals();
         rosrust::ros_info!("Flying through entry...");
         FAST_PLANNER_SERVER_CLIENT
@@ -77,8 +77,8 @@ fn main() {
                 if has_drone_flew_through_hole.flew_through {
                     rosrust::ros_info!("Detected that drone has flown through entry.");
                     rosrust::ros_info!("Adding virtual wall and landing...");
-                    add_virtual_wall(entry);
-                    land();
+                    add_virtual_wall(entry).unwrap();
+                    land().unwrap();
                     return;
                 }
                 rate.sleep();
@@ -87,9 +87,9 @@ fn main() {
     });
     thread::spawn(move || {
         rosrust::ros_info!("Taking off...");
-        takeoff(altitude);
+        takeoff(altitude).unwrap();
         rosrust::ros_info!("Spinning (looking for entry)...");
-        spin(1, altitude, angular_velocity);
+        spin_and_wait(1, altitude, angular_velocity).unwrap();
     });
     rosrust::spin();
 }
@@ -0,0 +1,61 @@
+use crate::{
+    events::{log_event, EventKind, EventPayload},
+    report, task_manager,
+};
+
+/// Режим прерывания задания
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AbortMode {
+    /// Посадка на месте
+    Land,
+    /// Возвращение на точку старта и посадка
+    Return,
+    /// Зависание на месте до следующей команды
+    Hover,
+}
+
+/// Команда на прерывание выполнения задания. Останавливает фоновые потоки текущего состояния, цели FastPlanner,
+/// вращение и исследование FUEL и переводит конечный автомат задания в одно из конечных состояний
+#[derive(Debug, Clone, PartialEq)]
+pub struct Abort {
+    pub mode: AbortMode,
+}
+
+impl Abort {
+    pub fn new(mode: AbortMode) -> Abort {
+        log_event(
+            EventKind::Abort,
+            &format!("Abort ({:?})", mode),
+            EventPayload::Details(format!("{:?}", mode)),
+        );
+        *task_manager::IS_OK.lock().unwrap() = false;
+        task_manager::set_paused(false);
+        report::schedule();
+        Abort { mode }
+    }
+}
+
+/// Команда на приостановку выполнения задания. Отменяет цели FastPlanner, приостанавливает вращение и исследование FUEL
+/// без смены состояния конечного автомата
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pause {}
+
+impl Pause {
+    pub fn new() -> Pause {
+        log_event(EventKind::Pause, "Pause", EventPayload::None);
+        task_manager::set_paused(true);
+        Pause {}
+    }
+}
+
+/// Команда на продолжение приостановленного задания. Повторяет действие, выполнявшееся текущим состоянием
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resume {}
+
+impl Resume {
+    pub fn new() -> Resume {
+        log_event(EventKind::Resume, "Resume", EventPayload::None);
+        task_manager::set_paused(false);
+        Resume {}
+    }
+}
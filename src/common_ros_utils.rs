@@ -36,17 +36,7 @@ pub fn wait_for_service(service_name: &str) {
 /// сообщение об ожидании.
 pub fn wait_for_topic(topic_name: &str) {
     let rate = rosrust::rate(0.1);
-    let normalized_topic_name = if topic_name.starts_with("/") {
-        topic_name.to_string()
-    } else {
-        format!("/{}", topic_name)
-    };
-    while rosrust::is_ok()
-        && rosrust::topics()
-            .unwrap()
-            .into_iter()
-            .all(|topic| topic.name != normalized_topic_name)
-    {
+    while rosrust::is_ok() && !is_topic_published(topic_name) {
         ros_info!(
             "{}: waiting for required topic \"{}\"...",
             NODE_NAME,
@@ -65,6 +55,22 @@ pub fn wait_for_topic(topic_name: &str) {
     }
 }
 
+/// Возвращает `true`, если в ROS опубликован топик `topic_name`. Если мастер ROS недоступен, возвращает `false`.
+pub fn is_topic_published(topic_name: &str) -> bool {
+    let normalized_topic_name = if topic_name.starts_with("/") {
+        topic_name.to_string()
+    } else {
+        format!("/{}", topic_name)
+    };
+    rosrust::topics()
+        .map(|topics| {
+            topics
+                .into_iter()
+                .any(|topic| topic.name == normalized_topic_name)
+        })
+        .unwrap_or(false)
+}
+
 /// Ожидает указанный сервер actionlib. Пока сервер не станет доступен, каждые 10 секунд публикует
 /// сообщение об ожидании.
 pub fn wait_for_actionlib_server<T: Action>(client: &SimpleActionClient<T>, client_name: &str) {
@@ -91,8 +97,12 @@ pub fn wait_for_actionlib_server<T: Action>(client: &SimpleActionClient<T>, clie
 }
 
 /// Возвращает параметр `param_name`, переданный через ROS. Если параметр не передан, либо при его получении произошла ошибка,
+/// возвращается `default_value`. Если ROS не инициализирован (например, при работе с `FakeBackend`), сразу
 /// возвращается `default_value`.
 pub fn get_param<'b, T: Deserialize<'b>>(param_name: &str, default_value: T) -> T {
+    if !rosrust::is_initialized() {
+        return default_value;
+    }
     match rosrust::param(param_name) {
         Some(param) => match param.get() {
             Ok(param) => {
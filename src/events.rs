@@ -1,35 +1,249 @@
-use crate::{msgs::std_msgs, task_manager, topic_publishers::DRONE_EVENT_PUBLISHER};
+use crate::{
+    backend::backend,
+    mission_log, mission_state,
+    msgs::{detection_msgs::DetectedObject, geometry_msgs::Point, task_manager::MissionEvent},
+    service_clients::ServiceError,
+    status,
+    task_manager::{self, TaskType},
+};
+use lazy_static::lazy_static;
 use rosrust::ros_info;
+use serde_json::json;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
 
-/// Рассылает сообщения о создании события
-pub fn log_event(event_name: &str) {
-    DRONE_EVENT_PUBLISHER
-        .send(std_msgs::String {
-            data: event_name.to_string(),
-        })
-        .unwrap();
+/// Порядковый номер следующего события
+static NEXT_EVENT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+lazy_static! {
+    /// Последние `options().event_history_size` событий, от старых к новым
+    static ref RECENT_EVENTS: Mutex<VecDeque<MissionEvent>> = Mutex::new(VecDeque::new());
+}
+
+/// Вид события, публикуемого в топик task_manager/mission_events
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    Other,
+    EntryFound,
+    FlewInsideBuilding,
+    CubeFound,
+    FoundAllCubes,
+    QrFound,
+    HoleFound,
+    FlewThroughHole,
+    FlewNearLandingPoint,
+    FlewNearStartPoint,
+    Failure,
+    Abort,
+    Pause,
+    Resume,
+    /// Наступил срок задания или истекло время ожидания данных
+    Timeout,
+    BatteryLevelChanged,
+    ServiceDown,
+    ServiceRestored,
+}
+
+impl EventKind {
+    /// Возвращает значение поля `kind` сообщения `MissionEvent`.
+    pub fn to_msg(self) -> u8 {
+        match self {
+            EventKind::Other => MissionEvent::OTHER,
+            EventKind::EntryFound => MissionEvent::ENTRY_FOUND,
+            EventKind::FlewInsideBuilding => MissionEvent::FLEW_INSIDE_BUILDING,
+            EventKind::CubeFound => MissionEvent::CUBE_FOUND,
+            EventKind::FoundAllCubes => MissionEvent::FOUND_ALL_CUBES,
+            EventKind::QrFound => MissionEvent::QR_FOUND,
+            EventKind::HoleFound => MissionEvent::HOLE_FOUND,
+            EventKind::FlewThroughHole => MissionEvent::FLEW_THROUGH_HOLE,
+            EventKind::FlewNearLandingPoint => MissionEvent::FLEW_NEAR_LANDING_POINT,
+            EventKind::FlewNearStartPoint => MissionEvent::FLEW_NEAR_START_POINT,
+            EventKind::Failure => MissionEvent::FAILURE,
+            EventKind::Abort => MissionEvent::ABORT,
+            EventKind::Pause => MissionEvent::PAUSE,
+            EventKind::Resume => MissionEvent::RESUME,
+            EventKind::Timeout => MissionEvent::TIMEOUT,
+            EventKind::BatteryLevelChanged => MissionEvent::BATTERY_LEVEL_CHANGED,
+            EventKind::ServiceDown => MissionEvent::SERVICE_DOWN,
+            EventKind::ServiceRestored => MissionEvent::SERVICE_RESTORED,
+        }
+    }
+}
+
+/// Данные события
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventPayload {
+    None,
+    /// Найденный проём
+    Hole(DetectedObject),
+    /// Содержание и положение QR-кода в СК map
+    Qr {
+        content: String,
+        position: Point,
+    },
+    /// Идентификатор и положение куба в СК map
+    Cube {
+        id: i32,
+        position: Point,
+    },
+    /// Причина невозможности продолжить задание
+    Failure(Failure),
+    /// Данные в свободной форме
+    Details(String),
+}
+
+/// Рассылает сообщения о создании события: название события `event_name` публикуется в топик task_manager/events, а
+/// событие вида `kind` с данными `payload` - в топик task_manager/mission_events.
+pub fn log_event(kind: EventKind, event_name: &str, payload: EventPayload) {
+    let active_task = task_manager::get_active_task();
+    let mut event = MissionEvent {
+        seq: NEXT_EVENT_SEQ.fetch_add(1, Ordering::SeqCst),
+        stamp: backend().now(),
+        mission_id: task_manager::get_mission_id(),
+        task: active_task.map(TaskType::number).unwrap_or(0),
+        kind: kind.to_msg(),
+        name: event_name.to_string(),
+        state: active_task
+            .map(task_manager::get_task_state_name)
+            .unwrap_or_default()
+            .to_string(),
+        ..Default::default()
+    };
+    match payload {
+        EventPayload::None => {}
+        EventPayload::Hole(hole) => event.hole = hole,
+        EventPayload::Qr { content, position } => {
+            event.qr_content = content;
+            event.qr_position = position;
+        }
+        EventPayload::Cube { id, position } => {
+            event.cube_id = id;
+            event.cube_position = position;
+        }
+        EventPayload::Failure(failure) => event.failure_cause = format!("{:?}", failure),
+        EventPayload::Details(details) => event.details = details,
+    }
+    {
+        let mut recent_events = RECENT_EVENTS.lock().unwrap();
+        recent_events.push_back(event.clone());
+        while recent_events.len() > task_manager::options().event_history_size {
+            recent_events.pop_front();
+        }
+    }
+    mission_log::record("event", mission_state::event_to_json(&event));
+    backend().publish_event(event_name);
+    backend().publish_mission_event(event);
     ros_info!("Event {} is created.", event_name);
 }
 
+/// Возвращает последние события, от старых к новым.
+pub fn get_recent_events() -> Vec<MissionEvent> {
+    RECENT_EVENTS.lock().unwrap().iter().cloned().collect()
+}
+
 /// События, предотвращающие дальнейшее успешное выполнение задания
 #[derive(Debug, Clone, PartialEq)]
 pub enum Failure {
     /// Событие происходит, когда вольтаж аккумулятора дрона становится ниже допустимого предела
     LowVoltageDetected,
+    /// Событие происходит, когда до окончания времени на выполнение задания остаётся время только на его завершение
+    SoftDeadlineReached,
     /// Событие происходит, когда время на выполнение активной части задания истекло
     Timeout,
+    /// Событие происходит, когда оставшегося времени задания едва хватает на возвращение на точку старта
+    NotEnoughTimeToReturn,
+    /// Событие происходит, когда заряда аккумулятора едва хватает на возвращение на точку старта
+    NotEnoughBatteryToReturn,
+    /// Событие происходит, когда с сервисом не удаётся связаться после всех повторных попыток вызова
+    ServiceUnavailable,
+    /// Событие происходит, когда сервис отклоняет запрос
+    ServiceCallRejected,
+    /// Событие происходит, когда сервис не отвечает за отведённое время
+    ServiceCallTimedOut,
+    /// Событие происходит, когда поза дрона перестаёт обновляться
+    LocalizationLost,
+    /// Событие происходит, когда состояние аккумулятора перестаёт обновляться
+    BatteryStateLost,
 }
 
 impl Failure {
     pub fn new_low_voltage_detected() -> Failure {
-        log_event("LowVoltageDetected");
+        log_failure(&Failure::LowVoltageDetected {}, "LowVoltageDetected");
         *task_manager::IS_OK.lock().unwrap() = false;
+        task_manager::set_paused(false);
         Failure::LowVoltageDetected {}
     }
 
+    pub fn new_soft_deadline_reached() -> Failure {
+        log_failure(&Failure::SoftDeadlineReached {}, "SoftDeadlineReached");
+        *task_manager::IS_OK.lock().unwrap() = false;
+        task_manager::set_paused(false);
+        Failure::SoftDeadlineReached {}
+    }
+
     pub fn new_timeout() -> Failure {
-        log_event("Timeout");
+        log_failure(&Failure::Timeout {}, "Timeout");
         *task_manager::IS_OK.lock().unwrap() = false;
+        task_manager::set_paused(false);
         Failure::Timeout {}
     }
+    pub fn new_not_enough_time_to_return() -> Failure {
+        log_failure(&Failure::NotEnoughTimeToReturn {}, "NotEnoughTimeToReturn");
+        *task_manager::IS_OK.lock().unwrap() = false;
+        task_manager::set_paused(false);
+        Failure::NotEnoughTimeToReturn {}
+    }
+
+    pub fn new_not_enough_battery_to_return() -> Failure {
+        log_failure(
+            &Failure::NotEnoughBatteryToReturn {},
+            "NotEnoughBatteryToReturn",
+        );
+        *task_manager::IS_OK.lock().unwrap() = false;
+        task_manager::set_paused(false);
+        Failure::NotEnoughBatteryToReturn {}
+    }
+
+    pub fn new_localization_lost() -> Failure {
+        log_failure(&Failure::LocalizationLost {}, "LocalizationLost");
+        *task_manager::IS_OK.lock().unwrap() = false;
+        task_manager::set_paused(false);
+        Failure::LocalizationLost {}
+    }
+
+    pub fn new_battery_state_lost() -> Failure {
+        log_failure(&Failure::BatteryStateLost {}, "BatteryStateLost");
+        *task_manager::IS_OK.lock().unwrap() = false;
+        task_manager::set_paused(false);
+        Failure::BatteryStateLost {}
+    }
+
+    /// Возвращает событие, соответствующее ошибке вызова сервиса `error`.
+    pub fn new_service_failure(error: &ServiceError) -> Failure {
+        let failure = match error {
+            ServiceError::Unavailable { .. } => Failure::ServiceUnavailable {},
+            ServiceError::Rejected { .. } => Failure::ServiceCallRejected {},
+            ServiceError::Timeout { .. } => Failure::ServiceCallTimedOut {},
+        };
+        log_failure(&failure, &format!("{:?} ({})", failure, error.service()));
+        *task_manager::IS_OK.lock().unwrap() = false;
+        task_manager::set_paused(false);
+        failure
+    }
+}
+
+/// Рассылает сообщения о событии `failure` с названием `event_name`.
+fn log_failure(failure: &Failure, event_name: &str) {
+    status::set_last_failure(failure);
+    mission_log::record("failure", json!({ "cause": format!("{:?}", failure) }));
+    log_event(
+        EventKind::Failure,
+        event_name,
+        EventPayload::Failure(failure.clone()),
+    );
 }
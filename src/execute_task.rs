@@ -0,0 +1,142 @@
+use crate::{
+    backend::{self, backend},
+    commands::AbortMode,
+    events,
+    msgs::task_manager::{
+        DroneStatus, ExecuteTaskAction, ExecuteTaskFeedback, ExecuteTaskGoal, ExecuteTaskResult,
+        MissionEvent,
+    },
+    status, task1,
+    task_manager::{self, TaskType, OPTIONS},
+    NODE_NAME,
+};
+use rosrust::{ros_info, ros_warn, Time};
+use rosrust_actionlib::{ActionServer, ServerSimpleGoalHandle};
+
+/// Создаёт сервер actionlib task_manager/execute_task, который запускает задание из цели `ExecuteTask`, публикует его
+/// статус и прогресс и возвращает итог задания после посадки или прерывания. Отмена цели прерывает задание в режиме
+/// `preempt_mode`.
+pub fn start_server() -> ActionServer<ExecuteTaskAction> {
+    ActionServer::<ExecuteTaskAction>::new_simple(
+        &format!("{}/execute_task", NODE_NAME),
+        handle_goal,
+    )
+    .unwrap()
+}
+
+fn handle_goal(goal_handle: ServerSimpleGoalHandle<ExecuteTaskAction>) {
+    let goal: ExecuteTaskGoal = goal_handle.goal().clone();
+    let started_at = backend().now();
+    let (task_type, mission_id) = match task_manager::request_task(goal.task, None, &goal.overrides)
+    {
+        Ok(started_task) => started_task,
+        Err(reason) => {
+            ros_warn!("ExecuteTask goal is rejected: {}", reason);
+            goal_handle
+                .response()
+                .result(ExecuteTaskResult {
+                    failure_cause: reason.clone(),
+                    ..Default::default()
+                })
+                .text(&reason)
+                .send_rejected();
+            return;
+        }
+    };
+    let rate = backend::rate(OPTIONS.status_rate);
+    let mut is_preempted = false;
+    loop {
+        if goal_handle.canceled() && !is_preempted {
+            is_preempted = true;
+            let mode = match goal.preempt_mode {
+                ExecuteTaskGoal::PREEMPT_RETURN => AbortMode::Return,
+                ExecuteTaskGoal::PREEMPT_HOVER => AbortMode::Hover,
+                _ => AbortMode::Land,
+            };
+            ros_info!(
+                "ExecuteTask goal is preempted, aborting the task ({:?})",
+                mode
+            );
+            if let Err(error) = task_manager::abort_task(mode) {
+                ros_warn!("Could not abort the task: {}", error);
+            }
+        }
+        let status = status::get_drone_status();
+        if !task_manager::is_task_running() || !backend().is_ok() {
+            let result = get_result(task_type, &mission_id, status, started_at);
+            let response = goal_handle.response().result(result.clone());
+            if is_preempted {
+                response
+                    .text("Task is aborted on preemption")
+                    .send_canceled();
+            } else if result.success {
+                response.send_succeeded();
+            } else {
+                response.text(&result.failure_cause).send_aborted();
+            }
+            return;
+        }
+        goal_handle.publish_feedback(ExecuteTaskFeedback {
+            progress: get_progress(task_type, &status),
+            status,
+        });
+        rate.sleep();
+    }
+}
+
+/// Возвращает долю выполненного задания от 0 до 1.
+fn get_progress(task_type: TaskType, status: &DroneStatus) -> f32 {
+    let progress = match task_type {
+        TaskType::One if status.cubes_count > 0 => {
+            status.cubes_found as f64 / status.cubes_count as f64
+        }
+        _ => {
+            let max_seconds = status.mission_seconds + status.remaining_seconds;
+            if max_seconds > 0.0 {
+                status.mission_seconds / max_seconds
+            } else {
+                0.0
+            }
+        }
+    };
+    progress.max(0.0).min(1.0) as f32
+}
+
+/// Возвращает итог задания `task_type` с идентификатором `mission_id`, запущенного в момент `started_at`, по его
+/// статусу `status`.
+fn get_result(
+    task_type: TaskType,
+    mission_id: &str,
+    status: DroneStatus,
+    started_at: Time,
+) -> ExecuteTaskResult {
+    let is_aborted = events::get_recent_events()
+        .iter()
+        .any(|event| event.kind == MissionEvent::ABORT && event.stamp >= started_at);
+    let failure_cause = if !status.last_failure.is_empty() {
+        status.last_failure.clone()
+    } else if is_aborted {
+        "Aborted".to_string()
+    } else if status.state_name != "Landing" {
+        format!("Task has finished in state {}", status.state_name)
+    } else {
+        String::new()
+    };
+    ExecuteTaskResult {
+        mission_id: mission_id.to_string(),
+        success: failure_cause.is_empty(),
+        final_state: status.state_name,
+        failure_cause,
+        mission_seconds: status.mission_seconds,
+        cubes: match task_type {
+            TaskType::One => task1::drone_state::DETECTED_CUBES
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|cube| cube.pose.position.clone())
+                .collect(),
+            _ => Vec::new(),
+        },
+        passed_rooms: status.passed_rooms,
+    }
+}
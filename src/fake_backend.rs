@@ -0,0 +1,617 @@
+use crate::{
+    backend::*,
+    geometry, mission_log, mission_state,
+    msgs::{
+        detection_msgs::{DetectedObject, DetectedObjects},
+        diagnostic_msgs::DiagnosticArray,
+        geometry_msgs::{Point, PointStamped, Pose, PoseStamped},
+        mavros_msgs::PositionTarget,
+        nav_msgs::Path,
+        nodes_monitor_msgs::Status,
+        qr_detector_msgs::QRCodeArray,
+        std_msgs::Header,
+        task_manager::{DroneStatus, MissionEvent},
+    },
+    service_clients::ServiceError,
+    task_manager::TaskType,
+};
+use rosrust::{Duration, Time};
+use serde_json::{json, Value};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
+
+/// Вызов, поступивший в `FakeBackend`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackendCall {
+    SendGoal(Pose),
+    CancelAllGoals,
+    StartExploration,
+    PauseExploration,
+    ResetExploration,
+    AddVirtualWall(DetectedObject),
+    SetVirtualWallsEnabled(bool),
+    Takeoff(f32),
+    Land,
+    Spin {
+        laps_count: i32,
+        altitude: f32,
+        angular_velocity: f32,
+    },
+    SpinAndWait {
+        laps_count: i32,
+        altitude: f32,
+        angular_velocity: f32,
+    },
+    StopSpinning,
+    SendSetpoint(PositionTarget),
+}
+
+#[derive(Default)]
+struct FakeBackendState {
+    calls: Vec<BackendCall>,
+    pending_goals: Vec<(Pose, Option<GoalDoneCallback>)>,
+    holes: Vec<DetectedObject>,
+    cubes: Vec<DetectedObject>,
+    qr_codes_callbacks: Vec<Arc<dyn Fn(QRCodeArray) + Send + Sync>>,
+    line_points_callbacks: Vec<Arc<dyn Fn(Path) + Send + Sync>>,
+    drone_pose: PoseStamped,
+    is_drone_pose_stale: bool,
+    battery_voltage: f32,
+    events: Vec<String>,
+    mission_events: Vec<MissionEvent>,
+    statuses: Vec<DroneStatus>,
+    detected_cubes: Vec<Point>,
+    transform_errors: VecDeque<ServiceError>,
+}
+
+/// Реализация `DroneBackend` в памяти, не требующая ROS. Записывает все поступившие вызовы и позволяет
+/// вручную задавать телеметрию, обнаруженные объекты и момент достижения целей планировщика. Как и реализация ROS,
+/// записывает в журнал задания позу дрона, ответы восприятия, QR-коды и завершение целей, поэтому журнал задания,
+/// выполненного на ней, можно воспроизвести.
+pub struct FakeBackend {
+    state: Mutex<FakeBackendState>,
+    started_at: Instant,
+    /// Смещение (нс) часов относительно времени, прошедшего с создания
+    time_offset: AtomicI64,
+    is_ok: AtomicBool,
+}
+
+impl FakeBackend {
+    pub fn new() -> FakeBackend {
+        FakeBackend {
+            state: Mutex::new(FakeBackendState {
+                drone_pose: PoseStamped {
+                    header: Header {
+                        seq: 0,
+                        stamp: Time::default(),
+                        frame_id: "map".to_string(),
+                    },
+                    pose: Pose {
+                        position: geometry::default_point(),
+                        orientation: geometry::default_quaternion(),
+                    },
+                },
+                battery_voltage: 12.6,
+                ..FakeBackendState::default()
+            }),
+            started_at: Instant::now(),
+            time_offset: AtomicI64::new(0),
+            is_ok: AtomicBool::new(true),
+        }
+    }
+
+    /// Возвращает все поступившие вызовы в порядке их поступления.
+    pub fn calls(&self) -> Vec<BackendCall> {
+        self.state.lock().unwrap().calls.clone()
+    }
+
+    /// Возвращает названия всех опубликованных событий.
+    pub fn events(&self) -> Vec<String> {
+        self.state.lock().unwrap().events.clone()
+    }
+
+    /// Возвращает все опубликованные события с их данными.
+    pub fn mission_events(&self) -> Vec<MissionEvent> {
+        self.state.lock().unwrap().mission_events.clone()
+    }
+
+    /// Возвращает все опубликованные статусы дрона.
+    pub fn statuses(&self) -> Vec<DroneStatus> {
+        self.state.lock().unwrap().statuses.clone()
+    }
+
+    /// Возвращает центры всех опубликованных кубов.
+    pub fn detected_cubes(&self) -> Vec<Point> {
+        self.state.lock().unwrap().detected_cubes.clone()
+    }
+
+    /// Возвращает позы целей планировщика, которые ещё не были достигнуты или отменены.
+    pub fn pending_goals(&self) -> Vec<Pose> {
+        self.state
+            .lock()
+            .unwrap()
+            .pending_goals
+            .iter()
+            .map(|(pose, _)| pose.clone())
+            .collect()
+    }
+
+    /// Перемещает дрона в позу `pose`.
+    pub fn set_drone_pose(&self, pose: Pose) {
+        let recorded_pose = mission_state::pose_to_json(&pose);
+        {
+            let mut state = self.state.lock().unwrap();
+            state.drone_pose.header.stamp = self.now();
+            state.drone_pose.pose = pose;
+        }
+        mission_log::record("pose", json!({ "pose": recorded_pose }));
+    }
+
+    /// Задаёт, устарела ли поза дрона.
+    pub fn set_drone_pose_stale(&self, is_stale: bool) {
+        self.state.lock().unwrap().is_drone_pose_stale = is_stale;
+    }
+
+    /// Задаёт текущий вольтаж аккумулятора.
+    pub fn set_battery_voltage(&self, voltage: f32) {
+        self.state.lock().unwrap().battery_voltage = voltage;
+    }
+
+    /// Добавляет обнаруженный проём.
+    pub fn add_hole(&self, hole: DetectedObject) {
+        self.state.lock().unwrap().holes.push(hole);
+    }
+
+    /// Добавляет обнаруженный куб.
+    pub fn add_cube(&self, cube: DetectedObject) {
+        self.state.lock().unwrap().cubes.push(cube);
+    }
+
+    /// Задаёт ошибку сервиса преобразований `error`, которой завершится следующий запрос преобразования СК. Ошибка
+    /// обрабатывается так же, как в реализации ROS.
+    pub fn fail_next_transform(&self, error: ServiceError) {
+        self.state.lock().unwrap().transform_errors.push_back(error);
+    }
+
+    /// Передаёт `qr_codes` всем подписчикам на QR-коды.
+    pub fn push_qr_codes(&self, qr_codes: QRCodeArray) {
+        record_qr_codes(&qr_codes);
+        let callbacks = self.state.lock().unwrap().qr_codes_callbacks.clone();
+        for callback in callbacks {
+            callback(qr_codes.clone());
+        }
+    }
+
+    /// Передаёт `path` всем подписчикам на точки линии.
+    pub fn push_line_points(&self, path: Path) {
+        let callbacks = self.state.lock().unwrap().line_points_callbacks.clone();
+        for callback in callbacks {
+            callback(path.clone());
+        }
+    }
+
+    /// Успешно завершает все отправленные цели планировщика, перемещая дрона в позу последней из них.
+    pub fn complete_goals(&self) {
+        let goals = std::mem::take(&mut self.state.lock().unwrap().pending_goals);
+        for (pose, on_done) in goals {
+            self.set_drone_pose(pose.clone());
+            record_goal_done(&pose, "Succeeded");
+            if let Some(on_done) = on_done {
+                on_done(GoalOutcome::Succeeded);
+            }
+        }
+    }
+
+    /// Переводит часы вперёд на `seconds` секунд, не дожидаясь их.
+    pub fn advance_time(&self, seconds: f64) {
+        self.time_offset
+            .fetch_add((seconds * 1e9) as i64, Ordering::SeqCst);
+    }
+
+    /// Имитирует завершение работы узла: `Clock::is_ok` начинает возвращать `false`.
+    pub fn shutdown(&self) {
+        self.is_ok.store(false, Ordering::SeqCst);
+    }
+
+    fn record(&self, call: BackendCall) {
+        self.state.lock().unwrap().calls.push(call);
+    }
+
+    fn header(&self) -> Header {
+        Header {
+            seq: 0,
+            stamp: self.now(),
+            frame_id: "map".to_string(),
+        }
+    }
+
+    fn take_transform_error(&self) -> Option<ServiceError> {
+        self.state.lock().unwrap().transform_errors.pop_front()
+    }
+
+    fn set_altitude(&self, altitude: f32) {
+        self.state.lock().unwrap().drone_pose.pose.position.z = altitude as f64;
+    }
+}
+
+/// Записывает в журнал задания завершение цели `pose` с состоянием `state` так же, как FastPlanner.
+fn record_goal_done(pose: &Pose, state: &str) {
+    mission_log::record(
+        "goal_done",
+        json!({
+            "pose": mission_state::pose_to_json(pose),
+            "state": state,
+        }),
+    );
+}
+
+impl Planner for FakeBackend {
+    fn send_goal(&self, pose: Pose, on_done: Option<GoalDoneCallback>) {
+        mission_log::record(
+            "goal",
+            json!({ "pose": mission_state::pose_to_json(&pose) }),
+        );
+        let mut state = self.state.lock().unwrap();
+        state.calls.push(BackendCall::SendGoal(pose.clone()));
+        state.pending_goals.push((pose, on_done));
+    }
+
+    fn cancel_all_goals(&self) {
+        let goals = {
+            let mut state = self.state.lock().unwrap();
+            state.calls.push(BackendCall::CancelAllGoals);
+            std::mem::take(&mut state.pending_goals)
+        };
+        for (pose, on_done) in goals {
+            record_goal_done(&pose, "Preempted");
+            if let Some(on_done) = on_done {
+                on_done(GoalOutcome::Canceled);
+            }
+        }
+    }
+
+    fn start_exploration(&self) {
+        self.record(BackendCall::StartExploration);
+    }
+
+    fn pause_exploration(&self) {
+        self.record(BackendCall::PauseExploration);
+    }
+
+    fn reset_exploration(&self) {
+        self.record(BackendCall::ResetExploration);
+    }
+
+    fn add_virtual_wall(&self, wall: DetectedObject) {
+        self.record(BackendCall::AddVirtualWall(wall));
+    }
+
+    fn set_virtual_walls_enabled(&self, are_enabled: bool) {
+        self.record(BackendCall::SetVirtualWallsEnabled(are_enabled));
+    }
+}
+
+impl Perception for FakeBackend {
+    fn get_closest_hole(&self) -> Option<DetectedObject> {
+        let hole = {
+            let state = self.state.lock().unwrap();
+            let drone_position = &state.drone_pose.pose.position;
+            state
+                .holes
+                .iter()
+                .min_by(|x, y| {
+                    geometry::get_distance_between_points(&x.pose.position, drone_position)
+                        .partial_cmp(&geometry::get_distance_between_points(
+                            &y.pose.position,
+                            drone_position,
+                        ))
+                        .unwrap()
+                })
+                .cloned()
+        };
+        record_perception(
+            "get_closest_hole",
+            hole.as_ref()
+                .map(mission_state::detected_object_to_json)
+                .unwrap_or(Value::Null),
+        );
+        hole
+    }
+
+    fn get_holes(&self) -> DetectedObjects {
+        let holes = DetectedObjects {
+            header: self.header(),
+            detected_objects: self.state.lock().unwrap().holes.clone(),
+        };
+        record_perception("get_holes", detected_objects_to_json(&holes));
+        holes
+    }
+
+    fn count_holes(&self) -> usize {
+        let count = self.state.lock().unwrap().holes.len();
+        record_perception("count_holes", json!(count));
+        count
+    }
+
+    fn get_cubes(&self) -> DetectedObjects {
+        let cubes = DetectedObjects {
+            header: self.header(),
+            detected_objects: self.state.lock().unwrap().cubes.clone(),
+        };
+        record_perception("get_cubes", detected_objects_to_json(&cubes));
+        cubes
+    }
+
+    fn count_cubes(&self) -> usize {
+        let count = self.state.lock().unwrap().cubes.len();
+        record_perception("count_cubes", json!(count));
+        count
+    }
+
+    fn subscribe_qr_codes(&self, callback: QrCodesCallback) {
+        self.state
+            .lock()
+            .unwrap()
+            .qr_codes_callbacks
+            .push(Arc::from(callback));
+    }
+
+    fn subscribe_line_points(&self, callback: LinePointsCallback) {
+        self.state
+            .lock()
+            .unwrap()
+            .line_points_callbacks
+            .push(Arc::from(callback));
+    }
+}
+
+impl Motion for FakeBackend {
+    fn takeoff(&self, height: f32) {
+        self.record(BackendCall::Takeoff(height));
+        self.set_altitude(height);
+    }
+
+    fn land(&self) {
+        self.record(BackendCall::Land);
+        self.set_altitude(0.0);
+    }
+
+    fn spin(&self, laps_count: i32, altitude: f32, angular_velocity: f32) {
+        self.record(BackendCall::Spin {
+            laps_count,
+            altitude,
+            angular_velocity,
+        });
+    }
+
+    fn spin_and_wait(&self, laps_count: i32, altitude: f32, angular_velocity: f32) {
+        self.record(BackendCall::SpinAndWait {
+            laps_count,
+            altitude,
+            angular_velocity,
+        });
+        self.set_altitude(altitude);
+    }
+
+    fn stop_spinning(&self) {
+        self.record(BackendCall::StopSpinning);
+    }
+
+    fn send_setpoint(&self, setpoint: PositionTarget) {
+        self.record(BackendCall::SendSetpoint(setpoint));
+    }
+}
+
+impl Transforms for FakeBackend {
+    fn transform_point(
+        &self,
+        mut point: PointStamped,
+        target_frame_id: &str,
+    ) -> Option<PointStamped> {
+        if let Some(error) = self.take_transform_error() {
+            return unwrap_transform(Err(error));
+        }
+        point.header.frame_id = target_frame_id.to_string();
+        Some(point)
+    }
+
+    fn transform_pose(&self, mut pose: PoseStamped, target_frame_id: &str) -> Option<PoseStamped> {
+        if let Some(error) = self.take_transform_error() {
+            return unwrap_transform(Err(error));
+        }
+        pose.header.frame_id = target_frame_id.to_string();
+        Some(pose)
+    }
+}
+
+impl Telemetry for FakeBackend {
+    fn get_current_drone_pose(&self) -> PoseStamped {
+        self.state.lock().unwrap().drone_pose.clone()
+    }
+
+    fn is_drone_pose_fresh(&self) -> bool {
+        !self.state.lock().unwrap().is_drone_pose_stale
+    }
+
+    fn get_current_battery_voltage(&self) -> f32 {
+        self.state.lock().unwrap().battery_voltage
+    }
+}
+
+impl Reporter for FakeBackend {
+    fn publish_event(&self, event_name: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .events
+            .push(event_name.to_string());
+    }
+
+    fn publish_mission_event(&self, event: MissionEvent) {
+        self.state.lock().unwrap().mission_events.push(event);
+    }
+
+    fn publish_status(&self, status: DroneStatus) {
+        self.state.lock().unwrap().statuses.push(status);
+    }
+
+    fn publish_nodes_monitor_status(&self, _: Status) {}
+
+    fn publish_diagnostics(&self, _: DiagnosticArray) {}
+
+    fn publish_detected_cube(&self, position: Point) {
+        self.state.lock().unwrap().detected_cubes.push(position);
+    }
+
+    fn publish_global_path(&self, _: Path) {}
+}
+
+impl Clock for FakeBackend {
+    fn now(&self) -> Time {
+        Time::from_nanos(
+            self.started_at.elapsed().as_nanos() as i64 + self.time_offset.load(Ordering::SeqCst),
+        )
+    }
+
+    fn sleep(&self, duration: Duration) {
+        if duration.nanos() > 0 {
+            std::thread::sleep(std::time::Duration::from_nanos(duration.nanos() as u64));
+        }
+    }
+
+    fn is_ok(&self) -> bool {
+        self.is_ok.load(Ordering::SeqCst)
+    }
+}
+
+impl DroneBackend for FakeBackend {
+    fn init(&self, _: TaskType) {
+        clear_failed_services();
+        let mut state = self.state.lock().unwrap();
+        state.qr_codes_callbacks.clear();
+        state.line_points_callbacks.clear();
+    }
+}
+
+/// Запуск заданий на `FakeBackend` в тестах конечных автоматов.
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::FakeBackend;
+    use crate::{
+        backend,
+        msgs::geometry_msgs::Pose,
+        task_manager::{self, TaskManagerOptions, TaskType},
+    };
+    use lazy_static::lazy_static;
+    use std::{
+        sync::{Arc, Mutex, MutexGuard},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    lazy_static! {
+        /// Задания хранят состояние в глобальных переменных, поэтому тесты с ними выполняются по очереди
+        static ref TEST_LOCK: Mutex<()> = Mutex::new(());
+    }
+
+    /// Задание, запущенное на `FakeBackend`. При удалении останавливает потоки задания.
+    pub struct TestMission {
+        pub fake: Arc<FakeBackend>,
+        pub task_type: TaskType,
+        _guard: MutexGuard<'static, ()>,
+    }
+
+    /// Делает новый `FakeBackend` активным и запускает на нём задание `task_type` с точкой старта `home_pose` без
+    /// журнала задания.
+    pub fn start(task_type: TaskType, home_pose: Pose) -> TestMission {
+        let mut options = task_manager::OPTIONS.clone();
+        options.mission_log.directory = String::new();
+        start_with_options(task_type, home_pose, options)
+    }
+
+    /// Делает новый `FakeBackend` активным и запускает на нём задание `task_type` с точкой старта `home_pose` и
+    /// параметрами `options`.
+    pub fn start_with_options(
+        task_type: TaskType,
+        home_pose: Pose,
+        options: TaskManagerOptions,
+    ) -> TestMission {
+        // Упавший тест не должен блокировать остальные
+        let guard = TEST_LOCK.lock().unwrap_or_else(|error| error.into_inner());
+        let fake = Arc::new(FakeBackend::new());
+        backend::set_backend(fake.clone());
+        fake.set_drone_pose(home_pose.clone());
+        task_manager::start_task_with_options(task_type, Some(home_pose), options);
+        TestMission {
+            fake,
+            task_type,
+            _guard: guard,
+        }
+    }
+
+    impl TestMission {
+        /// Ожидает, пока `condition` не вернёт `true`. Если этого не произошло за `timeout_seconds`, тест завершается с
+        /// ошибкой, описанной `description`.
+        pub fn wait_until(
+            &self,
+            description: &str,
+            timeout_seconds: u64,
+            condition: impl Fn(&FakeBackend) -> bool,
+        ) {
+            let started_at = Instant::now();
+            while !condition(&self.fake) {
+                assert!(
+                    started_at.elapsed() < Duration::from_secs(timeout_seconds),
+                    "Timed out waiting for {}, the state is {}",
+                    description,
+                    task_manager::get_task_state_name(self.task_type)
+                );
+                thread::sleep(Duration::from_millis(20));
+            }
+        }
+
+        /// Ожидает перехода конечного автомата задания в состояние `state_name` не дольше `timeout_seconds`.
+        pub fn wait_for_state(&self, state_name: &str, timeout_seconds: u64) {
+            let task_type = self.task_type;
+            self.wait_until(state_name, timeout_seconds, |_| {
+                task_manager::get_task_state_name(task_type) == state_name
+            });
+        }
+
+        /// Возвращает названия состояний, через которые прошло задание с момента запуска, начиная с
+        /// `WaitingForCommand`.
+        pub fn states(&self) -> Vec<String> {
+            let mut states: Vec<String> = Vec::new();
+            for status in self.fake.statuses() {
+                if status.state_name == "WaitingForCommand" {
+                    states.clear();
+                }
+                if states.last() != Some(&status.state_name) {
+                    states.push(status.state_name);
+                }
+            }
+            states
+        }
+
+        /// Возвращает названия опубликованных событий без данных в скобках.
+        pub fn events(&self) -> Vec<String> {
+            self.fake
+                .events()
+                .iter()
+                .map(|event| event.split(" (").next().unwrap().to_string())
+                .collect()
+        }
+    }
+
+    impl Drop for TestMission {
+        fn drop(&mut self) {
+            self.fake.shutdown();
+        }
+    }
+}
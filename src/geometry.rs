@@ -4,10 +4,13 @@ use std::fmt::Debug;
 
 use na::{Quaternion, Rotation3, UnitQuaternion, Vector3, Vector4};
 
-use crate::msgs::{
-    detection_msgs::DetectedObject,
-    geometry_msgs::{self, Point, PointStamped, Pose},
-    std_msgs::Header,
+use crate::{
+    backend::backend,
+    msgs::{
+        detection_msgs::DetectedObject,
+        geometry_msgs::{self, Point, PointStamped, Pose},
+        std_msgs::Header,
+    },
 };
 
 impl From<Quaternion<f64>> for geometry_msgs::Quaternion {
@@ -171,7 +174,7 @@ pub fn default_point() -> Point {
 pub fn new_header(frame_id: &str) -> Header {
     Header {
         seq: 0,
-        stamp: rosrust::now(),
+        stamp: backend().now(),
         frame_id: frame_id.to_string(),
     }
 }
@@ -394,3 +397,8 @@ pub fn get_yaw_between_points(from_point: &Point, to_point: &Point) -> f32 {
         None => 3.14,
     }
 }
+
+/// Возвращает угол поворота по оси Z для ориентации `orientation`.
+pub fn get_yaw(orientation: &geometry_msgs::Quaternion) -> f32 {
+    UnitQuaternion::from(orientation).euler_angles().2 as f32
+}
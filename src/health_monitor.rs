@@ -0,0 +1,186 @@
+use crate::{
+    backend::{self, backend},
+    common_ros_utils::is_topic_published,
+    events::Failure,
+    geometry::new_header,
+    msgs::diagnostic_msgs::{DiagnosticArray, DiagnosticStatus, KeyValue},
+    service_clients,
+    task_manager::{self, TaskType},
+    topic_subscribers, NODE_NAME,
+};
+use std::{collections::HashSet, sync::Once, thread};
+
+static START: Once = Once::new();
+
+/// Проверка топика: наибольшее время `timeout_seconds` (с) без сообщений и событие `Failure`, которое передаётся
+/// активному заданию, если сообщения перестали приходить. Отсутствие сообщений из критического топика публикуется как
+/// ошибка, из остальных - как предупреждение.
+struct TopicCheck {
+    topic_name: &'static str,
+    timeout_seconds: f64,
+    is_critical: bool,
+    new_failure: Option<fn() -> Failure>,
+}
+
+/// Возвращает проверки топиков, необходимых для задания `task_type`.
+fn get_topic_checks(task_type: TaskType) -> Vec<TopicCheck> {
+    let options = task_manager::options().health;
+    let mut checks = vec![
+        TopicCheck {
+            topic_name: topic_subscribers::DRONE_POSE_TOPIC,
+            timeout_seconds: task_manager::options().max_pose_age_seconds,
+            is_critical: true,
+            // Устаревшую позу обрабатывает защита, которую task_manager запускает для каждого задания
+            new_failure: None,
+        },
+        TopicCheck {
+            topic_name: topic_subscribers::BATTERY_STATE_TOPIC,
+            timeout_seconds: options.battery_timeout_seconds,
+            is_critical: true,
+            new_failure: Some(Failure::new_battery_state_lost),
+        },
+    ];
+    let vision_topic_name = match task_type {
+        TaskType::One => None,
+        TaskType::Two => Some(topic_subscribers::QR_CODES_TOPIC),
+        TaskType::Three => Some(topic_subscribers::LINE_POINTS_TOPIC),
+    };
+    if let Some(topic_name) = vision_topic_name {
+        checks.push(TopicCheck {
+            topic_name,
+            timeout_seconds: options.vision_timeout_seconds,
+            is_critical: false,
+            new_failure: None,
+        });
+    }
+    checks
+}
+
+fn new_status(level: u8, name: &str, message: String, values: Vec<KeyValue>) -> DiagnosticStatus {
+    DiagnosticStatus {
+        level,
+        name: format!("{}: {}", NODE_NAME, name),
+        message,
+        hardware_id: String::new(),
+        values,
+    }
+}
+
+/// Проверяет доступность сервисов и серверов actionlib, необходимых для задания `task_type`. Сервисы проверяются
+/// параллельно, поэтому проверка длится не дольше `options().health.service_probe_timeout_seconds`.
+fn get_dependency_statuses(task_type: TaskType) -> Vec<DiagnosticStatus> {
+    let timeout = std::time::Duration::from_secs_f64(
+        task_manager::options().health.service_probe_timeout_seconds,
+    );
+    let probes = service_clients::get_required_services(task_type)
+        .into_iter()
+        .map(|service_name| {
+            let probe = thread::spawn(move || {
+                rosrust::wait_for_service(service_name, Some(timeout)).is_ok()
+            });
+            (service_name, probe)
+        })
+        .collect::<Vec<_>>();
+    let mut statuses = Vec::new();
+    for (service_name, probe) in probes {
+        let (level, message) = if probe.join().unwrap_or(false) {
+            (DiagnosticStatus::OK, "Service is available")
+        } else {
+            (DiagnosticStatus::ERROR, "Service is unavailable")
+        };
+        statuses.push(new_status(
+            level,
+            service_name,
+            message.to_string(),
+            Vec::new(),
+        ));
+    }
+    for server_name in service_clients::get_required_action_servers(task_type) {
+        let (level, message) = if is_topic_published(&format!("{}/status", server_name)) {
+            (DiagnosticStatus::OK, "Action server is available")
+        } else {
+            (DiagnosticStatus::ERROR, "Action server is unavailable")
+        };
+        statuses.push(new_status(
+            level,
+            server_name,
+            message.to_string(),
+            Vec::new(),
+        ));
+    }
+    statuses
+}
+
+/// Запускает поток, который с частотой `options().health.rate` проверяет время с получения последнего сообщения из
+/// топиков и доступность сервисов и серверов actionlib, необходимых активному заданию, и публикует результат в
+/// `/diagnostics`. Когда сообщения из критического топика перестают приходить во время выполнения задания, передаёт
+/// заданию событие `Failure`. Поток один на узел: повторные вызовы ничего не делают.
+pub fn start() {
+    START.call_once(|| {
+        thread::spawn(|| {
+            let options = task_manager::options().health;
+            let rate = backend::rate(options.rate);
+            let mut stale_topic_names = HashSet::new();
+            let mut generation = task_manager::get_mission_generation();
+            while backend().is_ok() {
+                let task_type = match task_manager::get_active_task() {
+                    Some(task_type) => task_type,
+                    None => {
+                        rate.sleep();
+                        continue;
+                    }
+                };
+                // Устаревшие топики предыдущего задания не мешают сообщить о сбое в следующем
+                if generation != task_manager::get_mission_generation() {
+                    generation = task_manager::get_mission_generation();
+                    stale_topic_names.clear();
+                }
+                let mut statuses = Vec::new();
+                for check in get_topic_checks(task_type) {
+                    let age = topic_subscribers::get_message_age_seconds(check.topic_name);
+                    let (level, message) = match age {
+                        None => (
+                            DiagnosticStatus::WARN,
+                            "No messages received yet".to_string(),
+                        ),
+                        Some(age) if age > check.timeout_seconds => (
+                            if check.is_critical {
+                                DiagnosticStatus::ERROR
+                            } else {
+                                DiagnosticStatus::WARN
+                            },
+                            format!("No messages for {:.1} s", age),
+                        ),
+                        Some(_) => (DiagnosticStatus::OK, "Messages are received".to_string()),
+                    };
+                    statuses.push(new_status(
+                        level,
+                        check.topic_name,
+                        message,
+                        vec![KeyValue {
+                            key: "age_seconds".to_string(),
+                            value: age.map_or("-".to_string(), |age| format!("{:.2}", age)),
+                        }],
+                    ));
+                    // Событие передаётся один раз, пока сообщения снова не начнут приходить
+                    let is_stale = age.map_or(false, |age| age > check.timeout_seconds);
+                    if !is_stale {
+                        stale_topic_names.remove(check.topic_name);
+                    } else if stale_topic_names.insert(check.topic_name) {
+                        if let Some(new_failure) = check.new_failure {
+                            if task_manager::is_task_running() {
+                                task_manager::handle_failure(task_type, new_failure());
+                            }
+                        }
+                    }
+                }
+                statuses.extend(get_dependency_statuses(task_type));
+                backend().publish_diagnostics(DiagnosticArray {
+                    header: new_header(""),
+                    status: statuses,
+                });
+                rate.sleep();
+            }
+        });
+    });
+}
@@ -19,12 +19,29 @@ pub mod task3 {
     pub mod events;
     pub mod transitions;
 }
+pub mod backend;
+pub mod battery;
+pub mod commands;
 pub mod common_ros_utils;
 pub mod events;
+pub mod execute_task;
+pub mod fake_backend;
 pub mod geometry;
+pub mod health_monitor;
+pub mod metrics;
+pub mod mission_log;
+pub mod mission_state;
 pub mod msgs;
+pub mod overrides;
+pub mod readiness;
+pub mod replay;
+pub mod report;
+pub mod return_budget;
 pub mod rviz;
+pub mod scenario;
 pub mod service_clients;
+pub mod simulator;
+pub mod status;
 pub mod task_manager;
 pub mod topic_publishers;
 pub mod topic_subscribers;
@@ -0,0 +1,317 @@
+use crate::{
+    backend::{self, backend},
+    task_manager::{self, OPTIONS},
+};
+use lazy_static::lazy_static;
+use rosrust::{ros_err, ros_info, Time};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path, sync::Mutex};
+
+/// Границы интервалов (с) гистограмм задержки вызовов сервисов
+const SERVICE_LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0,
+];
+
+/// Границы интервалов (с) гистограммы длительности целей FastPlanner
+const GOAL_DURATION_BUCKETS: &[f64] = &[1.0, 2.0, 5.0, 10.0, 20.0, 30.0, 60.0, 120.0, 300.0];
+
+lazy_static! {
+    /// Метрики узла, накопленные с момента его запуска
+    static ref METRICS: Mutex<Metrics> = Mutex::new(Metrics {
+        state_seconds: BTreeMap::new(),
+        current_state: None,
+        spins_count: 0,
+        distance_flown: 0.0,
+        goal_outcomes: BTreeMap::new(),
+        goal_durations: Histogram::new(GOAL_DURATION_BUCKETS),
+        service_latencies: BTreeMap::new(),
+        service_errors: BTreeMap::new(),
+    });
+}
+
+/// Гистограмма наблюдаемых значений с фиксированными границами интервалов.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    bounds: &'static [f64],
+    /// Количество значений, не превышающих каждую из границ
+    cumulative_counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [f64]) -> Histogram {
+        Histogram {
+            bounds,
+            cumulative_counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Добавляет в гистограмму значение `value`.
+    pub fn observe(&mut self, value: f64) {
+        for (bound, count) in self.bounds.iter().zip(self.cumulative_counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "buckets": self
+                .bounds
+                .iter()
+                .zip(self.cumulative_counts.iter())
+                .map(|(bound, count)| json!({ "le": bound, "count": count }))
+                .collect::<Vec<_>>(),
+            "sum": self.sum,
+            "count": self.count,
+        })
+    }
+
+    /// Дописывает в `text` гистограмму `name` с метками `labels` в текстовом формате Prometheus.
+    fn write_prometheus(&self, text: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        for (bound, count) in self.bounds.iter().zip(self.cumulative_counts.iter()) {
+            let _ = writeln!(
+                text,
+                "{}_bucket{{{}{}le=\"{}\"}} {}",
+                name, labels, separator, bound, count
+            );
+        }
+        let _ = writeln!(
+            text,
+            "{}_bucket{{{}{}le=\"+Inf\"}} {}",
+            name, labels, separator, self.count
+        );
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", labels)
+        };
+        let _ = writeln!(text, "{}_sum{} {}", name, labels, self.sum);
+        let _ = writeln!(text, "{}_count{} {}", name, labels, self.count);
+    }
+}
+
+struct Metrics {
+    /// Время (с), проведённое в каждом состоянии конечного автомата каждого задания, без текущего состояния
+    state_seconds: BTreeMap<(u8, &'static str), f64>,
+    /// Задание, текущее состояние его конечного автомата и время перехода в него
+    current_state: Option<(u8, &'static str, Time)>,
+    spins_count: u64,
+    /// Расстояние (м), пройденное дроном по данным топика с позой дрона
+    distance_flown: f64,
+    /// Количество завершённых целей FastPlanner по итогу
+    goal_outcomes: BTreeMap<String, u64>,
+    goal_durations: Histogram,
+    service_latencies: BTreeMap<&'static str, Histogram>,
+    /// Количество вызовов каждого сервиса, завершившихся ошибкой
+    service_errors: BTreeMap<&'static str, u64>,
+}
+
+impl Metrics {
+    /// Возвращает время (с), проведённое в каждом состоянии, включая текущее состояние на момент `now`.
+    fn get_state_seconds(&self, now: Time) -> BTreeMap<(u8, &'static str), f64> {
+        let mut state_seconds = self.state_seconds.clone();
+        if let Some((task, name, entered_at)) = self.current_state {
+            *state_seconds.entry((task, name)).or_insert(0.0) += (now - entered_at).seconds();
+        }
+        state_seconds
+    }
+}
+
+/// Учитывает переход конечного автомата активного задания в состояние `name`.
+pub fn record_transition(name: &'static str) {
+    let task = match task_manager::get_active_task() {
+        Some(task_type) => task_type.number(),
+        None => return,
+    };
+    let now = backend().now();
+    let mut metrics = METRICS.lock().unwrap();
+    if let Some((previous_task, previous_name, entered_at)) = metrics.current_state {
+        if (previous_task, previous_name) == (task, name) {
+            return;
+        }
+        *metrics
+            .state_seconds
+            .entry((previous_task, previous_name))
+            .or_insert(0.0) += (now - entered_at).seconds();
+    }
+    metrics.current_state = Some((task, name, now));
+}
+
+/// Учитывает вращение дрона.
+pub fn record_spin() {
+    METRICS.lock().unwrap().spins_count += 1;
+}
+
+/// Учитывает перемещение дрона на `distance` м.
+pub fn add_distance_flown(distance: f64) {
+    METRICS.lock().unwrap().distance_flown += distance;
+}
+
+/// Учитывает завершение цели FastPlanner с итогом `outcome` через `duration_seconds` с после её отправки.
+pub fn record_goal(outcome: &str, duration_seconds: f64) {
+    let mut metrics = METRICS.lock().unwrap();
+    *metrics
+        .goal_outcomes
+        .entry(outcome.to_string())
+        .or_insert(0) += 1;
+    metrics.goal_durations.observe(duration_seconds);
+}
+
+/// Учитывает вызов сервиса `service`, длившийся `latency_seconds` с, включая повторные вызовы.
+pub fn record_service_call(service: &'static str, latency_seconds: f64, is_ok: bool) {
+    let mut metrics = METRICS.lock().unwrap();
+    metrics
+        .service_latencies
+        .entry(service)
+        .or_insert_with(|| Histogram::new(SERVICE_LATENCY_BUCKETS))
+        .observe(latency_seconds);
+    if !is_ok {
+        *metrics.service_errors.entry(service).or_insert(0) += 1;
+    }
+}
+
+/// Возвращает метрики узла в формате JSON.
+pub fn to_json() -> String {
+    let now = backend().now();
+    let metrics = METRICS.lock().unwrap();
+    let value = json!({
+        "state_seconds": metrics
+            .get_state_seconds(now)
+            .iter()
+            .map(|((task, name), seconds)| json!({ "task": task, "state": name, "seconds": seconds }))
+            .collect::<Vec<_>>(),
+        "spins_count": metrics.spins_count,
+        "distance_flown": metrics.distance_flown,
+        "goal_outcomes": metrics.goal_outcomes,
+        "goal_durations": metrics.goal_durations.to_json(),
+        "services": metrics
+            .service_latencies
+            .iter()
+            .map(|(service, latencies)| json!({
+                "service": service,
+                "latency_seconds": latencies.to_json(),
+                "errors_count": metrics.service_errors.get(service).copied().unwrap_or(0),
+            }))
+            .collect::<Vec<_>>(),
+    });
+    serde_json::to_string_pretty(&value).unwrap()
+}
+
+/// Возвращает метрики узла в текстовом формате Prometheus.
+pub fn to_prometheus() -> String {
+    let now = backend().now();
+    let metrics = METRICS.lock().unwrap();
+    let mut text = String::new();
+    let _ = writeln!(
+        text,
+        "# HELP task_manager_state_seconds_total Time spent in each state of the task state machine.\n\
+         # TYPE task_manager_state_seconds_total counter"
+    );
+    for ((task, name), seconds) in metrics.get_state_seconds(now) {
+        let _ = writeln!(
+            text,
+            "task_manager_state_seconds_total{{task=\"{}\",state=\"{}\"}} {}",
+            task, name, seconds
+        );
+    }
+    let _ = writeln!(
+        text,
+        "# HELP task_manager_spins_total Number of spins around the Z axis.\n\
+         # TYPE task_manager_spins_total counter\n\
+         task_manager_spins_total {}",
+        metrics.spins_count
+    );
+    let _ = writeln!(
+        text,
+        "# HELP task_manager_distance_flown_meters_total Path length of the drone.\n\
+         # TYPE task_manager_distance_flown_meters_total counter\n\
+         task_manager_distance_flown_meters_total {}",
+        metrics.distance_flown
+    );
+    let _ = writeln!(
+        text,
+        "# HELP task_manager_planner_goals_total Number of finished FastPlanner goals by outcome.\n\
+         # TYPE task_manager_planner_goals_total counter"
+    );
+    for (outcome, count) in &metrics.goal_outcomes {
+        let _ = writeln!(
+            text,
+            "task_manager_planner_goals_total{{outcome=\"{}\"}} {}",
+            outcome, count
+        );
+    }
+    let _ = writeln!(
+        text,
+        "# HELP task_manager_planner_goal_duration_seconds Time from sending a FastPlanner goal to its completion.\n\
+         # TYPE task_manager_planner_goal_duration_seconds histogram"
+    );
+    metrics.goal_durations.write_prometheus(
+        &mut text,
+        "task_manager_planner_goal_duration_seconds",
+        "",
+    );
+    let _ = writeln!(
+        text,
+        "# HELP task_manager_service_call_latency_seconds Latency of service calls including retries.\n\
+         # TYPE task_manager_service_call_latency_seconds histogram"
+    );
+    for (service, latencies) in &metrics.service_latencies {
+        latencies.write_prometheus(
+            &mut text,
+            "task_manager_service_call_latency_seconds",
+            &format!("service=\"{}\"", service),
+        );
+    }
+    let _ = writeln!(
+        text,
+        "# HELP task_manager_service_call_errors_total Number of failed service calls.\n\
+         # TYPE task_manager_service_call_errors_total counter"
+    );
+    for (service, count) in &metrics.service_errors {
+        let _ = writeln!(
+            text,
+            "task_manager_service_call_errors_total{{service=\"{}\"}} {}",
+            service, count
+        );
+    }
+    text
+}
+
+/// Записывает метрики узла в текстовом формате Prometheus в файл `path`. Файл заменяется целиком, чтобы сборщик
+/// метрик не прочитал его частично записанным.
+pub fn write_prometheus_file(path: &Path) -> std::io::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let temporary_path = path.with_extension("prom.tmp");
+    fs::write(&temporary_path, to_prometheus())?;
+    fs::rename(&temporary_path, path)
+}
+
+/// Запускает поток, который каждые `OPTIONS.metrics.period_seconds` с записывает метрики узла в файл
+/// `OPTIONS.metrics.file`. Если файл не задан, метрики в файл не записываются.
+pub fn start_exporting() {
+    let options = &OPTIONS.metrics;
+    if options.file.is_empty() || options.period_seconds <= 0.0 {
+        return;
+    }
+    ros_info!("Metrics are written to {}", options.file);
+    std::thread::spawn(move || {
+        let rate = backend::rate(1.0 / options.period_seconds);
+        let path = Path::new(&options.file);
+        while backend().is_ok() {
+            if let Err(error) = write_prometheus_file(path) {
+                ros_err!("Could not write metrics to {}: {}", path.display(), error);
+            }
+            rate.sleep();
+        }
+    });
+}
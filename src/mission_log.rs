@@ -0,0 +1,128 @@
+use crate::{
+    backend::{self, backend},
+    battery, mission_state,
+    task_manager::{self, TaskType},
+};
+use lazy_static::lazy_static;
+use rosrust::{ros_err, ros_info};
+use serde_json::{json, Value};
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+lazy_static! {
+    /// Журнал выполняемого задания
+    static ref MISSION_LOG: Mutex<Option<MissionLog>> = Mutex::new(None);
+}
+
+/// Журнал задания в формате JSON Lines: каждая строка - JSON-объект с временем записи `stamp` (с), видом записи
+/// `type` и её данными.
+struct MissionLog {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+/// Создаёт в каталоге `options().mission_log.directory` журнал `<mission_id>.jsonl` задания `task_type` и запускает
+/// поток, записывающий в него позу дрона и состояние аккумулятора с частотой `options().mission_log.pose_rate`. Если
+/// каталог не задан, журнал не ведётся.
+pub fn start(task_type: TaskType, mission_id: &str) {
+    let options = task_manager::options().mission_log.clone();
+    *MISSION_LOG.lock().unwrap() = None;
+    if options.directory.is_empty() {
+        return;
+    }
+    let path = Path::new(&options.directory).join(format!("{}.jsonl", mission_id));
+    let file = fs::create_dir_all(&options.directory).and_then(|_| File::create(&path));
+    match file {
+        Ok(file) => {
+            ros_info!("Mission log is written to {}", path.display());
+            *MISSION_LOG.lock().unwrap() = Some(MissionLog {
+                path,
+                writer: BufWriter::new(file),
+            });
+        }
+        Err(error) => {
+            ros_err!("Could not create mission log {}: {}", path.display(), error);
+            return;
+        }
+    }
+    record(
+        "start",
+        json!({ "task": task_type.number(), "mission_id": mission_id }),
+    );
+    if options.pose_rate <= 0.0 {
+        return;
+    }
+    let generation = task_manager::get_mission_generation();
+    std::thread::spawn(move || {
+        let active_backend = backend();
+        let rate = backend::rate(task_manager::options().mission_log.pose_rate);
+        // Журнал следующего задания ведёт его собственный поток
+        while active_backend.is_ok() && task_manager::get_mission_generation() == generation {
+            let pose = active_backend.get_current_drone_pose().pose;
+            record(
+                "pose",
+                json!({ "pose": mission_state::pose_to_json(&pose) }),
+            );
+            if let Some(status) = battery::get_battery_status() {
+                record(
+                    "battery",
+                    json!({
+                        "voltage": status.voltage,
+                        "percentage": status.percentage,
+                    }),
+                );
+            }
+            rate.sleep();
+        }
+    });
+}
+
+/// Возвращает путь к журналу выполняемого задания, если он ведётся.
+pub fn get_path() -> Option<PathBuf> {
+    MISSION_LOG
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|mission_log| mission_log.path.clone())
+}
+
+/// Загружает все записи журнала задания `path`.
+pub fn load(path: &Path) -> Result<Vec<Value>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|error| format!("Could not read {}: {}", path.display(), error))?;
+    let mut records = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(line)
+            .map_err(|error| format!("Line {} is invalid: {}", index + 1, error))?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Записывает в журнал задания запись вида `record_type` с данными `fields` - JSON-объектом. Если журнал не ведётся,
+/// ничего не делает.
+pub fn record(record_type: &str, fields: Value) {
+    let mut line = json!({
+        "stamp": backend().now().nanos() as f64 / 1e9,
+        "type": record_type,
+    });
+    if let (Some(line), Value::Object(fields)) = (line.as_object_mut(), fields) {
+        line.extend(fields);
+    }
+    let mut mission_log = MISSION_LOG.lock().unwrap();
+    let mission_log = match mission_log.as_mut() {
+        Some(mission_log) => mission_log,
+        None => return,
+    };
+    let result = writeln!(mission_log.writer, "{}", line).and_then(|_| mission_log.writer.flush());
+    if let Err(error) = result {
+        ros_err!("Could not write to mission log: {}", error);
+    }
+}
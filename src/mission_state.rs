@@ -0,0 +1,201 @@
+use crate::{
+    events,
+    msgs::{
+        detection_msgs::DetectedObject,
+        geometry_msgs::{Point, Pose, Quaternion, Vector3},
+        task_manager::{DetectedQr, GetMissionStateRes, HoleQrConnection, MissionEvent},
+    },
+    status, task1, task2, task_manager,
+};
+use serde_json::{json, Value};
+
+/// Возвращает полное состояние задания: статус дрона, точку старта, сроки задания, обнаруженные объекты со связями
+/// между ними и последние события.
+pub fn get_mission_state() -> GetMissionStateRes {
+    let (soft_deadline_seconds, max_seconds) = match task_manager::get_active_task() {
+        Some(task_type) => {
+            let options = task_manager::options();
+            let max_seconds = options.get_max_timer_minutes(task_type) as f64 * 60.0;
+            (
+                max_seconds - options.get_soft_deadline_seconds(task_type) as f64,
+                max_seconds,
+            )
+        }
+        None => (0.0, 0.0),
+    };
+    let mut hole_qr_connections: Vec<HoleQrConnection> =
+        task2::drone_state::DETECTED_HOLE_QR_CONNECTIONS
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&hole_id, &qr_index)| HoleQrConnection {
+                hole_id: hole_id as u32,
+                qr_index: qr_index as u32,
+            })
+            .collect();
+    hole_qr_connections.sort_by_key(|connection| connection.hole_id);
+    let holes = task2::drone_state::DETECTED_HOLES
+        .lock()
+        .unwrap()
+        .detected_objects
+        .clone();
+    let qr_codes = task2::drone_state::DETECTED_QR_CODES
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|qr| DetectedQr {
+            content: qr.content.clone(),
+            position: qr.position.clone(),
+            is_on_floor: qr.is_on_floor,
+        })
+        .collect();
+    let cubes = task1::drone_state::DETECTED_CUBES.lock().unwrap().clone();
+    GetMissionStateRes {
+        status: status::get_drone_status(),
+        home_pose: task_manager::get_home_pose(),
+        soft_deadline_seconds,
+        max_seconds,
+        cubes,
+        holes,
+        qr_codes,
+        hole_qr_connections,
+        recent_events: events::get_recent_events(),
+    }
+}
+
+/// Возвращает состояние задания `state` в формате JSON.
+pub fn to_json(state: &GetMissionStateRes) -> String {
+    let status = &state.status;
+    let value = json!({
+        "status": {
+            "state": status.state,
+            "is_error": status.is_error,
+            "task": status.task,
+            "mission_id": status.mission_id,
+            "state_name": status.state_name,
+            "state_seconds": status.state_seconds,
+            "is_paused": status.is_paused,
+            "mission_seconds": status.mission_seconds,
+            "remaining_seconds": status.remaining_seconds,
+            "battery_voltage": status.battery_voltage,
+            "battery_percentage": status.battery_percentage,
+            "battery_level": status.battery_level,
+            "cubes_found": status.cubes_found,
+            "cubes_count": status.cubes_count,
+            "passed_rooms": status.passed_rooms,
+            "last_failure": status.last_failure,
+        },
+        "home_pose": pose_to_json(&state.home_pose),
+        "soft_deadline_seconds": state.soft_deadline_seconds,
+        "max_seconds": state.max_seconds,
+        "cubes": state.cubes.iter().map(detected_object_to_json).collect::<Vec<_>>(),
+        "holes": state.holes.iter().map(detected_object_to_json).collect::<Vec<_>>(),
+        "qr_codes": state
+            .qr_codes
+            .iter()
+            .map(|qr| json!({
+                "content": qr.content,
+                "position": point_to_json(&qr.position),
+                "is_on_floor": qr.is_on_floor,
+            }))
+            .collect::<Vec<_>>(),
+        "hole_qr_connections": state
+            .hole_qr_connections
+            .iter()
+            .map(|connection| json!({
+                "hole_id": connection.hole_id,
+                "qr_index": connection.qr_index,
+            }))
+            .collect::<Vec<_>>(),
+        "recent_events": state.recent_events.iter().map(event_to_json).collect::<Vec<_>>(),
+    });
+    serde_json::to_string_pretty(&value).unwrap()
+}
+
+/// Возвращает событие `event` в формате JSON.
+pub fn event_to_json(event: &MissionEvent) -> Value {
+    json!({
+        "seq": event.seq,
+        "stamp": event.stamp.nanos() as f64 / 1e9,
+        "mission_id": event.mission_id,
+        "task": event.task,
+        "kind": event.kind,
+        "name": event.name,
+        "state": event.state,
+        "hole": detected_object_to_json(&event.hole),
+        "qr_content": event.qr_content,
+        "qr_position": point_to_json(&event.qr_position),
+        "cube_id": event.cube_id,
+        "cube_position": point_to_json(&event.cube_position),
+        "failure_cause": event.failure_cause,
+        "details": event.details,
+    })
+}
+
+/// Возвращает точку `point` в формате JSON.
+pub fn point_to_json(point: &Point) -> Value {
+    json!({ "x": point.x, "y": point.y, "z": point.z })
+}
+
+/// Возвращает позу `pose` в формате JSON.
+pub fn pose_to_json(pose: &Pose) -> Value {
+    json!({
+        "position": point_to_json(&pose.position),
+        "orientation": {
+            "x": pose.orientation.x,
+            "y": pose.orientation.y,
+            "z": pose.orientation.z,
+            "w": pose.orientation.w,
+        },
+    })
+}
+
+fn vector_to_json(vector: &Vector3) -> Value {
+    json!({ "x": vector.x, "y": vector.y, "z": vector.z })
+}
+
+/// Возвращает обнаруженный объект `object` в формате JSON.
+pub fn detected_object_to_json(object: &DetectedObject) -> Value {
+    json!({
+        "id": object.id,
+        "pose": pose_to_json(&object.pose),
+        "dimensions": vector_to_json(&object.dimensions),
+    })
+}
+
+/// Возвращает точку, записанную в формате JSON функцией `point_to_json`. Отсутствующие координаты равны 0.
+pub fn point_from_json(value: &Value) -> Point {
+    Point {
+        x: value["x"].as_f64().unwrap_or(0.0),
+        y: value["y"].as_f64().unwrap_or(0.0),
+        z: value["z"].as_f64().unwrap_or(0.0),
+    }
+}
+
+/// Возвращает позу, записанную в формате JSON функцией `pose_to_json`.
+pub fn pose_from_json(value: &Value) -> Pose {
+    let orientation = &value["orientation"];
+    Pose {
+        position: point_from_json(&value["position"]),
+        orientation: Quaternion {
+            x: orientation["x"].as_f64().unwrap_or(0.0),
+            y: orientation["y"].as_f64().unwrap_or(0.0),
+            z: orientation["z"].as_f64().unwrap_or(0.0),
+            w: orientation["w"].as_f64().unwrap_or(1.0),
+        },
+    }
+}
+
+/// Возвращает обнаруженный объект, записанный в формате JSON функцией `detected_object_to_json`.
+pub fn detected_object_from_json(value: &Value) -> DetectedObject {
+    let dimensions = point_from_json(&value["dimensions"]);
+    DetectedObject {
+        id: value["id"].as_i64().unwrap_or(0) as _,
+        pose: pose_from_json(&value["pose"]),
+        dimensions: Vector3 {
+            x: dimensions.x,
+            y: dimensions.y,
+            z: dimensions.z,
+        },
+    }
+}
@@ -6,7 +6,14 @@ rosrust::rosmsg_include!(
     geometry_msgs / PointStamped,
     detection_msgs / DetectedObjects,
     task_manager / DroneStatus,
+    task_manager / MissionEvent,
     task_manager / Start,
+    task_manager / Abort,
+    task_manager / GetReadiness,
+    task_manager / GetMissionState,
+    task_manager / GetMissionStateJson,
+    task_manager / GetMetrics,
+    task_manager / ExecuteTaskAction,
     visualization_msgs / Marker,
     std_srvs / Empty,
     std_srvs / SetBool,
@@ -24,6 +31,8 @@ rosrust::rosmsg_include!(
     qr_detector_msgs / QRCode,
     qr_detector_msgs / QRCodeArray,
     nav_msgs / Path,
-    mavros_msgs / PositionTarget
+    mavros_msgs / PositionTarget,
+    diagnostic_msgs / DiagnosticArray
 );
 rosrust_actionlib::action!(self; fast_planner_server: FastPlanner);
+rosrust_actionlib::action!(self; task_manager: ExecuteTask);
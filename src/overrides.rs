@@ -0,0 +1,268 @@
+use crate::{
+    msgs::{
+        diagnostic_msgs::KeyValue,
+        geometry_msgs::{Point, Pose, Quaternion},
+    },
+    task_manager::{TaskManagerOptions, TaskType, TimeoutPolicy},
+};
+use std::{collections::HashSet, str::FromStr};
+
+/// Ключи переопределений параметров, которые можно передать при запуске задания
+pub const OVERRIDE_KEYS: &[&str] = &[
+    "operating_altitude",
+    "low_altitude",
+    "cubes_count",
+    "max_timer_minutes",
+    "soft_deadline_seconds",
+    "soft_deadline_policy",
+    "hard_deadline_policy",
+    "stale_pose_policy",
+    "home_pose",
+];
+
+/// Параметры задания с применёнными переопределениями.
+#[derive(Clone)]
+pub struct MissionOverrides {
+    pub options: TaskManagerOptions,
+    /// Точка старта, заданная ключом `home_pose`
+    pub home_pose: Option<Pose>,
+}
+
+/// Применяет к параметрам `options` задания `task_type` переопределения `overrides` - пары "ключ - значение" с ключами
+/// из `OVERRIDE_KEYS`. Точка старта `home_pose` задаётся как "x,y,z" или "x,y,z,yaw" (м, рад). Возвращает ошибку с
+/// описанием первого некорректного переопределения.
+pub fn apply(
+    task_type: TaskType,
+    mut options: TaskManagerOptions,
+    overrides: &[KeyValue],
+) -> Result<MissionOverrides, String> {
+    let mut home_pose = None;
+    let mut keys = HashSet::new();
+    for KeyValue { key, value } in overrides {
+        if !keys.insert(key.as_str()) {
+            return Err(format!("Override \"{}\" is specified more than once", key));
+        }
+        let value = value.trim();
+        match key.as_str() {
+            "operating_altitude" => options.operating_altitude = parse_positive(key, value)?,
+            "low_altitude" => options.low_altitude = parse_positive(key, value)?,
+            "cubes_count" => {
+                if !matches!(task_type, TaskType::One) {
+                    return Err("Override \"cubes_count\" is only used by task 1".to_string());
+                }
+                options.task1.cubes_count = parse_positive(key, value)?;
+            }
+            "max_timer_minutes" => {
+                let max_timer_minutes = parse_positive(key, value)?;
+                match task_type {
+                    TaskType::One => options.task1.max_timer_minutes = max_timer_minutes,
+                    TaskType::Two => options.task2.max_timer_minutes = max_timer_minutes,
+                    TaskType::Three => options.task3.max_timer_minutes = max_timer_minutes,
+                }
+            }
+            "soft_deadline_seconds" => {
+                let soft_deadline_seconds: f32 = parse_number(key, value)?;
+                if soft_deadline_seconds < 0.0 {
+                    return Err(format!("Override \"{}\" must not be negative", key));
+                }
+                match task_type {
+                    TaskType::One => options.task1.soft_deadline_seconds = soft_deadline_seconds,
+                    TaskType::Two => options.task2.soft_deadline_seconds = soft_deadline_seconds,
+                    TaskType::Three => options.task3.soft_deadline_seconds = soft_deadline_seconds,
+                }
+            }
+            "soft_deadline_policy" => options.soft_deadline_policy = parse_policy(key, value)?,
+            "hard_deadline_policy" => options.hard_deadline_policy = parse_policy(key, value)?,
+            "stale_pose_policy" => options.stale_pose_policy = parse_policy(key, value)?,
+            "home_pose" => home_pose = Some(parse_pose(key, value)?),
+            _ => {
+                return Err(format!(
+                    "Unknown override \"{}\", expected one of: {}",
+                    key,
+                    OVERRIDE_KEYS.join(", ")
+                ))
+            }
+        }
+    }
+    if options.low_altitude > options.operating_altitude {
+        return Err(format!(
+            "low_altitude ({}) must not exceed operating_altitude ({})",
+            options.low_altitude, options.operating_altitude
+        ));
+    }
+    if options.get_soft_deadline_seconds(task_type)
+        >= options.get_max_timer_minutes(task_type) * 60.0
+    {
+        return Err(format!(
+            "soft_deadline_seconds ({}) must be less than max_timer_minutes ({}) in seconds",
+            options.get_soft_deadline_seconds(task_type),
+            options.get_max_timer_minutes(task_type)
+        ));
+    }
+    Ok(MissionOverrides { options, home_pose })
+}
+
+fn parse<T: FromStr>(key: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Override \"{}\" has invalid value \"{}\"", key, value))
+}
+
+/// Числовое значение переопределения.
+trait Number: FromStr + PartialOrd + Default {
+    fn is_finite(&self) -> bool;
+}
+
+impl Number for usize {
+    fn is_finite(&self) -> bool {
+        true
+    }
+}
+
+impl Number for f32 {
+    fn is_finite(&self) -> bool {
+        f32::is_finite(*self)
+    }
+}
+
+impl Number for f64 {
+    fn is_finite(&self) -> bool {
+        f64::is_finite(*self)
+    }
+}
+
+/// Возвращает конечное число: NaN и бесконечности считаются некорректными значениями.
+fn parse_number<T: Number>(key: &str, value: &str) -> Result<T, String> {
+    let number: T = parse(key, value)?;
+    if !number.is_finite() {
+        return Err(format!("Override \"{}\" must be a finite number", key));
+    }
+    Ok(number)
+}
+
+fn parse_positive<T: Number>(key: &str, value: &str) -> Result<T, String> {
+    let number: T = parse_number(key, value)?;
+    if number <= T::default() {
+        return Err(format!("Override \"{}\" must be positive", key));
+    }
+    Ok(number)
+}
+
+fn parse_policy(key: &str, value: &str) -> Result<TimeoutPolicy, String> {
+    TimeoutPolicy::from_name(value).ok_or_else(|| {
+        format!(
+            "Override \"{}\" has invalid value \"{}\", expected failure, land, return, hover or ignore",
+            key, value
+        )
+    })
+}
+
+/// Возвращает позу, заданную как "x,y,z" или "x,y,z,yaw".
+fn parse_pose(key: &str, value: &str) -> Result<Pose, String> {
+    let numbers = value
+        .split(',')
+        .map(|number| parse_number::<f64>(key, number.trim()))
+        .collect::<Result<Vec<_>, _>>()?;
+    if numbers.len() != 3 && numbers.len() != 4 {
+        return Err(format!(
+            "Override \"{}\" must be \"x,y,z\" or \"x,y,z,yaw\", got \"{}\"",
+            key, value
+        ));
+    }
+    let yaw = numbers.get(3).copied().unwrap_or(0.0);
+    Ok(Pose {
+        position: Point {
+            x: numbers[0],
+            y: numbers[1],
+            z: numbers[2],
+        },
+        orientation: Quaternion {
+            x: 0.0,
+            y: 0.0,
+            z: (yaw / 2.0).sin(),
+            w: (yaw / 2.0).cos(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::apply;
+    use crate::{
+        msgs::diagnostic_msgs::KeyValue,
+        task_manager::{TaskType, OPTIONS},
+    };
+
+    fn apply_one(key: &str, value: &str) -> Result<super::MissionOverrides, String> {
+        let overrides = [KeyValue {
+            key: key.to_string(),
+            value: value.to_string(),
+        }];
+        apply(TaskType::One, OPTIONS.clone(), &overrides)
+    }
+
+    #[test]
+    fn applies_valid_overrides() {
+        let mission_overrides = apply_one("operating_altitude", " 2.5 ").unwrap();
+        assert_eq!(mission_overrides.options.operating_altitude, 2.5);
+        assert_eq!(
+            apply_one("cubes_count", "2")
+                .unwrap()
+                .options
+                .task1
+                .cubes_count,
+            2
+        );
+        assert_eq!(
+            apply_one("max_timer_minutes", "7.5")
+                .unwrap()
+                .options
+                .task1
+                .max_timer_minutes,
+            7.5
+        );
+        let home_pose = apply_one("home_pose", "1,2,0.5")
+            .unwrap()
+            .home_pose
+            .unwrap();
+        assert_eq!(home_pose.position.x, 1.0);
+        assert_eq!(home_pose.position.y, 2.0);
+        assert_eq!(home_pose.position.z, 0.5);
+    }
+
+    #[test]
+    fn rejects_zero_and_negative_values() {
+        for value in ["0", "0.0", "-1"].iter() {
+            assert!(apply_one("operating_altitude", value).is_err(), "{}", value);
+            assert!(apply_one("max_timer_minutes", value).is_err(), "{}", value);
+        }
+        assert!(apply_one("cubes_count", "0").is_err());
+        assert!(apply_one("cubes_count", "-1").is_err());
+        assert!(apply_one("soft_deadline_seconds", "-1").is_err());
+        assert!(apply_one("soft_deadline_seconds", "0").is_ok());
+    }
+
+    #[test]
+    fn rejects_non_finite_values() {
+        for value in ["NaN", "nan", "inf", "-inf", "infinity"].iter() {
+            assert!(apply_one("operating_altitude", value).is_err(), "{}", value);
+            assert!(apply_one("low_altitude", value).is_err(), "{}", value);
+            assert!(apply_one("max_timer_minutes", value).is_err(), "{}", value);
+            assert!(
+                apply_one("soft_deadline_seconds", value).is_err(),
+                "{}",
+                value
+            );
+        }
+        // Переполнение f32 даёт бесконечность
+        assert!(apply_one("max_timer_minutes", "1e40").is_err());
+        assert!(apply_one("home_pose", "0,NaN,1").is_err());
+        assert!(apply_one("home_pose", "0,0,inf").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_key() {
+        let error = apply_one("cruise_altitude", "1.0").err().unwrap();
+        assert!(error.contains("Unknown override \"cruise_altitude\""));
+    }
+}
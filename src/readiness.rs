@@ -0,0 +1,76 @@
+use crate::{
+    common_ros_utils::is_topic_published, service_clients, task_manager::TaskType,
+    topic_subscribers,
+};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Отчёт о готовности узлов, необходимых для выполнения задания
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReadinessReport {
+    /// Недоступные сервисы
+    pub missing_services: Vec<String>,
+    /// Неопубликованные топики
+    pub missing_topics: Vec<String>,
+    /// Недоступные серверы actionlib
+    pub missing_action_servers: Vec<String>,
+}
+
+impl ReadinessReport {
+    /// Возвращает `true`, если доступно всё, что необходимо для выполнения задания.
+    pub fn is_ready(&self) -> bool {
+        self.missing_services.is_empty()
+            && self.missing_topics.is_empty()
+            && self.missing_action_servers.is_empty()
+    }
+}
+
+impl fmt::Display for ReadinessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_ready() {
+            return write!(f, "all dependencies are up");
+        }
+        let missing = [
+            ("services", &self.missing_services),
+            ("topics", &self.missing_topics),
+            ("action servers", &self.missing_action_servers),
+        ]
+        .iter()
+        .filter(|(_, names)| !names.is_empty())
+        .map(|(kind, names)| format!("missing {}: {}", kind, names.join(", ")))
+        .collect::<Vec<_>>();
+        write!(f, "{}", missing.join("; "))
+    }
+}
+
+/// Проверяет, доступны ли сервисы, топики и серверы actionlib, необходимые для выполнения задания `task_type`.
+/// Сервисы ожидаются не дольше `timeout_seconds` (с) в сумме, топики и серверы actionlib проверяются однократно.
+pub fn check(task_type: TaskType, timeout_seconds: f64) -> ReadinessReport {
+    let deadline = Instant::now() + Duration::from_secs_f64(timeout_seconds.max(0.0));
+    let missing_services = service_clients::get_required_services(task_type)
+        .into_iter()
+        .filter(|service| {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            rosrust::wait_for_service(service, Some(timeout)).is_err()
+        })
+        .map(String::from)
+        .collect();
+    let missing_topics = topic_subscribers::get_required_topics(task_type)
+        .into_iter()
+        .filter(|topic| !is_topic_published(topic))
+        .map(String::from)
+        .collect();
+    // Сервер actionlib публикует статус своих целей в топик <название сервера>/status
+    let missing_action_servers = service_clients::get_required_action_servers(task_type)
+        .into_iter()
+        .filter(|server| !is_topic_published(&format!("{}/status", server)))
+        .map(String::from)
+        .collect();
+    ReadinessReport {
+        missing_services,
+        missing_topics,
+        missing_action_servers,
+    }
+}
@@ -0,0 +1,765 @@
+use crate::{
+    backend::{self, *},
+    commands::AbortMode,
+    events::Failure,
+    geometry, mission_log,
+    mission_state::{detected_object_from_json, point_from_json, pose_from_json},
+    msgs::{
+        detection_msgs::{DetectedObject, DetectedObjects},
+        diagnostic_msgs::DiagnosticArray,
+        geometry_msgs::{Point, PointStamped, Pose, PoseStamped},
+        mavros_msgs::PositionTarget,
+        nav_msgs::Path as LinePath,
+        nodes_monitor_msgs::Status,
+        qr_detector_msgs::{QRCode, QRCodeArray},
+        std_msgs::Header,
+        task_manager::{DroneStatus, MissionEvent},
+    },
+    service_clients::{self, ServiceError},
+    task_manager::{self, TaskType},
+};
+use rosrust::{Duration, Time};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+};
+
+/// Вольтаж аккумулятора при воспроизведении: разряд аккумулятора воспроизводится событиями `Failure` из журнала задания
+const REPLAY_BATTERY_VOLTAGE: f32 = 12.6;
+
+/// Название сервиса в событиях `Failure`, воспроизводимых из журнала задания
+const REPLAY_SERVICE_NAME: &str = "replay";
+
+/// Параметры воспроизведения журнала задания.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayOptions {
+    /// Во сколько раз время воспроизведения идёт быстрее реального
+    pub time_scale: f64,
+    /// Шаг воспроизведения (с)
+    pub step: f64,
+    /// Время (с), в течение которого воспроизведение продолжается после последней записи журнала
+    pub tail_seconds: f64,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        ReplayOptions {
+            time_scale: 10.0,
+            step: 0.05,
+            tail_seconds: 5.0,
+        }
+    }
+}
+
+/// Журнал задания, загруженный для воспроизведения.
+pub struct MissionRecording {
+    pub task_type: TaskType,
+    /// Точка старта задания
+    pub home_pose: Option<Pose>,
+    /// Записи журнала, упорядоченные по времени. Время записей (с) отсчитывается от начала задания.
+    records: Vec<(f64, Value)>,
+}
+
+impl MissionRecording {
+    /// Загружает журнал задания, записанный модулем `mission_log`.
+    pub fn load(path: &Path) -> Result<MissionRecording, String> {
+        let lines = mission_log::load(path)?;
+        let start = lines
+            .iter()
+            .find(|record| record["type"] == "start")
+            .ok_or_else(|| "Mission log has no start record".to_string())?;
+        let task_number = start["task"].as_u64().unwrap_or(0) as u32;
+        let task_type = TaskType::from_number(task_number)?;
+        let start_stamp = start["stamp"].as_f64().unwrap_or(0.0);
+        let home_pose = lines
+            .iter()
+            .find(|record| record["type"] == "home_pose")
+            .map(|record| pose_from_json(&record["pose"]));
+        let mut records: Vec<(f64, Value)> = lines
+            .into_iter()
+            .map(|record| {
+                (
+                    record["stamp"].as_f64().unwrap_or(0.0) - start_stamp,
+                    record,
+                )
+            })
+            // Записи с некорректным временем не участвуют в воспроизведении
+            .filter(|(time, _)| time.is_finite() && *time >= 0.0)
+            .collect();
+        records.sort_by(|x, y| x.0.total_cmp(&y.0));
+        Ok(MissionRecording {
+            task_type,
+            home_pose,
+            records,
+        })
+    }
+
+    /// Возвращает названия последовательно сменявших друг друга состояний конечного автомата задания.
+    pub fn get_transitions(&self) -> Vec<String> {
+        let states = self
+            .records
+            .iter()
+            .filter(|(_, record)| record["type"] == "transition")
+            .map(|(_, record)| record["state"].as_str().unwrap_or_default().to_string());
+        get_distinct_transitions(states)
+    }
+
+    /// Возвращает названия всех записанных событий.
+    pub fn get_events(&self) -> Vec<String> {
+        self.records
+            .iter()
+            .filter(|(_, record)| record["type"] == "event")
+            .map(|(_, record)| record["name"].as_str().unwrap_or_default().to_string())
+            .collect()
+    }
+}
+
+/// Результат воспроизведения журнала задания.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayResult {
+    /// Состояния конечного автомата задания, записанные в журнал
+    pub expected_transitions: Vec<String>,
+    /// Состояния конечного автомата задания при воспроизведении
+    pub actual_transitions: Vec<String>,
+    /// События, записанные в журнал
+    pub expected_events: Vec<String>,
+    /// События при воспроизведении
+    pub actual_events: Vec<String>,
+}
+
+impl ReplayResult {
+    /// Возвращает `true`, если при воспроизведении конечный автомат задания прошёл те же состояния, что и в журнале.
+    pub fn is_matching(&self) -> bool {
+        self.expected_transitions == self.actual_transitions
+    }
+}
+
+/// Возвращает состояния `states` без повторов подряд и без начального `WaitingForCommand`.
+fn get_distinct_transitions(states: impl Iterator<Item = String>) -> Vec<String> {
+    let mut transitions: Vec<String> = Vec::new();
+    for state in states {
+        if transitions.last() != Some(&state)
+            && !(transitions.is_empty() && state == "WaitingForCommand")
+        {
+            transitions.push(state);
+        }
+    }
+    transitions
+}
+
+/// Действие, которое выполняется после применения записи журнала вне блокировки состояния воспроизведения.
+enum ReplayAction {
+    GoalDone(GoalDoneCallback, GoalOutcome),
+    QrCodes(Vec<Arc<dyn Fn(QRCodeArray) + Send + Sync>>, QRCodeArray),
+    Failure(String),
+    Abort(AbortMode),
+    Pause,
+    Resume,
+}
+
+struct ReplayState {
+    time: f64,
+    is_stopped: bool,
+    next_record: usize,
+    /// Название последнего воспроизведённого события из журнала
+    last_event_name: String,
+    drone_pose: PoseStamped,
+    /// Последний записанный результат каждого запроса к восприятию
+    perception: HashMap<String, Value>,
+    pending_goals: Vec<(Pose, Option<GoalDoneCallback>)>,
+    qr_codes_callbacks: Vec<Arc<dyn Fn(QRCodeArray) + Send + Sync>>,
+    states: Vec<String>,
+    events: Vec<String>,
+}
+
+/// Реализация `DroneBackend`, воспроизводящая журнал задания: поза дрона, ответы pos_collector, QR-коды,
+/// преобразования СК и завершение целей FastPlanner берутся из журнала в моменты времени, в которые они были записаны.
+/// Блокирующие вызовы сервисов длятся столько же, сколько при записи. События `Failure`, которые не могут возникнуть
+/// при воспроизведении сами по себе (например, разряд аккумулятора), и команды оператора передаются заданию так же, как
+/// при записи. Сроки задания наступают по часам воспроизведения.
+pub struct ReplayBackend {
+    recording: MissionRecording,
+    options: ReplayOptions,
+    state: Mutex<ReplayState>,
+    tick: Condvar,
+}
+
+impl ReplayBackend {
+    /// Создаёт реализацию, воспроизводящую журнал `recording`, и запускает поток воспроизведения.
+    pub fn new(recording: MissionRecording, options: ReplayOptions) -> Arc<ReplayBackend> {
+        let replay_backend = Arc::new(ReplayBackend {
+            state: Mutex::new(ReplayState {
+                time: 0.0,
+                is_stopped: false,
+                next_record: 0,
+                last_event_name: String::new(),
+                drone_pose: PoseStamped {
+                    header: Header {
+                        seq: 0,
+                        stamp: Time::default(),
+                        frame_id: "map".to_string(),
+                    },
+                    pose: recording.home_pose.clone().unwrap_or_else(|| Pose {
+                        position: geometry::default_point(),
+                        orientation: geometry::default_quaternion(),
+                    }),
+                },
+                perception: HashMap::new(),
+                pending_goals: Vec::new(),
+                qr_codes_callbacks: Vec::new(),
+                states: Vec::new(),
+                events: Vec::new(),
+            }),
+            recording,
+            options,
+            tick: Condvar::new(),
+        });
+        let stepper = replay_backend.clone();
+        thread::Builder::new()
+            .name("replay".to_string())
+            .spawn(move || stepper.run())
+            .unwrap();
+        replay_backend
+    }
+
+    /// Возвращает время (с) окончания воспроизведения.
+    pub fn get_end_time(&self) -> f64 {
+        self.recording
+            .records
+            .last()
+            .map(|(time, _)| *time)
+            .unwrap_or(0.0)
+            + self.options.tail_seconds
+    }
+
+    /// Останавливает воспроизведение.
+    pub fn stop(&self) {
+        self.lock().is_stopped = true;
+        self.tick.notify_all();
+    }
+
+    /// Возвращает результат воспроизведения.
+    pub fn get_result(&self) -> ReplayResult {
+        let state = self.lock();
+        ReplayResult {
+            expected_transitions: self.recording.get_transitions(),
+            actual_transitions: get_distinct_transitions(state.states.iter().cloned()),
+            expected_events: self.recording.get_events(),
+            actual_events: state.events.clone(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<ReplayState> {
+        self.state.lock().unwrap()
+    }
+
+    fn wait_until<F: Fn(&ReplayState) -> bool>(&self, condition: F) {
+        let mut state = self.lock();
+        while !state.is_stopped && !condition(&state) {
+            state = self.tick.wait(state).unwrap();
+        }
+    }
+
+    /// Блокирует вызывающий поток до момента, в который при записи завершился очередной вызов сервиса `service`.
+    fn wait_for_service_call(&self, service: &str) {
+        let now = self.lock().time;
+        let done_at = self
+            .recording
+            .records
+            .iter()
+            .find(|(time, record)| {
+                *time >= now && record["type"] == "service_call" && record["service"] == service
+            })
+            .map(|(time, _)| *time);
+        if let Some(done_at) = done_at {
+            self.wait_until(|state| state.time >= done_at);
+        }
+    }
+
+    fn get_perception(&self, method: &str) -> Value {
+        self.lock()
+            .perception
+            .get(method)
+            .cloned()
+            .unwrap_or(Value::Null)
+    }
+
+    /// Возвращает записанный результат преобразования `method` из СК `source_frame_id` в `target_frame_id` для
+    /// исходных данных `is_source`: последний к текущему моменту или, если его ещё нет, ближайший следующий, так как
+    /// преобразование записывается после вызвавшего его сообщения. Результат `Value::Null` означает, что
+    /// преобразование было невозможно. Если преобразование не записано, возвращает `None`.
+    fn find_transform<F: Fn(&Value) -> bool>(
+        &self,
+        method: &str,
+        source_frame_id: &str,
+        target_frame_id: &str,
+        is_source: F,
+    ) -> Option<Value> {
+        let now = self.lock().time;
+        let records: Vec<&(f64, Value)> = self
+            .recording
+            .records
+            .iter()
+            .filter(|(_, record)| {
+                record["type"] == "transform"
+                    && record["method"] == method
+                    && record["source_frame_id"] == source_frame_id
+                    && record["target_frame_id"] == target_frame_id
+                    && is_source(&record["source"])
+            })
+            .collect();
+        records
+            .iter()
+            .rev()
+            .find(|(time, _)| *time <= now)
+            .or_else(|| records.first())
+            .map(|(_, record)| record["result"].clone())
+    }
+
+    fn run(&self) {
+        let wall_step =
+            std::time::Duration::from_secs_f64(self.options.step / self.options.time_scale);
+        loop {
+            thread::sleep(wall_step);
+            let actions = {
+                let mut state = self.lock();
+                if state.is_stopped {
+                    break;
+                }
+                state.time += self.options.step;
+                self.apply_records(&mut state)
+            };
+            self.tick.notify_all();
+            // Действия выполняются в отдельных потоках, так как они могут обращаться к реализации и блокироваться до
+            // следующих шагов воспроизведения.
+            for action in actions {
+                thread::spawn(move || run_action(action));
+            }
+        }
+    }
+
+    /// Применяет все записи журнала, время которых наступило, и возвращает действия, которые необходимо выполнить.
+    fn apply_records(&self, state: &mut ReplayState) -> Vec<ReplayAction> {
+        let mut actions = Vec::new();
+        while let Some((time, record)) = self.recording.records.get(state.next_record) {
+            if *time > state.time {
+                break;
+            }
+            state.next_record += 1;
+            match record["type"].as_str().unwrap_or_default() {
+                "pose" => {
+                    state.drone_pose.header.stamp = Time::from_nanos((time * 1e9) as i64);
+                    state.drone_pose.pose = pose_from_json(&record["pose"]);
+                }
+                "perception" => {
+                    let method = record["method"].as_str().unwrap_or_default().to_string();
+                    state.perception.insert(method, record["result"].clone());
+                }
+                "qr_codes" => {
+                    let mut qr_codes = QRCodeArray::default();
+                    qr_codes.qr_codes = record["qr_codes"]
+                        .as_array()
+                        .map(|qr_codes| qr_codes.iter().map(qr_code_from_json).collect())
+                        .unwrap_or_default();
+                    actions.push(ReplayAction::QrCodes(
+                        state.qr_codes_callbacks.clone(),
+                        qr_codes,
+                    ));
+                }
+                "goal_done" => {
+                    let pose = pose_from_json(&record["pose"]);
+                    let index = state.pending_goals.iter().position(|(goal, _)| {
+                        geometry::get_distance_between_points(&goal.position, &pose.position) < 1e-3
+                    });
+                    if let Some(index) = index {
+                        if let (_, Some(on_done)) = state.pending_goals.remove(index) {
+                            let outcome = GoalOutcome::from_state_name(
+                                record["state"].as_str().unwrap_or_default(),
+                            );
+                            actions.push(ReplayAction::GoalDone(on_done, outcome));
+                        }
+                    }
+                }
+                "failure" => actions.push(ReplayAction::Failure(
+                    record["cause"].as_str().unwrap_or_default().to_string(),
+                )),
+                "event" => {
+                    let name = record["name"].as_str().unwrap_or_default();
+                    // Прерывание по сроку задания наступает по часам воспроизведения и не повторяется из журнала.
+                    let is_deadline_abort = state.last_event_name == "SoftDeadlineReached"
+                        || state.last_event_name == "Timeout";
+                    if name.starts_with("Abort") && !is_deadline_abort {
+                        let mode = match record["details"].as_str().unwrap_or_default() {
+                            "Return" => AbortMode::Return,
+                            "Hover" => AbortMode::Hover,
+                            _ => AbortMode::Land,
+                        };
+                        actions.push(ReplayAction::Abort(mode));
+                    } else if name == "Pause" {
+                        actions.push(ReplayAction::Pause);
+                    } else if name == "Resume" {
+                        actions.push(ReplayAction::Resume);
+                    }
+                    state.last_event_name = name.to_string();
+                }
+                _ => {}
+            }
+        }
+        actions
+    }
+}
+
+fn qr_code_from_json(value: &Value) -> QRCode {
+    let position = point_from_json(&value["position"]);
+    let mut qr = QRCode::default();
+    qr.header.frame_id = value["frame_id"].as_str().unwrap_or_default().to_string();
+    qr.data = value["data"].as_str().unwrap_or_default().to_string();
+    qr.position.x = position.x;
+    qr.position.y = position.y;
+    qr.position.z = position.z;
+    qr
+}
+
+fn run_action(action: ReplayAction) {
+    let task_type = match task_manager::get_active_task() {
+        Some(task_type) => task_type,
+        None => return,
+    };
+    let result = match action {
+        ReplayAction::GoalDone(on_done, outcome) => {
+            on_done(outcome);
+            Ok(())
+        }
+        ReplayAction::QrCodes(callbacks, qr_codes) => {
+            for callback in callbacks {
+                callback(qr_codes.clone());
+            }
+            Ok(())
+        }
+        ReplayAction::Failure(cause) => {
+            if let Some(failure) = new_failure(&cause) {
+                task_manager::handle_failure(task_type, failure);
+            }
+            Ok(())
+        }
+        ReplayAction::Abort(mode) => task_manager::abort_task(mode),
+        ReplayAction::Pause => task_manager::pause_task(),
+        ReplayAction::Resume => task_manager::resume_task(),
+    };
+    if let Err(error) = result {
+        rosrust::ros_warn!("Could not replay the command: {}", error);
+    }
+}
+
+/// Возвращает событие `Failure` с причиной `cause`, если оно не может возникнуть при воспроизведении само по себе.
+/// События, связанные со сроками задания, наступают по часам воспроизведения.
+fn new_failure(cause: &str) -> Option<Failure> {
+    match cause {
+        "LowVoltageDetected" => Some(Failure::new_low_voltage_detected()),
+        "NotEnoughBatteryToReturn" => Some(Failure::new_not_enough_battery_to_return()),
+        "LocalizationLost" => Some(Failure::new_localization_lost()),
+        "BatteryStateLost" => Some(Failure::new_battery_state_lost()),
+        "ServiceUnavailable" => Some(Failure::new_service_failure(&ServiceError::Unavailable {
+            service: REPLAY_SERVICE_NAME,
+            message: String::new(),
+        })),
+        "ServiceCallRejected" => Some(Failure::new_service_failure(&ServiceError::Rejected {
+            service: REPLAY_SERVICE_NAME,
+            message: String::new(),
+        })),
+        "ServiceCallTimedOut" => Some(Failure::new_service_failure(&ServiceError::Timeout {
+            service: REPLAY_SERVICE_NAME,
+        })),
+        _ => None,
+    }
+}
+
+impl Planner for ReplayBackend {
+    fn send_goal(&self, pose: Pose, on_done: Option<GoalDoneCallback>) {
+        self.lock().pending_goals.push((pose, on_done));
+    }
+
+    fn cancel_all_goals(&self) {
+        self.lock().pending_goals.clear();
+    }
+
+    fn start_exploration(&self) {}
+
+    fn pause_exploration(&self) {}
+
+    fn reset_exploration(&self) {}
+
+    fn add_virtual_wall(&self, _: DetectedObject) {}
+
+    fn set_virtual_walls_enabled(&self, _: bool) {}
+}
+
+impl Perception for ReplayBackend {
+    fn get_closest_hole(&self) -> Option<DetectedObject> {
+        match self.get_perception("get_closest_hole") {
+            Value::Null => None,
+            hole => Some(detected_object_from_json(&hole)),
+        }
+    }
+
+    fn get_holes(&self) -> DetectedObjects {
+        DetectedObjects {
+            header: geometry::new_header("map"),
+            detected_objects: detected_objects_from_json(&self.get_perception("get_holes")),
+        }
+    }
+
+    fn count_holes(&self) -> usize {
+        self.get_perception("count_holes").as_u64().unwrap_or(0) as usize
+    }
+
+    fn get_cubes(&self) -> DetectedObjects {
+        DetectedObjects {
+            header: geometry::new_header("map"),
+            detected_objects: detected_objects_from_json(&self.get_perception("get_cubes")),
+        }
+    }
+
+    fn count_cubes(&self) -> usize {
+        self.get_perception("count_cubes").as_u64().unwrap_or(0) as usize
+    }
+
+    fn subscribe_qr_codes(&self, callback: QrCodesCallback) {
+        self.lock().qr_codes_callbacks.push(Arc::from(callback));
+    }
+
+    fn subscribe_line_points(&self, _: LinePointsCallback) {}
+}
+
+fn detected_objects_from_json(value: &Value) -> Vec<DetectedObject> {
+    value
+        .as_array()
+        .map(|objects| objects.iter().map(detected_object_from_json).collect())
+        .unwrap_or_default()
+}
+
+impl Motion for ReplayBackend {
+    fn takeoff(&self, _: f32) {
+        self.wait_for_service_call(service_clients::TAKEOFF_SERVICE);
+    }
+
+    fn land(&self) {
+        self.wait_for_service_call(service_clients::LAND_SERVICE);
+    }
+
+    fn spin(&self, _: i32, _: f32, _: f32) {}
+
+    fn spin_and_wait(&self, _: i32, _: f32, _: f32) {
+        self.wait_for_service_call(service_clients::SPIN_SERVICE);
+    }
+
+    fn stop_spinning(&self) {}
+
+    fn send_setpoint(&self, _: PositionTarget) {}
+}
+
+impl Transforms for ReplayBackend {
+    fn transform_point(
+        &self,
+        mut point: PointStamped,
+        target_frame_id: &str,
+    ) -> Option<PointStamped> {
+        let source = point.point.clone();
+        let result = self.find_transform(
+            "transform_point",
+            &point.header.frame_id,
+            target_frame_id,
+            |recorded| is_same_point(&point_from_json(recorded), &source),
+        );
+        match result {
+            Some(Value::Null) => return None,
+            Some(result) => point.point = point_from_json(&result),
+            None => {}
+        }
+        point.header.frame_id = target_frame_id.to_string();
+        Some(point)
+    }
+
+    fn transform_pose(&self, mut pose: PoseStamped, target_frame_id: &str) -> Option<PoseStamped> {
+        let source = pose.pose.position.clone();
+        let result = self.find_transform(
+            "transform_pose",
+            &pose.header.frame_id,
+            target_frame_id,
+            |recorded| is_same_point(&point_from_json(&recorded["position"]), &source),
+        );
+        match result {
+            Some(Value::Null) => return None,
+            Some(result) => pose.pose = pose_from_json(&result),
+            None => {}
+        }
+        pose.header.frame_id = target_frame_id.to_string();
+        Some(pose)
+    }
+}
+
+fn is_same_point(x: &Point, y: &Point) -> bool {
+    geometry::get_distance_between_points(x, y) < 1e-6
+}
+
+impl Telemetry for ReplayBackend {
+    fn get_current_drone_pose(&self) -> PoseStamped {
+        self.lock().drone_pose.clone()
+    }
+
+    fn get_current_battery_voltage(&self) -> f32 {
+        REPLAY_BATTERY_VOLTAGE
+    }
+}
+
+impl Reporter for ReplayBackend {
+    fn publish_event(&self, event_name: &str) {
+        self.lock().events.push(event_name.to_string());
+    }
+
+    fn publish_mission_event(&self, _: MissionEvent) {}
+
+    fn publish_status(&self, status: DroneStatus) {
+        self.lock().states.push(status.state_name);
+    }
+
+    fn publish_nodes_monitor_status(&self, _: Status) {}
+
+    fn publish_diagnostics(&self, _: DiagnosticArray) {}
+
+    fn publish_detected_cube(&self, _: Point) {}
+
+    fn publish_global_path(&self, _: LinePath) {}
+}
+
+impl Clock for ReplayBackend {
+    fn now(&self) -> Time {
+        Time::from_nanos((self.lock().time * 1e9) as i64)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let until = self.lock().time + duration.seconds();
+        self.wait_until(|state| state.time >= until);
+    }
+
+    fn is_ok(&self) -> bool {
+        !self.lock().is_stopped
+    }
+}
+
+impl DroneBackend for ReplayBackend {
+    fn init(&self, _: TaskType) {}
+}
+
+/// Воспроизводит журнал задания `recording` через конечный автомат задания и возвращает состояния, которые он прошёл
+/// при записи и при воспроизведении. `ReplayBackend` устанавливается активной реализацией `backend::DroneBackend` и
+/// останавливается по завершении. Журнал воспроизводимого задания не ведётся.
+pub fn replay_mission(recording: MissionRecording, options: ReplayOptions) -> ReplayResult {
+    let task_type = recording.task_type;
+    let home_pose = recording.home_pose.clone();
+    let replay_backend = ReplayBackend::new(recording, options);
+    backend::set_backend(replay_backend.clone());
+    let mut task_options = task_manager::OPTIONS.clone();
+    task_options.mission_log.directory = String::new();
+    task_manager::start_task_with_options(task_type, home_pose, task_options);
+    let end_time = replay_backend.get_end_time();
+    replay_backend.wait_until(|state| state.time >= end_time);
+    let result = replay_backend.get_result();
+    replay_backend.stop();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{replay_mission, MissionRecording, ReplayOptions};
+    use crate::{
+        events::Failure,
+        fake_backend::testing,
+        geometry, mission_log,
+        msgs::{detection_msgs::DetectedObject, geometry_msgs::Pose},
+        task_manager::{self, TaskType},
+    };
+    use std::fs;
+
+    fn new_pose(x: f64, y: f64, z: f64) -> Pose {
+        Pose {
+            position: geometry::new_point(x, y, z),
+            orientation: geometry::default_quaternion(),
+        }
+    }
+
+    fn new_object(id: i64, x: f64, y: f64, z: f64) -> DetectedObject {
+        DetectedObject {
+            id: id as _,
+            pose: new_pose(x, y, z),
+            ..DetectedObject::default()
+        }
+    }
+
+    #[test]
+    fn replays_mission_recorded_on_fake_backend() {
+        let directory =
+            std::env::temp_dir().join(format!("task_manager_replay_{}", std::process::id()));
+        let mut options = task_manager::OPTIONS.clone();
+        options.mission_log.directory = directory.to_string_lossy().into_owned();
+        let mission = testing::start_with_options(TaskType::One, new_pose(0.0, 0.0, 0.0), options);
+        mission.wait_for_state("LookingForEntry", 5);
+
+        mission.fake.set_drone_pose(new_pose(2.9, 0.0, 1.5));
+        mission.fake.add_hole(new_object(0, 3.0, 0.0, 1.5));
+        mission.wait_for_state("FlyingInside", 5);
+        mission.wait_until("entry goal", 5, |fake| fake.pending_goals().len() == 1);
+        mission.fake.complete_goals();
+        mission.wait_for_state("Exploring", 5);
+
+        mission.fake.add_cube(new_object(0, 5.0, 1.5, 0.1));
+        mission.wait_until("published cube", 5, |fake| fake.detected_cubes().len() == 1);
+
+        task_manager::handle_failure(TaskType::One, Failure::new_low_voltage_detected());
+        mission.wait_for_state("ReturningToStartPoint", 5);
+        mission.wait_until("return goal", 5, |fake| fake.pending_goals().len() == 1);
+        mission.fake.complete_goals();
+        mission.wait_for_state("Landing", 5);
+
+        let path = mission_log::get_path().expect("Mission log is not written");
+        mission.fake.shutdown();
+        let recording = MissionRecording::load(&path).unwrap();
+        assert_eq!(
+            recording.get_transitions(),
+            vec![
+                "LookingForEntry",
+                "FlyingInside",
+                "Exploring",
+                "ReturningToStartPoint",
+                "Landing",
+            ]
+        );
+
+        let result = replay_mission(recording, ReplayOptions::default());
+        fs::remove_dir_all(&directory).ok();
+        assert_eq!(result.actual_transitions, result.expected_transitions);
+        assert_eq!(result.actual_events, result.expected_events);
+        assert!(result.is_matching());
+    }
+
+    #[test]
+    fn skips_records_with_non_finite_time() {
+        let path = std::env::temp_dir().join(format!(
+            "task_manager_replay_stamps_{}.jsonl",
+            std::process::id()
+        ));
+        // Время последней записи от начала задания выходит за пределы f64
+        let lines = [
+            r#"{"stamp": -1e308, "type": "start", "task": 2, "mission_id": "mission"}"#,
+            r#"{"stamp": 0.0, "type": "transition", "state": "Exploring"}"#,
+            r#"{"stamp": 1e308, "type": "transition", "state": "Landing"}"#,
+        ];
+        fs::write(&path, lines.join("\n")).unwrap();
+        let recording = MissionRecording::load(&path);
+        fs::remove_file(&path).ok();
+        assert_eq!(recording.unwrap().get_transitions(), vec!["Exploring"]);
+    }
+}
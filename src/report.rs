@@ -0,0 +1,549 @@
+use crate::{
+    backend::backend,
+    geometry, mission_log,
+    mission_state::{point_from_json, point_to_json, pose_from_json},
+    msgs::{geometry_msgs::Point, task_manager::MissionEvent},
+    task_manager,
+};
+use rosrust::{ros_err, ros_info, Duration};
+use serde_json::{json, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Состояние конечного автомата задания в хронологии задания.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateSpan {
+    pub name: String,
+    pub description: String,
+    pub is_error: bool,
+    /// Время (с) от начала задания, в которое конечный автомат перешёл в состояние
+    pub start_seconds: f64,
+    pub duration_seconds: f64,
+}
+
+/// Событие задания, записанное в журнал.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportEvent {
+    /// Время (с) от начала задания
+    pub time_seconds: f64,
+    pub name: String,
+    /// Название состояния, в котором произошло событие
+    pub state: String,
+    pub details: String,
+}
+
+/// Найденный куб.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportCube {
+    pub id: i32,
+    pub position: Point,
+}
+
+/// Итоги задания, собранные из журнала задания.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MissionSummary {
+    pub mission_id: String,
+    pub task: u8,
+    /// Длительность задания (с) от запуска до последней записи журнала
+    pub duration_seconds: f64,
+    /// Последнее состояние конечного автомата задания
+    pub final_state: String,
+    pub timeline: Vec<StateSpan>,
+    pub events: Vec<ReportEvent>,
+    pub cubes: Vec<ReportCube>,
+    /// Содержимое QR-кодов пройденных комнат в порядке прохождения (2 задание)
+    pub passed_rooms: Vec<String>,
+    /// Пройденное расстояние (м) по записанным позам дрона
+    pub distance_flown: f64,
+    pub start_voltage: Option<f64>,
+    pub end_voltage: Option<f64>,
+    pub start_percentage: Option<f64>,
+    pub end_percentage: Option<f64>,
+    /// Причины событий `Failure` со временем (с) от начала задания
+    pub failures: Vec<(f64, String)>,
+    pub final_position: Option<Point>,
+    /// Горизонтальное расстояние (м) от места посадки до последней цели FastPlanner
+    pub landing_error: Option<f64>,
+    /// Горизонтальное расстояние (м) от места посадки до точки старта
+    pub distance_to_home: Option<f64>,
+}
+
+impl MissionSummary {
+    /// Возвращает падение вольтажа аккумулятора (В) за время задания.
+    pub fn get_voltage_consumption(&self) -> Option<f64> {
+        Some(self.start_voltage? - self.end_voltage?)
+    }
+
+    /// Возвращает израсходованный за время задания заряд (от 0 до 1).
+    pub fn get_percentage_consumption(&self) -> Option<f64> {
+        Some(self.start_percentage? - self.end_percentage?)
+    }
+}
+
+/// Собирает итоги задания из записей журнала задания `records`.
+pub fn summarize(records: &[Value]) -> Result<MissionSummary, String> {
+    let start = records
+        .iter()
+        .find(|record| record["type"] == "start")
+        .ok_or_else(|| "Mission log has no start record".to_string())?;
+    let start_stamp = start["stamp"].as_f64().unwrap_or(0.0);
+    let time_of = |record: &Value| record["stamp"].as_f64().unwrap_or(start_stamp) - start_stamp;
+    let duration_seconds = records.iter().map(time_of).fold(0.0, f64::max);
+    let mut summary = MissionSummary {
+        mission_id: start["mission_id"].as_str().unwrap_or_default().to_string(),
+        task: start["task"].as_u64().unwrap_or(0) as u8,
+        duration_seconds,
+        final_state: String::new(),
+        timeline: Vec::new(),
+        events: Vec::new(),
+        cubes: Vec::new(),
+        passed_rooms: Vec::new(),
+        distance_flown: 0.0,
+        start_voltage: None,
+        end_voltage: None,
+        start_percentage: None,
+        end_percentage: None,
+        failures: Vec::new(),
+        final_position: None,
+        landing_error: None,
+        distance_to_home: None,
+    };
+    let mut home_position = None;
+    let mut last_goal = None;
+    let mut landing_goal = None;
+    for record in records {
+        let time_seconds = time_of(record);
+        match record["type"].as_str().unwrap_or_default() {
+            "home_pose" => home_position = Some(pose_from_json(&record["pose"]).position),
+            "transition" => {
+                let name = record["state"].as_str().unwrap_or_default().to_string();
+                if summary.timeline.last().map(|span| &span.name) == Some(&name) {
+                    continue;
+                }
+                if let Some(span) = summary.timeline.last_mut() {
+                    span.duration_seconds = time_seconds - span.start_seconds;
+                }
+                if name == "Landing" {
+                    landing_goal = last_goal.clone();
+                }
+                summary.timeline.push(StateSpan {
+                    name,
+                    description: record["description"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    is_error: record["is_error"].as_bool().unwrap_or(false),
+                    start_seconds: time_seconds,
+                    duration_seconds: 0.0,
+                });
+            }
+            "event" => {
+                let kind = record["kind"].as_u64().unwrap_or(0) as u8;
+                if kind == MissionEvent::CUBE_FOUND {
+                    summary.cubes.push(ReportCube {
+                        id: record["cube_id"].as_i64().unwrap_or(0) as i32,
+                        position: point_from_json(&record["cube_position"]),
+                    });
+                } else if kind == MissionEvent::FLEW_THROUGH_HOLE {
+                    summary.passed_rooms.push(
+                        record["qr_content"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                    );
+                }
+                let details = ["details", "failure_cause", "qr_content"]
+                    .iter()
+                    .filter_map(|field| record[*field].as_str())
+                    .find(|value| !value.is_empty())
+                    .unwrap_or_default();
+                summary.events.push(ReportEvent {
+                    time_seconds,
+                    name: record["name"].as_str().unwrap_or_default().to_string(),
+                    state: record["state"].as_str().unwrap_or_default().to_string(),
+                    details: details.to_string(),
+                });
+            }
+            "failure" => summary.failures.push((
+                time_seconds,
+                record["cause"].as_str().unwrap_or_default().to_string(),
+            )),
+            "goal" => last_goal = Some(pose_from_json(&record["pose"]).position),
+            "pose" => {
+                let position = pose_from_json(&record["pose"]).position;
+                if let Some(last_position) = &summary.final_position {
+                    summary.distance_flown +=
+                        geometry::get_distance_between_points(last_position, &position);
+                }
+                summary.final_position = Some(position);
+            }
+            "battery" => {
+                let voltage = record["voltage"].as_f64();
+                let percentage = record["percentage"].as_f64();
+                if summary.start_voltage.is_none() {
+                    summary.start_voltage = voltage;
+                    summary.start_percentage = percentage;
+                }
+                summary.end_voltage = voltage.or(summary.end_voltage);
+                summary.end_percentage = percentage.or(summary.end_percentage);
+            }
+            _ => {}
+        }
+    }
+    if let Some(span) = summary.timeline.last_mut() {
+        span.duration_seconds = (duration_seconds - span.start_seconds).max(0.0);
+        summary.final_state = span.name.clone();
+    }
+    if let Some(final_position) = &summary.final_position {
+        summary.landing_error = landing_goal
+            .or(last_goal)
+            .map(|goal| get_horizontal_distance(final_position, &goal));
+        summary.distance_to_home = home_position
+            .map(|home_position| get_horizontal_distance(final_position, &home_position));
+    }
+    Ok(summary)
+}
+
+fn get_horizontal_distance(point1: &Point, point2: &Point) -> f64 {
+    ((point1.x - point2.x).powi(2) + (point1.y - point2.y).powi(2)).sqrt()
+}
+
+/// Возвращает итоги задания `summary` в формате JSON.
+pub fn to_json(summary: &MissionSummary) -> String {
+    let value = json!({
+        "mission_id": summary.mission_id,
+        "task": summary.task,
+        "duration_seconds": summary.duration_seconds,
+        "final_state": summary.final_state,
+        "timeline": summary
+            .timeline
+            .iter()
+            .map(|span| json!({
+                "state": span.name,
+                "description": span.description,
+                "is_error": span.is_error,
+                "start_seconds": span.start_seconds,
+                "duration_seconds": span.duration_seconds,
+            }))
+            .collect::<Vec<_>>(),
+        "events": summary
+            .events
+            .iter()
+            .map(|event| json!({
+                "time_seconds": event.time_seconds,
+                "name": event.name,
+                "state": event.state,
+                "details": event.details,
+            }))
+            .collect::<Vec<_>>(),
+        "cubes": summary
+            .cubes
+            .iter()
+            .map(|cube| json!({ "id": cube.id, "position": point_to_json(&cube.position) }))
+            .collect::<Vec<_>>(),
+        "passed_rooms": summary.passed_rooms,
+        "distance_flown": summary.distance_flown,
+        "battery": {
+            "start_voltage": summary.start_voltage,
+            "end_voltage": summary.end_voltage,
+            "voltage_consumption": summary.get_voltage_consumption(),
+            "start_percentage": summary.start_percentage,
+            "end_percentage": summary.end_percentage,
+            "percentage_consumption": summary.get_percentage_consumption(),
+        },
+        "failures": summary
+            .failures
+            .iter()
+            .map(|(time_seconds, cause)| json!({ "time_seconds": time_seconds, "cause": cause }))
+            .collect::<Vec<_>>(),
+        "final_position": summary.final_position.as_ref().map(point_to_json),
+        "landing_error": summary.landing_error,
+        "distance_to_home": summary.distance_to_home,
+    });
+    serde_json::to_string_pretty(&value).unwrap()
+}
+
+/// Возвращает отчёт о задании `summary` в формате Markdown.
+pub fn to_markdown(summary: &MissionSummary) -> String {
+    let mut lines = vec![
+        format!("# Mission report: task {}", summary.task),
+        String::new(),
+    ];
+    for (name, value) in get_overview(summary) {
+        lines.push(format!("- **{}:** {}", name, value));
+    }
+    for section in get_sections(summary) {
+        lines.push(String::new());
+        lines.push(format!("## {}", section.title));
+        lines.push(String::new());
+        if section.rows.is_empty() {
+            lines.push("None".to_string());
+            continue;
+        }
+        lines.push(format!("| {} |", section.columns.join(" | ")));
+        lines.push(format!("|{}", "---|".repeat(section.columns.len())));
+        for row in &section.rows {
+            let cells: Vec<String> = row.iter().map(|cell| cell.replace('|', "\\|")).collect();
+            lines.push(format!("| {} |", cells.join(" | ")));
+        }
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+/// Возвращает отчёт о задании `summary` в формате HTML.
+pub fn to_html(summary: &MissionSummary) -> String {
+    let title = format!("Mission report: task {}", summary.task);
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n</head>\n<body>\n<h1>{0}</h1>\n<ul>\n",
+        escape_html(&title)
+    );
+    for (name, value) in get_overview(summary) {
+        html += &format!(
+            "<li><b>{}:</b> {}</li>\n",
+            escape_html(name),
+            escape_html(&value)
+        );
+    }
+    html += "</ul>\n";
+    for section in get_sections(summary) {
+        html += &format!("<h2>{}</h2>\n", escape_html(section.title));
+        if section.rows.is_empty() {
+            html += "<p>None</p>\n";
+            continue;
+        }
+        html += "<table border=\"1\">\n<tr>";
+        for column in &section.columns {
+            html += &format!("<th>{}</th>", escape_html(column));
+        }
+        html += "</tr>\n";
+        for row in &section.rows {
+            html += "<tr>";
+            for cell in row {
+                html += &format!("<td>{}</td>", escape_html(cell));
+            }
+            html += "</tr>\n";
+        }
+        html += "</table>\n";
+    }
+    html += "</body>\n</html>\n";
+    html
+}
+
+/// Таблица отчёта о задании
+struct ReportSection {
+    title: &'static str,
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+
+/// Возвращает основные показатели задания в виде пар "название - значение".
+fn get_overview(summary: &MissionSummary) -> Vec<(&'static str, String)> {
+    let format_optional = |value: Option<f64>, unit: &str| match value {
+        Some(value) => format!("{:.2}{}", value, unit),
+        None => "unknown".to_string(),
+    };
+    let mut overview = vec![
+        ("Mission", summary.mission_id.clone()),
+        ("Duration", format!("{:.1} s", summary.duration_seconds)),
+        ("Final state", summary.final_state.clone()),
+        ("Distance flown", format!("{:.2} m", summary.distance_flown)),
+        (
+            "Battery voltage",
+            format!(
+                "{} -> {} (consumed {})",
+                format_optional(summary.start_voltage, " V"),
+                format_optional(summary.end_voltage, " V"),
+                format_optional(summary.get_voltage_consumption(), " V")
+            ),
+        ),
+        (
+            "Battery charge consumed",
+            format_optional(
+                summary
+                    .get_percentage_consumption()
+                    .map(|value| value * 100.0),
+                " %",
+            ),
+        ),
+        (
+            "Landing error",
+            format_optional(summary.landing_error, " m"),
+        ),
+        (
+            "Distance to home",
+            format_optional(summary.distance_to_home, " m"),
+        ),
+    ];
+    if summary.task == 1 {
+        overview.push(("Cubes found", summary.cubes.len().to_string()));
+    }
+    if summary.task == 2 {
+        overview.push(("Rooms passed", summary.passed_rooms.join(" -> ")));
+    }
+    overview
+}
+
+/// Возвращает таблицы отчёта: хронологию состояний, события, найденные кубы и события `Failure`.
+fn get_sections(summary: &MissionSummary) -> Vec<ReportSection> {
+    vec![
+        ReportSection {
+            title: "Timeline",
+            columns: vec!["Start (s)", "Duration (s)", "State", "Description"],
+            rows: summary
+                .timeline
+                .iter()
+                .map(|span| {
+                    vec![
+                        format!("{:.1}", span.start_seconds),
+                        format!("{:.1}", span.duration_seconds),
+                        span.name.clone(),
+                        span.description.clone(),
+                    ]
+                })
+                .collect(),
+        },
+        ReportSection {
+            title: "Events",
+            columns: vec!["Time (s)", "Event", "State", "Details"],
+            rows: summary
+                .events
+                .iter()
+                .map(|event| {
+                    vec![
+                        format!("{:.1}", event.time_seconds),
+                        event.name.clone(),
+                        event.state.clone(),
+                        event.details.clone(),
+                    ]
+                })
+                .collect(),
+        },
+        ReportSection {
+            title: "Cubes",
+            columns: vec!["Id", "X", "Y", "Z"],
+            rows: summary
+                .cubes
+                .iter()
+                .map(|cube| {
+                    vec![
+                        cube.id.to_string(),
+                        format!("{:.2}", cube.position.x),
+                        format!("{:.2}", cube.position.y),
+                        format!("{:.2}", cube.position.z),
+                    ]
+                })
+                .collect(),
+        },
+        ReportSection {
+            title: "Failures",
+            columns: vec!["Time (s)", "Cause"],
+            rows: summary
+                .failures
+                .iter()
+                .map(|(time_seconds, cause)| vec![format!("{:.1}", time_seconds), cause.clone()])
+                .collect(),
+        },
+    ]
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Создаёт рядом с журналом задания `log_path` отчёт о задании в форматах Markdown (`.md`) и HTML (`.html`) и итоги
+/// задания в формате JSON (`.summary.json`). Возвращает итоги задания.
+pub fn generate(log_path: &Path) -> Result<MissionSummary, String> {
+    let directory = log_path.parent().unwrap_or_else(|| Path::new(""));
+    generate_in(log_path, directory)
+}
+
+/// Создаёт в каталоге `directory` отчёт о задании по журналу задания `log_path`. Файлы отчёта называются так же, как
+/// журнал задания.
+pub fn generate_in(log_path: &Path, directory: &Path) -> Result<MissionSummary, String> {
+    let summary = summarize(&mission_log::load(log_path)?)?;
+    let outputs = [
+        ("md", to_markdown(&summary)),
+        ("html", to_html(&summary)),
+        ("summary.json", to_json(&summary)),
+    ];
+    for (extension, text) in outputs.iter() {
+        let path = report_path(log_path, directory, extension);
+        fs::write(&path, text)
+            .map_err(|error| format!("Could not write {}: {}", path.display(), error))?;
+    }
+    Ok(summary)
+}
+
+fn report_path(log_path: &Path, directory: &Path, extension: &str) -> PathBuf {
+    let file_name = log_path.file_name().unwrap_or_default();
+    directory.join(file_name).with_extension(extension)
+}
+
+/// Через `options().mission_log.report_delay_seconds` после вызова создаёт отчёт о выполняемом задании в каталоге
+/// `options().mission_log.directory`, чтобы в него попали посадка и итоговая поза дрона. Если журнал не ведётся или
+/// задержка отрицательна, отчёт не создаётся.
+pub fn schedule() {
+    let options = task_manager::options().mission_log.clone();
+    let log_path = match mission_log::get_path() {
+        Some(log_path) if !options.directory.is_empty() && options.report_delay_seconds >= 0.0 => {
+            log_path
+        }
+        _ => return,
+    };
+    std::thread::spawn(move || {
+        backend().sleep(Duration::from_nanos(
+            (options.report_delay_seconds * 1e9) as i64,
+        ));
+        let directory = Path::new(&options.directory);
+        match generate_in(&log_path, directory) {
+            Ok(_) => ros_info!(
+                "Mission report is written to {}",
+                report_path(&log_path, directory, "md").display()
+            ),
+            Err(error) => ros_err!("Could not generate mission report: {}", error),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fake_backend::testing,
+        geometry, mission_log,
+        msgs::geometry_msgs::Pose,
+        task_manager::{self, TaskType},
+    };
+    use std::fs;
+
+    #[test]
+    fn writes_report_to_mission_log_directory() {
+        let directory =
+            std::env::temp_dir().join(format!("task_manager_report_{}", std::process::id()));
+        let mut options = task_manager::OPTIONS.clone();
+        options.mission_log.directory = directory.to_string_lossy().into_owned();
+        options.mission_log.report_delay_seconds = 0.0;
+        let home_pose = Pose {
+            position: geometry::new_point(0.0, 0.0, 0.0),
+            orientation: geometry::default_quaternion(),
+        };
+        let mission = testing::start_with_options(TaskType::Two, home_pose, options);
+        mission.wait_for_state("Exploring", 5);
+        let log_path = mission_log::get_path().expect("Mission log is not written");
+        assert_eq!(log_path.parent(), Some(directory.as_path()));
+
+        mission.fake.set_drone_pose_stale(true);
+        mission.wait_for_state("Landing", 5);
+        // Итоги задания записываются последними
+        let summary_path = log_path.with_extension("summary.json");
+        mission.wait_until("mission report", 5, |_| summary_path.exists());
+        let report = fs::read_to_string(log_path.with_extension("md")).unwrap();
+        fs::remove_dir_all(&directory).ok();
+        assert!(report.contains("Landing"));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{
+    backend::backend,
+    battery, geometry,
+    msgs::geometry_msgs::Point,
+    task_manager::{self, TaskType},
+};
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+
+lazy_static! {
+    /// Центр проёма, через который дрон влетел в здание. Обратный путь к точке старта проходит через него
+    static ref ENTRY_POINT: Mutex<Option<Point>> = Mutex::new(None);
+}
+
+/// Запоминает центр проёма `entry_point`, через который дрон влетел в здание.
+pub fn set_entry_point(entry_point: Option<Point>) {
+    *ENTRY_POINT.lock().unwrap() = entry_point;
+}
+
+/// Оценка ресурсов, необходимых для возвращения на точку старта.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReturnBudget {
+    /// Длина обратного пути (м)
+    pub return_distance: f64,
+    /// Время (с), необходимое на возвращение
+    pub return_seconds: f64,
+    /// Оставшееся время (с) задания
+    pub remaining_seconds: f64,
+    /// Вольтаж аккумулятора (В), ожидаемый по возвращении. `None`, если скорость разряда ещё неизвестна
+    pub predicted_voltage: Option<f32>,
+}
+
+impl ReturnBudget {
+    /// Возвращает `true`, если оставшегося времени не хватает на возвращение с запасом
+    /// `options().return_time_margin_seconds`.
+    pub fn is_time_exhausted(&self) -> bool {
+        self.remaining_seconds
+            < self.return_seconds + task_manager::options().return_time_margin_seconds as f64
+    }
+
+    /// Возвращает `true`, если ожидаемый по возвращении вольтаж ниже допустимого с запасом
+    /// `options().return_voltage_margin`.
+    pub fn is_battery_exhausted(&self) -> bool {
+        match self.predicted_voltage {
+            Some(predicted_voltage) => {
+                predicted_voltage
+                    < task_manager::options().min_battery_voltage
+                        + task_manager::options().return_voltage_margin
+            }
+            None => false,
+        }
+    }
+}
+
+/// Оценивает ресурсы, необходимые для возвращения на точку старта задания `task_type`: длину пути от текущей позиции
+/// дрона до точки старта (через проём, в который дрон влетел, если он известен), время на путь с линейной скоростью
+/// `options().linear_velocity` и вольтаж по возвращении при средней с начала задания скорости разряда, если вольтаж в
+/// начале задания `start_voltage` известен.
+pub fn estimate(task_type: TaskType, start_voltage: Option<f32>) -> ReturnBudget {
+    let drone_position = backend().get_current_drone_pose().pose.position;
+    let start_point = task_manager::get_home_pose().position;
+    let return_distance = match &*ENTRY_POINT.lock().unwrap() {
+        Some(entry_point) => {
+            geometry::get_distance_between_points(&drone_position, entry_point)
+                + geometry::get_distance_between_points(entry_point, &start_point)
+        }
+        None => geometry::get_distance_between_points(&drone_position, &start_point),
+    };
+    let return_seconds = return_distance / task_manager::options().linear_velocity as f64;
+    let seconds_passed = task_manager::get_mission_seconds();
+    let remaining_seconds =
+        task_manager::options().get_max_timer_minutes(task_type) as f64 * 60.0 - seconds_passed;
+    let predicted_voltage = match start_voltage {
+        // Средняя скорость разряда в первую минуту слишком неточна
+        Some(start_voltage) if seconds_passed > 60.0 => {
+            let voltage = battery::get_filtered_voltage();
+            let drain_per_second = (start_voltage - voltage) / seconds_passed as f32;
+            Some(voltage - drain_per_second * return_seconds as f32)
+        }
+        _ => None,
+    };
+    ReturnBudget {
+        return_distance,
+        return_seconds,
+        remaining_seconds,
+        predicted_voltage,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate, set_entry_point};
+    use crate::{
+        battery,
+        fake_backend::testing::{self, TestMission},
+        geometry,
+        msgs::geometry_msgs::Pose,
+        task_manager::{self, TaskType},
+    };
+
+    fn new_pose(x: f64, y: f64, z: f64) -> Pose {
+        Pose {
+            position: geometry::new_point(x, y, z),
+            orientation: geometry::default_quaternion(),
+        }
+    }
+
+    /// Запускает 2 задание, в котором дрон находится в 50 м от точки старта и возвращается со скоростью 1 м/с.
+    fn start_mission() -> TestMission {
+        let mut options = task_manager::OPTIONS.clone();
+        options.mission_log.directory = String::new();
+        options.linear_velocity = 1.0;
+        options.battery.rate = 50.0;
+        options.battery.window_size = 1;
+        let mission = testing::start_with_options(TaskType::Two, new_pose(0.0, 0.0, 0.0), options);
+        mission.wait_for_state("Exploring", 5);
+        mission.fake.set_drone_pose(new_pose(30.0, 40.0, 0.0));
+        mission
+    }
+
+    fn set_filtered_voltage(mission: &TestMission, voltage: f32) {
+        mission.fake.set_battery_voltage(voltage);
+        mission.wait_until("filtered voltage", 5, |_| {
+            battery::get_filtered_voltage() == voltage
+        });
+    }
+
+    #[test]
+    fn estimates_time_budget() {
+        // Задание длится 10 мин, запас времени - 30 с
+        let mission = start_mission();
+        let budget = estimate(TaskType::Two, None);
+        assert!((budget.return_distance - 50.0).abs() < 1e-6);
+        assert!((budget.return_seconds - 50.0).abs() < 1e-6);
+        assert!(budget.remaining_seconds <= 600.0 && budget.remaining_seconds > 590.0);
+        assert_eq!(budget.predicted_voltage, None);
+        assert!(!budget.is_time_exhausted());
+        assert!(!budget.is_battery_exhausted());
+
+        // Обратный путь проходит через проём
+        set_entry_point(Some(geometry::new_point(30.0, 0.0, 0.0)));
+        let budget = estimate(TaskType::Two, None);
+        set_entry_point(None);
+        assert!((budget.return_distance - 70.0).abs() < 1e-6);
+
+        mission.fake.advance_time(500.0);
+        let budget = estimate(TaskType::Two, None);
+        assert!(budget.remaining_seconds <= 100.0 && budget.remaining_seconds > 90.0);
+        assert!(!budget.is_time_exhausted());
+
+        mission.fake.advance_time(30.0);
+        assert!(estimate(TaskType::Two, None).is_time_exhausted());
+    }
+
+    #[test]
+    fn estimates_battery_budget() {
+        // Допустимый вольтаж - 10 В, запас - 0.2 В
+        let mission = start_mission();
+        set_filtered_voltage(&mission, 10.1);
+        // В первую минуту скорость разряда ещё неизвестна
+        let budget = estimate(TaskType::Two, Some(12.6));
+        assert_eq!(budget.predicted_voltage, None);
+        assert!(!budget.is_battery_exhausted());
+
+        mission.fake.advance_time(100.0);
+        // 2.5 В за 100 с, на возвращение уходит 1.25 В
+        let budget = estimate(TaskType::Two, Some(12.6));
+        assert!((budget.predicted_voltage.unwrap() - 8.85).abs() < 0.02);
+        assert!(budget.is_battery_exhausted());
+
+        set_filtered_voltage(&mission, 12.5);
+        // 0.1 В за 100 с, на возвращение уходит 0.05 В
+        let budget = estimate(TaskType::Two, Some(12.6));
+        assert!((budget.predicted_voltage.unwrap() - 12.45).abs() < 0.01);
+        assert!(!budget.is_battery_exhausted());
+
+        // Вольтаж в начале задания неизвестен
+        assert_eq!(estimate(TaskType::Two, None).predicted_voltage, None);
+    }
+}
@@ -0,0 +1,327 @@
+extern crate nalgebra as na;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use na::UnitQuaternion;
+use serde::Deserialize;
+
+use crate::{
+    geometry,
+    msgs::{
+        detection_msgs::DetectedObject,
+        geometry_msgs::{Point, Pose, Vector3},
+    },
+    simulator::{BatteryModel, SimulationResult, SimulatorOptions, Wall, World, WorldQr},
+    task_manager::TaskType,
+};
+
+/// Сценарий проверки задания в симуляторе: описание мира и ожидаемый результат. Загружается из YAML (`.yaml`,
+/// `.yml`) или TOML (`.toml`) файла.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Scenario {
+    /// Название сценария
+    pub name: String,
+    /// Номер задания (1, 2 или 3)
+    pub task: u8,
+    /// Наибольшее время симуляции (с)
+    #[serde(default = "default_max_seconds")]
+    pub max_seconds: f64,
+    /// Расстояние (м), на котором дрон обнаруживает объекты. По умолчанию - как в `SimulatorOptions::default`.
+    pub sensor_range: Option<f64>,
+    pub world: ScenarioWorld,
+    pub expect: Expectations,
+}
+
+/// Точка в СК map. Координата z по умолчанию равна 0.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ScenarioPoint {
+    pub x: f64,
+    pub y: f64,
+    #[serde(default)]
+    pub z: f64,
+}
+
+/// Поза в СК map, ориентация задаётся углом рыскания `yaw` (рад).
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ScenarioPose {
+    pub x: f64,
+    pub y: f64,
+    #[serde(default)]
+    pub z: f64,
+    #[serde(default)]
+    pub yaw: f64,
+}
+
+/// Стена здания, см. `simulator::Wall`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ScenarioWall {
+    pub from: ScenarioPoint,
+    pub to: ScenarioPoint,
+    pub height: f64,
+}
+
+/// Проём: центр, угол рыскания нормали к плоскости проёма (рад), ширина и высота (м).
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ScenarioHole {
+    pub position: ScenarioPoint,
+    #[serde(default)]
+    pub yaw: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// QR-код и его содержание. Для QR-кодов на полу координата z не учитывается.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioQr {
+    pub position: ScenarioPoint,
+    pub content: String,
+}
+
+/// Точка кривой разряда аккумулятора: вольтаж `voltage` (В) через `time` с после начала симуляции.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct BatteryPoint {
+    pub time: f64,
+    pub voltage: f32,
+}
+
+/// Описание мира сценария.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScenarioWorld {
+    pub start_pose: ScenarioPose,
+    #[serde(default)]
+    pub walls: Vec<ScenarioWall>,
+    #[serde(default)]
+    pub holes: Vec<ScenarioHole>,
+    #[serde(default)]
+    pub cubes: Vec<ScenarioPoint>,
+    #[serde(default)]
+    pub wall_qr_codes: Vec<ScenarioQr>,
+    #[serde(default)]
+    pub floor_qr_codes: Vec<ScenarioQr>,
+    /// Кривая разряда аккумулятора. По умолчанию вольтаж постоянен и равен 12.6 В.
+    #[serde(default)]
+    pub battery: Vec<BatteryPoint>,
+}
+
+/// Ожидаемый результат выполнения задания. Проверяются только заданные ожидания.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Expectations {
+    /// Название состояния конечного автомата в конце симуляции, например "Landing"
+    pub final_state: Option<String>,
+    /// Количество опубликованных кубов
+    pub cubes_published: Option<usize>,
+    /// Номера пройденных комнат через пробел в порядке прохождения, например "1 2 3"
+    pub passed_rooms: Option<String>,
+    /// Точка, рядом с которой дрон должен приземлиться
+    pub landed_near: Option<LandingExpectation>,
+}
+
+/// Ожидание посадки не дальше `tolerance` (м) от точки `position`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct LandingExpectation {
+    pub position: ScenarioPoint,
+    #[serde(default = "default_landing_tolerance")]
+    pub tolerance: f64,
+}
+
+/// Результат проверки одного ожидания.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpectationResult {
+    /// Название ожидания
+    pub name: String,
+    pub is_passed: bool,
+    /// Ожидаемое и фактическое значения
+    pub details: String,
+}
+
+fn default_max_seconds() -> f64 {
+    900.0
+}
+
+fn default_landing_tolerance() -> f64 {
+    0.3
+}
+
+impl From<ScenarioPoint> for Point {
+    fn from(point: ScenarioPoint) -> Self {
+        geometry::new_point(point.x, point.y, point.z)
+    }
+}
+
+/// Возвращает ориентацию с углом рыскания `yaw` (рад).
+fn new_orientation(yaw: f64) -> crate::msgs::geometry_msgs::Quaternion {
+    UnitQuaternion::from_euler_angles(0.0, 0.0, yaw)
+        .into_inner()
+        .into()
+}
+
+impl Scenario {
+    /// Загружает сценарий из файла `path`. Формат определяется по расширению файла.
+    pub fn load(path: &Path) -> Result<Scenario, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|error| format!("Could not read \"{}\": {}", path.display(), error))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => serde_yaml::from_str(&content)
+                .map_err(|error| format!("Could not parse \"{}\": {}", path.display(), error)),
+            Some("toml") => toml::from_str(&content)
+                .map_err(|error| format!("Could not parse \"{}\": {}", path.display(), error)),
+            _ => Err(format!("\"{}\" is not a YAML or TOML file", path.display())),
+        }
+    }
+
+    /// Возвращает задание, которое проверяет сценарий.
+    pub fn get_task_type(&self) -> Result<TaskType, String> {
+        match self.task {
+            1 => Ok(TaskType::One),
+            2 => Ok(TaskType::Two),
+            3 => Ok(TaskType::Three),
+            task => Err(format!("Wrong task number {} is specified", task)),
+        }
+    }
+
+    /// Возвращает параметры симулятора для сценария.
+    pub fn get_simulator_options(&self) -> SimulatorOptions {
+        let mut options = SimulatorOptions::default();
+        if let Some(sensor_range) = self.sensor_range {
+            options.sensor_range = sensor_range;
+        }
+        options
+    }
+
+    /// Возвращает мир симулятора, описанный в сценарии.
+    pub fn get_world(&self) -> World {
+        let world = &self.world;
+        let start_pose = world.start_pose;
+        World {
+            start_pose: Pose {
+                position: geometry::new_point(start_pose.x, start_pose.y, start_pose.z),
+                orientation: new_orientation(start_pose.yaw),
+            },
+            walls: world
+                .walls
+                .iter()
+                .map(|wall| Wall {
+                    from: wall.from.into(),
+                    to: wall.to.into(),
+                    height: wall.height,
+                })
+                .collect(),
+            holes: world
+                .holes
+                .iter()
+                .map(|hole| DetectedObject {
+                    id: 0,
+                    pose: Pose {
+                        position: hole.position.into(),
+                        orientation: new_orientation(hole.yaw),
+                    },
+                    dimensions: Vector3 {
+                        x: 0.05,
+                        y: hole.width,
+                        z: hole.height,
+                    },
+                })
+                .collect(),
+            cubes: world.cubes.iter().map(|&cube| cube.into()).collect(),
+            qr_codes: world
+                .wall_qr_codes
+                .iter()
+                .map(|qr| WorldQr {
+                    position: qr.position.into(),
+                    content: qr.content.clone(),
+                })
+                .chain(world.floor_qr_codes.iter().map(|qr| WorldQr {
+                    position: geometry::new_point(qr.position.x, qr.position.y, 0.0),
+                    content: qr.content.clone(),
+                }))
+                .collect(),
+            battery: if world.battery.is_empty() {
+                BatteryModel::Curve(vec![(0.0, 12.6)])
+            } else {
+                BatteryModel::Curve(
+                    world
+                        .battery
+                        .iter()
+                        .map(|point| (point.time, point.voltage))
+                        .collect(),
+                )
+            },
+        }
+    }
+
+    /// Проверяет результат симуляции `result` на соответствие всем заданным ожиданиям.
+    pub fn check(&self, result: &SimulationResult) -> Vec<ExpectationResult> {
+        let expect = &self.expect;
+        let mut results = Vec::new();
+        if let Some(final_state) = &expect.final_state {
+            results.push(ExpectationResult {
+                name: "final state".to_string(),
+                is_passed: &result.final_state == final_state,
+                details: format!("expected {}, got {}", final_state, result.final_state),
+            });
+        }
+        if let Some(cubes_published) = expect.cubes_published {
+            results.push(ExpectationResult {
+                name: "cubes published".to_string(),
+                is_passed: result.published_cubes.len() == cubes_published,
+                details: format!(
+                    "expected {}, got {}",
+                    cubes_published,
+                    result.published_cubes.len()
+                ),
+            });
+        }
+        if let Some(passed_rooms) = &expect.passed_rooms {
+            let expected_rooms = passed_rooms.split_whitespace().collect::<Vec<_>>();
+            results.push(ExpectationResult {
+                name: "passed rooms".to_string(),
+                is_passed: result.passed_rooms == expected_rooms,
+                details: format!(
+                    "expected \"{}\", got \"{}\"",
+                    expected_rooms.join(" "),
+                    result.passed_rooms.join(" ")
+                ),
+            });
+        }
+        if let Some(landed_near) = expect.landed_near {
+            let distance = geometry::get_distance_between_points(
+                &result.final_pose.position,
+                &landed_near.position.into(),
+            );
+            results.push(ExpectationResult {
+                name: "landed near".to_string(),
+                is_passed: result.has_landed && distance <= landed_near.tolerance,
+                details: if result.has_landed {
+                    format!(
+                        "expected at most {} m, got {:.2} m",
+                        landed_near.tolerance, distance
+                    )
+                } else {
+                    "drone has not landed".to_string()
+                },
+            });
+        }
+        results
+    }
+}
+
+/// Возвращает пути ко всем файлам сценариев (YAML и TOML) в директории `directory`, отсортированные по имени.
+pub fn find_scenarios(directory: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(directory)
+        .map_err(|error| format!("Could not read \"{}\": {}", directory.display(), error))?;
+    let mut paths = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && matches!(
+                    path.extension().and_then(|extension| extension.to_str()),
+                    Some("yaml") | Some("yml") | Some("toml")
+                )
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths)
+}
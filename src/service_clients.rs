@@ -1,10 +1,13 @@
 use crate::{
+    backend::{GoalDoneCallback, GoalOutcome},
     common_ros_utils::*,
+    events::{log_event, EventKind, EventPayload},
     geometry::{self, default_point, default_quaternion, new_header},
+    metrics, mission_log, mission_state,
     msgs::{
         autotakeoff::*,
         detection_msgs::*,
-        fast_planner_server::FastPlannerAction,
+        fast_planner_server::{FastPlannerAction, FastPlannerGoal},
         geometry_msgs::{PointStamped, Pose, PoseStamped},
         motion_controller::*,
         plan_env::*,
@@ -15,72 +18,329 @@ use crate::{
     task_manager::TaskType,
 };
 use lazy_static::{initialize, lazy_static};
-use rosrust::{self, Client, Duration};
+use rosrust::{self, ros_info, ros_warn, Client, Duration, ServicePair};
 use rosrust_actionlib::SimpleActionClient;
-use std::sync::Mutex;
+use serde_json::json;
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Mutex, RwLock,
+    },
+    thread, time,
+};
+
+// Названия сервисов и серверов actionlib, которые использует узел
+pub const FAST_PLANNER_SERVER: &str = "fast_planner_server";
+pub const USE_FUEL_SERVICE: &str = "fuel/use_fuel";
+pub const GET_NEAREST_HOLE_SERVICE: &str = "vision/holes/pos_collector/get_nearest";
+pub const RESET_FUEL_SERVICE: &str = "fuel/reset";
+pub const SPIN_SERVICE: &str = "motion_controller/spin";
+pub const STOP_SPIN_SERVICE: &str = "motion_controller/stop";
+pub const TAKEOFF_SERVICE: &str = "takeoff_landing";
+/// Посадка выполняется тем же сервисом, что и взлёт, и записывается в журнал задания под его названием
+pub const LAND_SERVICE: &str = TAKEOFF_SERVICE;
+pub const ADD_VIRTUAL_WALL_SERVICE: &str = "sdf_map/add_wall";
+pub const SET_ARE_WALLS_ENABLED_SERVICE: &str = "sdf_map/set_are_walls_enabled";
+pub const COUNT_CUBES_SERVICE: &str = "vision/cubes/pos_collector/count";
+pub const GET_ALL_CUBES_SERVICE: &str = "vision/cubes/pos_collector/get_all";
+pub const TRANSFORM_POINT_SERVICE: &str = "transform/point";
+pub const TRANSFORM_POSE_SERVICE: &str = "transform/pose";
+pub const COUNT_HOLES_SERVICE: &str = "vision/holes/pos_collector/count";
+pub const GET_ALL_HOLES_SERVICE: &str = "vision/holes/pos_collector/get_all";
 
 lazy_static! {
     pub static ref FAST_PLANNER_SERVER_CLIENT: Mutex<SimpleActionClient<FastPlannerAction>> = {
-        let fast_planner_client = SimpleActionClient::new("fast_planner_server").unwrap();
-        wait_for_actionlib_server(&fast_planner_client, "fast_planner_server");
+        let fast_planner_client = SimpleActionClient::new(FAST_PLANNER_SERVER).unwrap();
+        wait_for_actionlib_server(&fast_planner_client, FAST_PLANNER_SERVER);
         Mutex::new(fast_planner_client)
     };
-    pub static ref USE_FUEL_CLIENT: Client<SetBool> = {
-        wait_for_service("fuel/use_fuel");
-        rosrust::client("fuel/use_fuel").unwrap()
-    };
-    pub static ref GET_NEAREST_HOLE: Client<NearestPos> = {
-        wait_for_service("vision/holes/pos_collector/get_nearest");
-        rosrust::client("vision/holes/pos_collector/get_nearest").unwrap()
-    };
-    pub static ref RESET_FUEL_CLIENT: Client<Empty> = {
-        wait_for_service("fuel/reset");
-        rosrust::client("fuel/reset").unwrap()
-    };
-    pub static ref SPIN_CLIENT: Client<Spin> = {
-        wait_for_service("motion_controller/spin");
-        rosrust::client("motion_controller/spin").unwrap()
-    };
-    pub static ref STOP_SPIN_CLIENT: Client<Empty> = {
-        wait_for_service("motion_controller/stop");
-        rosrust::client("motion_controller/stop").unwrap()
-    };
-    pub static ref TAKEOFF_CLIENT: Client<Takeoff> = {
-        wait_for_service("takeoff_landing");
-        rosrust::client("takeoff_landing").unwrap()
-    };
-    pub static ref ADD_VIRTUAL_WALL_CLIENT: Client<AddWalls> = {
-        wait_for_service("sdf_map/add_wall");
-        rosrust::client("sdf_map/add_wall").unwrap()
-    };
-    pub static ref SET_ARE_WALLS_ENABLED_CLIENT: Client<SetBool> = {
-        wait_for_service("sdf_map/set_are_walls_enabled");
-        rosrust::client("sdf_map/set_are_walls_enabled").unwrap()
-    };
-    pub static ref COUNT_CUBES_CLIENT: Client<Count> = {
-        wait_for_service("vision/cubes/pos_collector/count");
-        rosrust::client("vision/cubes/pos_collector/count").unwrap()
-    };
-    pub static ref GET_ALL_CUBES_CLIENT: Client<GetAll> = {
-        wait_for_service("vision/cubes/pos_collector/get_all");
-        rosrust::client("vision/cubes/pos_collector/get_all").unwrap()
-    };
-    pub static ref TRANSFORM_POINT_CLIENT: Client<TransformPoint> = {
-        wait_for_service("transform/point");
-        rosrust::client::<TransformPoint>("transform/point").unwrap()
-    };
-    pub static ref TRANSFORM_POSE_CLIENT: Client<TransformPose> = {
-        wait_for_service("transform/pose");
-        rosrust::client::<TransformPose>("transform/pose").unwrap()
-    };
-    pub static ref COUNT_HOLES_CLIENT: Client<Count> = {
-        wait_for_service("vision/holes/pos_collector/count");
-        rosrust::client("vision/holes/pos_collector/count").unwrap()
-    };
-    pub static ref GET_ALL_HOLES_CLIENT: Client<GetAll> = {
-        wait_for_service("vision/holes/pos_collector/get_all");
-        rosrust::client("vision/holes/pos_collector/get_all").unwrap()
-    };
+    pub static ref USE_FUEL_CLIENT: ServiceClient<SetBool> =
+        ServiceClient::new(USE_FUEL_SERVICE, "use_fuel", 5.0);
+    pub static ref GET_NEAREST_HOLE: ServiceClient<NearestPos> =
+        ServiceClient::new(GET_NEAREST_HOLE_SERVICE, "get_nearest_hole", 5.0);
+    pub static ref RESET_FUEL_CLIENT: ServiceClient<Empty> =
+        ServiceClient::new(RESET_FUEL_SERVICE, "reset_fuel", 5.0);
+    pub static ref SPIN_CLIENT: ServiceClient<Spin> =
+        ServiceClient::new(SPIN_SERVICE, "spin", 120.0);
+    pub static ref STOP_SPIN_CLIENT: ServiceClient<Empty> =
+        ServiceClient::new(STOP_SPIN_SERVICE, "stop_spin", 5.0);
+    pub static ref TAKEOFF_CLIENT: ServiceClient<Takeoff> =
+        ServiceClient::new(TAKEOFF_SERVICE, "takeoff_landing", 60.0);
+    pub static ref ADD_VIRTUAL_WALL_CLIENT: ServiceClient<AddWalls> =
+        ServiceClient::new(ADD_VIRTUAL_WALL_SERVICE, "add_wall", 5.0);
+    pub static ref SET_ARE_WALLS_ENABLED_CLIENT: ServiceClient<SetBool> =
+        ServiceClient::new(SET_ARE_WALLS_ENABLED_SERVICE, "set_are_walls_enabled", 5.0);
+    pub static ref COUNT_CUBES_CLIENT: ServiceClient<Count> =
+        ServiceClient::new(COUNT_CUBES_SERVICE, "count_cubes", 5.0);
+    pub static ref GET_ALL_CUBES_CLIENT: ServiceClient<GetAll> =
+        ServiceClient::new(GET_ALL_CUBES_SERVICE, "get_all_cubes", 5.0);
+    pub static ref TRANSFORM_POINT_CLIENT: ServiceClient<TransformPoint> =
+        ServiceClient::new(TRANSFORM_POINT_SERVICE, "transform_point", 2.0);
+    pub static ref TRANSFORM_POSE_CLIENT: ServiceClient<TransformPose> =
+        ServiceClient::new(TRANSFORM_POSE_SERVICE, "transform_pose", 2.0);
+    pub static ref COUNT_HOLES_CLIENT: ServiceClient<Count> =
+        ServiceClient::new(COUNT_HOLES_SERVICE, "count_holes", 5.0);
+    pub static ref GET_ALL_HOLES_CLIENT: ServiceClient<GetAll> =
+        ServiceClient::new(GET_ALL_HOLES_SERVICE, "get_all_holes", 5.0);
+}
+
+/// Ошибка вызова сервиса
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServiceError {
+    /// Не удалось связаться с сервисом `service`
+    Unavailable {
+        service: &'static str,
+        message: String,
+    },
+    /// Сервис `service` отклонил запрос
+    Rejected {
+        service: &'static str,
+        message: String,
+    },
+    /// Сервис `service` не ответил за отведённое время
+    Timeout { service: &'static str },
+}
+
+impl ServiceError {
+    /// Возвращает название сервиса, вызов которого завершился ошибкой.
+    pub fn service(&self) -> &'static str {
+        match self {
+            ServiceError::Unavailable { service, .. }
+            | ServiceError::Rejected { service, .. }
+            | ServiceError::Timeout { service } => *service,
+        }
+    }
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::Unavailable { service, message } => {
+                write!(f, "service \"{}\" is unavailable: {}", service, message)
+            }
+            ServiceError::Rejected { service, message } => {
+                write!(
+                    f,
+                    "service \"{}\" rejected the request: {}",
+                    service, message
+                )
+            }
+            ServiceError::Timeout { service } => {
+                write!(f, "service \"{}\" has not responded in time", service)
+            }
+        }
+    }
+}
+
+/// Политика повторных вызовов сервиса. Повторяются только вызовы, при которых не удалось связаться с сервисом:
+/// отклонённый запрос считается окончательным ответом.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Наибольшее количество попыток вызова, включая первую
+    pub attempts: u32,
+    /// Пауза (с) перед первым повторным вызовом
+    pub backoff_seconds: f64,
+    /// Множитель, на который увеличивается пауза перед каждым следующим повторным вызовом
+    pub backoff_multiplier: f64,
+    /// Наибольшее время (с), в течение которого ожидается перезапуск сервиса перед повторным вызовом
+    pub reconnect_timeout_seconds: f64,
+}
+
+impl RetryPolicy {
+    /// Возвращает политику сервиса с ключом `key` из параметров ROS `~<key>_retry_attempts`,
+    /// `~<key>_retry_backoff_seconds`, `~<key>_retry_backoff_multiplier` и `~<key>_reconnect_timeout_seconds`.
+    /// Незаданные параметры берутся из общих для всех сервисов `~service_retry_attempts`,
+    /// `~service_retry_backoff_seconds`, `~service_retry_backoff_multiplier` и `~service_reconnect_timeout_seconds`.
+    pub fn from_params(key: &str) -> RetryPolicy {
+        let attempts = get_param("~service_retry_attempts", 3);
+        let backoff_seconds = get_param("~service_retry_backoff_seconds", 0.5);
+        let backoff_multiplier = get_param("~service_retry_backoff_multiplier", 2.0);
+        let reconnect_timeout_seconds = get_param("~service_reconnect_timeout_seconds", 5.0);
+        RetryPolicy {
+            attempts: get_param(&format!("~{}_retry_attempts", key), attempts).max(1),
+            backoff_seconds: get_param(&format!("~{}_retry_backoff_seconds", key), backoff_seconds),
+            backoff_multiplier: get_param(
+                &format!("~{}_retry_backoff_multiplier", key),
+                backoff_multiplier,
+            ),
+            reconnect_timeout_seconds: get_param(
+                &format!("~{}_reconnect_timeout_seconds", key),
+                reconnect_timeout_seconds,
+            ),
+        }
+    }
+}
+
+/// Клиент сервиса с ограничением времени ответа и повторными вызовами по политике `RetryPolicy`. Если соединение с
+/// сервисом потеряно (например, узел сервиса перезапустился), клиент ожидает появления сервиса и пересоздаёт
+/// соединение, публикуя события `ServiceDown` и `ServiceRestored`.
+pub struct ServiceClient<T: ServicePair> {
+    name: &'static str,
+    client: RwLock<Client<T>>,
+    /// `true`, если соединение с сервисом потеряно и ещё не восстановлено
+    is_down: AtomicBool,
+    retry_policy: RetryPolicy,
+    /// Наибольшее время ожидания ответа (с) на один вызов. Если не больше 0, время ожидания не ограничено.
+    timeout_seconds: f64,
+}
+
+impl<T: ServicePair> ServiceClient<T> {
+    /// Ожидает сервис `name` и создаёт его клиента. Политика повторных вызовов задаётся параметрами ROS с ключом
+    /// `key`, см. `RetryPolicy::from_params`, время ожидания ответа - параметром `~<key>_timeout_seconds` (по
+    /// умолчанию - `default_timeout_seconds`).
+    pub fn new(name: &'static str, key: &str, default_timeout_seconds: f64) -> ServiceClient<T> {
+        wait_for_service(name);
+        ServiceClient {
+            name,
+            client: RwLock::new(rosrust::client(name).unwrap()),
+            is_down: AtomicBool::new(false),
+            retry_policy: RetryPolicy::from_params(key),
+            timeout_seconds: get_param(
+                &format!("~{}_timeout_seconds", key),
+                default_timeout_seconds,
+            ),
+        }
+    }
+
+    /// Возвращает название сервиса.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Возвращает `true`, если соединение с сервисом потеряно и ещё не восстановлено.
+    pub fn is_down(&self) -> bool {
+        self.is_down.load(Ordering::SeqCst)
+    }
+
+    /// Вызывает сервис с запросом `request`. Если связаться с сервисом не удалось, пересоздаёт соединение и повторяет
+    /// вызов с растущей паузой, пока не закончатся попытки. Если сервис не ответил вовремя, вызов не повторяется.
+    pub fn call(&self, request: &T::Request) -> Result<T::Response, ServiceError> {
+        let started_at = time::Instant::now();
+        let result = self.call_with_retries(request);
+        let latency_seconds = started_at.elapsed().as_secs_f64();
+        metrics::record_service_call(self.name, latency_seconds, result.is_ok());
+        mission_log::record(
+            "service_call",
+            match &result {
+                Ok(response) => json!({
+                    "service": self.name,
+                    "latency_seconds": latency_seconds,
+                    "result": "ok",
+                    "response": format!("{:?}", response),
+                }),
+                Err(error) => json!({
+                    "service": self.name,
+                    "latency_seconds": latency_seconds,
+                    "result": "error",
+                    "error": error.to_string(),
+                }),
+            },
+        );
+        result
+    }
+
+    /// См. `ServiceClient::call`.
+    fn call_with_retries(&self, request: &T::Request) -> Result<T::Response, ServiceError> {
+        let mut backoff_seconds = self.retry_policy.backoff_seconds;
+        let mut attempt = 1;
+        loop {
+            let error = match self.request(request)? {
+                Ok(Ok(response)) => {
+                    self.set_down(false);
+                    return Ok(response);
+                }
+                Ok(Err(message)) => {
+                    self.set_down(false);
+                    return Err(ServiceError::Rejected {
+                        service: self.name,
+                        message,
+                    });
+                }
+                Err(error) => ServiceError::Unavailable {
+                    service: self.name,
+                    message: error.to_string(),
+                },
+            };
+            self.set_down(true);
+            if attempt >= self.retry_policy.attempts || !rosrust::is_ok() {
+                return Err(error);
+            }
+            if !self.reconnect() {
+                ros_warn!(
+                    "Attempt {} of {} failed: {}. Retrying in {:.1} s...",
+                    attempt,
+                    self.retry_policy.attempts,
+                    error,
+                    backoff_seconds
+                );
+                rosrust::sleep(Duration::from_nanos((backoff_seconds * 1e9) as i64));
+                backoff_seconds *= self.retry_policy.backoff_multiplier;
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Ожидает появления сервиса не дольше `RetryPolicy::reconnect_timeout_seconds` и пересоздаёт соединение с ним.
+    /// Возвращает `true`, если соединение пересоздано.
+    fn reconnect(&self) -> bool {
+        let timeout =
+            time::Duration::from_secs_f64(self.retry_policy.reconnect_timeout_seconds.max(0.0));
+        if rosrust::wait_for_service(self.name, Some(timeout)).is_err() {
+            return false;
+        }
+        match rosrust::client(self.name) {
+            Ok(client) => {
+                *self.client.write().unwrap() = client;
+                ros_info!("Connection to service \"{}\" is re-established.", self.name);
+                true
+            }
+            Err(error) => {
+                ros_warn!(
+                    "Could not re-establish connection to service \"{}\": {}",
+                    self.name,
+                    error
+                );
+                false
+            }
+        }
+    }
+
+    /// Отмечает, потеряно ли соединение с сервисом, и публикует событие при изменении.
+    fn set_down(&self, is_down: bool) {
+        if self.is_down.swap(is_down, Ordering::SeqCst) != is_down {
+            let (kind, event_name) = if is_down {
+                (EventKind::ServiceDown, "ServiceDown")
+            } else {
+                (EventKind::ServiceRestored, "ServiceRestored")
+            };
+            log_event(
+                kind,
+                &format!("{} ({})", event_name, self.name),
+                EventPayload::Details(self.name.to_string()),
+            );
+        }
+    }
+
+    /// Выполняет один вызов сервиса. Если время ожидания ограничено, вызов выполняется в отдельном потоке, который
+    /// продолжает ждать ответа и после истечения времени ожидания - его результат отбрасывается.
+    fn request(
+        &self,
+        request: &T::Request,
+    ) -> Result<rosrust::error::Result<Result<T::Response, String>>, ServiceError> {
+        if self.timeout_seconds <= 0.0 {
+            return Ok(self.client.read().unwrap().req(request));
+        }
+        let (sender, receiver) = mpsc::channel();
+        let client = self.client.read().unwrap().clone();
+        let request = request.clone();
+        thread::spawn(move || {
+            let _ = sender.send(client.req(&request));
+        });
+        receiver
+            .recv_timeout(time::Duration::from_secs_f64(self.timeout_seconds))
+            .map_err(|_| ServiceError::Timeout { service: self.name })
+    }
 }
 
 /// Ожидает и инициализирует всех клиентов сервисов, использующихся узлом.
@@ -118,89 +378,152 @@ pub fn init(task_type: TaskType) {
     }
 }
 
+/// Возвращает названия сервисов, клиенты которых инициализирует `init` для задания `task_type`.
+pub fn get_required_services(task_type: TaskType) -> Vec<&'static str> {
+    match task_type {
+        TaskType::One => vec![
+            USE_FUEL_SERVICE,
+            GET_NEAREST_HOLE_SERVICE,
+            RESET_FUEL_SERVICE,
+            SPIN_SERVICE,
+            STOP_SPIN_SERVICE,
+            TAKEOFF_SERVICE,
+            ADD_VIRTUAL_WALL_SERVICE,
+            SET_ARE_WALLS_ENABLED_SERVICE,
+            COUNT_CUBES_SERVICE,
+            GET_ALL_CUBES_SERVICE,
+        ],
+        TaskType::Two => vec![
+            USE_FUEL_SERVICE,
+            GET_NEAREST_HOLE_SERVICE,
+            RESET_FUEL_SERVICE,
+            SPIN_SERVICE,
+            STOP_SPIN_SERVICE,
+            TAKEOFF_SERVICE,
+            ADD_VIRTUAL_WALL_SERVICE,
+            SET_ARE_WALLS_ENABLED_SERVICE,
+            COUNT_HOLES_SERVICE,
+            GET_ALL_HOLES_SERVICE,
+        ],
+        TaskType::Three => vec![TAKEOFF_SERVICE],
+    }
+}
+
+/// Возвращает названия серверов actionlib, клиенты которых инициализирует `init` для задания `task_type`.
+pub fn get_required_action_servers(task_type: TaskType) -> Vec<&'static str> {
+    match task_type {
+        TaskType::One | TaskType::Two => vec![FAST_PLANNER_SERVER],
+        TaskType::Three => Vec::new(),
+    }
+}
+
 /// Подаёт дрону команду на взлёт. Блокирует вызывающий поток, пока дрон не достигнет высоты `height`.
-pub fn takeoff(height: f32) {
-    TAKEOFF_CLIENT
-        .req(&TakeoffReq {
-            height,
-            land: false,
-        })
-        .unwrap()
-        .unwrap();
+pub fn takeoff(height: f32) -> Result<(), ServiceError> {
+    TAKEOFF_CLIENT.call(&TakeoffReq {
+        height,
+        land: false,
+    })?;
+    Ok(())
 }
 
 /// Подаёт дрону команду на приземление в текущей точке. Блокирует вызывающий поток, пока дрон не приземлится.
-pub fn land() {
-    TAKEOFF_CLIENT
-        .req(&TakeoffReq {
-            height: 0.0,
-            land: true,
-        })
-        .unwrap()
-        .unwrap();
+pub fn land() -> Result<(), ServiceError> {
+    // Вызов записывается в журнал задания под названием `LAND_SERVICE`
+    TAKEOFF_CLIENT.call(&TakeoffReq {
+        height: 0.0,
+        land: true,
+    })?;
+    Ok(())
 }
 
 /// Сбрасывает карту и ранее построенные маршруты FUEL.
-pub fn reset_fuel() {
-    RESET_FUEL_CLIENT.req(&EmptyReq {}).unwrap().unwrap();
+pub fn reset_fuel() -> Result<(), ServiceError> {
+    RESET_FUEL_CLIENT.call(&EmptyReq {})?;
+    Ok(())
+}
+
+/// Отправляет FastPlanner цель - позу `pose` в СК "map". Если указан `on_done`, он вызывается по завершении цели.
+pub fn send_goal(pose: Pose, on_done: Option<GoalDoneCallback>) {
+    let goal = mission_state::pose_to_json(&pose);
+    mission_log::record("goal", json!({ "pose": goal }));
+    let client = FAST_PLANNER_SERVER_CLIENT.lock().unwrap();
+    let goal_sender = client.build_goal_sender(FastPlannerGoal {
+        header: new_header("map"),
+        pose,
+    });
+    let sent_at = time::Instant::now();
+    goal_sender
+        .on_done(move |state, _| {
+            let outcome = format!("{:?}", state);
+            let duration_seconds = sent_at.elapsed().as_secs_f64();
+            metrics::record_goal(&outcome, duration_seconds);
+            mission_log::record(
+                "goal_done",
+                json!({
+                    "pose": goal,
+                    "state": outcome,
+                    "duration_seconds": duration_seconds,
+                }),
+            );
+            if let Some(on_done) = &on_done {
+                on_done(GoalOutcome::from_state_name(&outcome));
+            }
+        })
+        .send();
 }
 
 /// Отменяет все цели FastPlanner.
-pub fn cancel_all_goals() {
+pub fn cancel_all_goals() -> Result<(), ServiceError> {
     FAST_PLANNER_SERVER_CLIENT
         .lock()
         .unwrap()
         .cancel_all_goals()
-        .unwrap();
+        .map_err(|error| ServiceError::Unavailable {
+            service: FAST_PLANNER_SERVER,
+            message: error.to_string(),
+        })?;
     rosrust::sleep(Duration::from_seconds(1));
+    Ok(())
 }
 
 /// Вращает дрона вокруг оси Z по часовой стрелке `laps_count` полных оборотов на высоте `altitude` м с угловой скоростью
-/// `angular_velocity` м/с. Не блокирует вызывающий поток.
-pub fn spin(laps_count: i32, altitude: f32, angular_velocity: f32) {
-    SPIN_CLIENT.req_async(SpinReq {
+/// `angular_velocity` м/с. Блокирует вызывающий поток, пока дрон не прекратит вращение.
+pub fn spin_and_wait(
+    laps_count: i32,
+    altitude: f32,
+    angular_velocity: f32,
+) -> Result<(), ServiceError> {
+    metrics::record_spin();
+    SPIN_CLIENT.call(&SpinReq {
         laps_count,
         altitude,
         angular_velocity,
-    });
-}
-
-/// См. `service_clients::spin`. Блокирует вызывающий поток, пока дрон не прекратит вращение.
-pub fn spin_and_wait(laps_count: i32, altitude: f32, angular_velocity: f32) {
-    SPIN_CLIENT
-        .req(&SpinReq {
-            laps_count,
-            altitude,
-            angular_velocity,
-        })
-        .unwrap()
-        .unwrap();
+    })?;
+    Ok(())
 }
 
 /// Останавливает вращение.
-pub fn stop_spinning() {
-    STOP_SPIN_CLIENT.req(&EmptyReq {}).unwrap().unwrap();
+pub fn stop_spinning() -> Result<(), ServiceError> {
+    STOP_SPIN_CLIENT.call(&EmptyReq {})?;
+    Ok(())
 }
 
 /// Начинает автономное исследование, переключает FastPlanner на FUEL.
-pub fn start_exploration() {
-    USE_FUEL_CLIENT
-        .req(&SetBoolReq { data: true })
-        .unwrap()
-        .unwrap();
+pub fn start_exploration() -> Result<(), ServiceError> {
+    USE_FUEL_CLIENT.call(&SetBoolReq { data: true })?;
+    Ok(())
 }
 
 /// Останавливает автономное исследование, переключая FUEL на FastPlanner.
-pub fn pause_exploration() {
-    USE_FUEL_CLIENT
-        .req(&SetBoolReq { data: false })
-        .unwrap()
-        .unwrap();
+pub fn pause_exploration() -> Result<(), ServiceError> {
+    USE_FUEL_CLIENT.call(&SetBoolReq { data: false })?;
+    Ok(())
 }
 
-/// Возвращает ближайший к дрону обнаруженный проём. Если ещё не найден ни один проём, возвращает `None`.
-pub fn get_closest_hole() -> Option<DetectedObject> {
-    match GET_NEAREST_HOLE.req(&NearestPosReq {
+/// Возвращает ближайший к дрону обнаруженный проём. Если ещё не найден ни один проём (pos_collector отклоняет запрос
+/// или возвращает пустой список), возвращает `None`.
+pub fn get_closest_hole() -> Result<Option<DetectedObject>, ServiceError> {
+    let result = GET_NEAREST_HOLE.call(&NearestPosReq {
         myPose: PoseStamped {
             header: new_header("base_link"),
             pose: Pose {
@@ -208,102 +531,94 @@ pub fn get_closest_hole() -> Option<DetectedObject> {
                 orientation: default_quaternion(),
             },
         },
-    }) {
-        Ok(result) => match result {
-            Ok(mut result) => {
-                let mut result = result.nearestObj.detected_objects.swap_remove(0);
-                geometry::fix_hole(&mut result);
-                Some(result)
-            }
-            Err(_) => None,
-        },
-        Err(_) => None,
+    });
+    match result {
+        // pos_collector может вернуть и пустой список проёмов
+        Ok(result) => Ok(result
+            .nearestObj
+            .detected_objects
+            .into_iter()
+            .next()
+            .map(|mut hole| {
+                geometry::fix_hole(&mut hole);
+                hole
+            })),
+        Err(ServiceError::Rejected { .. }) => Ok(None),
+        Err(error) => Err(error),
     }
 }
 
 /// Возвращает все обнаруженные кубы.
-pub fn get_cubes() -> DetectedObjects {
-    GET_ALL_CUBES_CLIENT
-        .req(&GetAllReq {})
-        .unwrap()
-        .unwrap()
-        .objects
+pub fn get_cubes() -> Result<DetectedObjects, ServiceError> {
+    Ok(GET_ALL_CUBES_CLIENT.call(&GetAllReq {})?.objects)
 }
 
 /// Возвращает количество обнаруженных кубов.
-pub fn count_cubes() -> usize {
-    COUNT_CUBES_CLIENT.req(&CountReq {}).unwrap().unwrap().count as usize
+pub fn count_cubes() -> Result<usize, ServiceError> {
+    Ok(COUNT_CUBES_CLIENT.call(&CountReq {})?.count as usize)
 }
 
 /// Возвращает все обнаруженные проёмы.
-pub fn get_holes() -> DetectedObjects {
-    let mut holes = GET_ALL_HOLES_CLIENT
-        .req(&GetAllReq {})
-        .unwrap()
-        .unwrap()
-        .objects;
+pub fn get_holes() -> Result<DetectedObjects, ServiceError> {
+    let mut holes = GET_ALL_HOLES_CLIENT.call(&GetAllReq {})?.objects;
     for hole in holes.detected_objects.iter_mut() {
         geometry::fix_hole(hole);
     }
-    holes
+    Ok(holes)
 }
 
 /// Возвращает количество обнаруженных проёмов.
-pub fn count_holes() -> usize {
-    COUNT_HOLES_CLIENT.req(&CountReq {}).unwrap().unwrap().count as usize
+pub fn count_holes() -> Result<usize, ServiceError> {
+    Ok(COUNT_HOLES_CLIENT.call(&CountReq {})?.count as usize)
 }
 
 /// Добавляет виртуальную стену `wall` в FastPlanner и FUEL. `wall` должен подаваться в СК "map".
-pub fn add_virtual_wall(wall: DetectedObject) {
-    ADD_VIRTUAL_WALL_CLIENT
-        .req(&AddWallsReq {
-            objects: DetectedObjects {
-                header: new_header("map"),
-                detected_objects: vec![wall],
-            },
-        })
-        .unwrap()
-        .unwrap();
+pub fn add_virtual_wall(wall: DetectedObject) -> Result<(), ServiceError> {
+    ADD_VIRTUAL_WALL_CLIENT.call(&AddWallsReq {
+        objects: DetectedObjects {
+            header: new_header("map"),
+            detected_objects: vec![wall],
+        },
+    })?;
+    Ok(())
 }
 
 /// Включает виртуальные стены в FastPlanner и FUEL.
-pub fn enable_virtual_walls() {
-    SET_ARE_WALLS_ENABLED_CLIENT
-        .req(&SetBoolReq { data: true })
-        .unwrap()
-        .unwrap();
+pub fn enable_virtual_walls() -> Result<(), ServiceError> {
+    SET_ARE_WALLS_ENABLED_CLIENT.call(&SetBoolReq { data: true })?;
+    Ok(())
 }
 
 /// Отключает виртуальные стены в FastPlanner и FUEL.
-pub fn disable_virtual_walls() {
-    SET_ARE_WALLS_ENABLED_CLIENT
-        .req(&SetBoolReq { data: false })
-        .unwrap()
-        .unwrap();
+pub fn disable_virtual_walls() -> Result<(), ServiceError> {
+    SET_ARE_WALLS_ENABLED_CLIENT.call(&SetBoolReq { data: false })?;
+    Ok(())
 }
 
 /// Возвращает точку `point` в указанной СК `target_frame_id`.
-/// Вызывает панику, если трансформация невозможна.
-pub fn transform_point(point: PointStamped, target_frame_id: &str) -> PointStamped {
-    TRANSFORM_POINT_CLIENT
-        .req(&TransformPointReq {
+/// Возвращает `ServiceError::Rejected`, если трансформация невозможна.
+pub fn transform_point(
+    point: PointStamped,
+    target_frame_id: &str,
+) -> Result<PointStamped, ServiceError> {
+    Ok(TRANSFORM_POINT_CLIENT
+        .call(&TransformPointReq {
             point,
             target_frame_id: target_frame_id.to_string(),
-        })
-        .unwrap()
-        .unwrap()
-        .point
+        })?
+        .point)
 }
 
 /// Возвращает позу `pose` в указанной СК `target_frame_id`.
-/// Вызывает панику, если трансформация невозможна.
-pub fn transform_pose(pose: PoseStamped, target_frame_id: &str) -> PoseStamped {
-    TRANSFORM_POSE_CLIENT
-        .req(&TransformPoseReq {
+/// Возвращает `ServiceError::Rejected`, если трансформация невозможна.
+pub fn transform_pose(
+    pose: PoseStamped,
+    target_frame_id: &str,
+) -> Result<PoseStamped, ServiceError> {
+    Ok(TRANSFORM_POSE_CLIENT
+        .call(&TransformPoseReq {
             pose,
             target_frame_id: target_frame_id.to_string(),
-        })
-        .unwrap()
-        .unwrap()
-        .pose
+        })?
+        .pose)
 }
@@ -0,0 +1,855 @@
+use crate::{
+    backend::{self, *},
+    geometry,
+    msgs::{
+        detection_msgs::{DetectedObject, DetectedObjects},
+        diagnostic_msgs::DiagnosticArray,
+        geometry_msgs::{self, Point, PointStamped, Pose, PoseStamped, Quaternion},
+        mavros_msgs::PositionTarget,
+        nav_msgs::Path,
+        nodes_monitor_msgs::Status,
+        qr_detector_msgs::{QRCode, QRCodeArray},
+        std_msgs::Header,
+        task_manager::{DroneStatus, MissionEvent},
+    },
+    task2,
+    task_manager::{self, TaskType},
+};
+use rosrust::{Duration, Time};
+use std::{
+    f64::consts::PI,
+    sync::{Arc, Condvar, Mutex, MutexGuard},
+    thread,
+};
+
+/// QR-код, расположенный в мире симулятора.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorldQr {
+    /// Позиция кода в СК map
+    pub position: Point,
+    /// Содержание кода
+    pub content: String,
+}
+
+/// Стена здания - вертикальный прямоугольник, стоящий на полу между точками `from` и `to` (координата z не
+/// учитывается) и имеющий высоту `height`. Стены закрывают объекты от датчиков дрона везде, кроме проёмов.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wall {
+    pub from: Point,
+    pub to: Point,
+    pub height: f64,
+}
+
+/// Модель разряда аккумулятора.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatteryModel {
+    /// Вольтаж линейно падает от `initial_voltage` на `drain_per_minute` В в минуту.
+    Linear {
+        initial_voltage: f32,
+        drain_per_minute: f32,
+    },
+    /// Вольтаж линейно интерполируется между точками (время симуляции (с), вольтаж), упорядоченными по времени.
+    /// До первой точки и после последней вольтаж постоянен.
+    Curve(Vec<(f64, f32)>),
+}
+
+impl BatteryModel {
+    /// Возвращает вольтаж аккумулятора через `seconds` с после начала симуляции.
+    pub fn get_voltage(&self, seconds: f64) -> f32 {
+        match self {
+            BatteryModel::Linear {
+                initial_voltage,
+                drain_per_minute,
+            } => initial_voltage - drain_per_minute * (seconds / 60.0) as f32,
+            BatteryModel::Curve(points) => {
+                let next_index = match points.iter().position(|(time, _)| *time > seconds) {
+                    Some(0) => return points[0].1,
+                    Some(next_index) => next_index,
+                    None => return points.last().map(|(_, voltage)| *voltage).unwrap_or(0.0),
+                };
+                let (previous_time, previous_voltage) = points[next_index - 1];
+                let (next_time, next_voltage) = points[next_index];
+                let ratio = ((seconds - previous_time) / (next_time - previous_time)) as f32;
+                previous_voltage + (next_voltage - previous_voltage) * ratio
+            }
+        }
+    }
+}
+
+/// Мир, в котором летает симулируемый дрон.
+#[derive(Debug, Clone, PartialEq)]
+pub struct World {
+    /// Начальная поза дрона в СК map
+    pub start_pose: Pose,
+    /// Стены зданий и комнат
+    pub walls: Vec<Wall>,
+    /// Проёмы в СК map. Ориентация и размеры задаются так же, как после `geometry::fix_hole`: x - нормаль к
+    /// плоскости проёма (глубина), y - ширина, z - высота
+    pub holes: Vec<DetectedObject>,
+    /// Центры кубов в СК map
+    pub cubes: Vec<Point>,
+    /// QR-коды на стенах и на полу
+    pub qr_codes: Vec<WorldQr>,
+    /// Аккумулятор дрона
+    pub battery: BatteryModel,
+}
+
+/// Параметры симулятора.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulatorOptions {
+    /// Во сколько раз время симуляции идёт быстрее реального
+    pub time_scale: f64,
+    /// Шаг симуляции (с)
+    pub step: f64,
+    /// Расстояние (м), на котором дрон обнаруживает проёмы, кубы и QR-коды
+    pub sensor_range: f64,
+    /// Линейная скорость дрона (м/с)
+    pub linear_velocity: f64,
+    /// Расстояние до цели планировщика (м), на котором цель считается достигнутой
+    pub goal_tolerance: f64,
+    /// Период (с), с которым публикуются QR-коды, находящиеся в зоне обнаружения
+    pub qr_publish_period: f64,
+}
+
+impl Default for SimulatorOptions {
+    fn default() -> Self {
+        SimulatorOptions {
+            time_scale: 60.0,
+            step: 0.05,
+            sensor_range: 4.0,
+            linear_velocity: 0.5,
+            goal_tolerance: 0.05,
+            qr_publish_period: 0.5,
+        }
+    }
+}
+
+/// Результат симуляции задания.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationResult {
+    /// Название состояния конечного автомата задания в конце симуляции
+    pub final_state: String,
+    /// Названия всех опубликованных событий
+    pub events: Vec<String>,
+    /// Центры всех опубликованных кубов
+    pub published_cubes: Vec<Point>,
+    /// Номера последовательно пройденных комнат (для 2 задания)
+    pub passed_rooms: Vec<String>,
+    /// Поза дрона в конце симуляции
+    pub final_pose: Pose,
+    /// `true`, если дрон приземлился
+    pub has_landed: bool,
+    /// Время симуляции (с)
+    pub simulated_seconds: f64,
+}
+
+struct SimulatorState {
+    time: f64,
+    position: Point,
+    orientation: Quaternion,
+    goal: Option<(Pose, Option<GoalDoneCallback>)>,
+    altitude_target: Option<f64>,
+    spin_until: Option<f64>,
+    is_exploring: bool,
+    has_landed: bool,
+    is_stopped: bool,
+    are_virtual_walls_enabled: bool,
+    virtual_walls: Vec<DetectedObject>,
+    is_hole_detected: Vec<bool>,
+    is_cube_detected: Vec<bool>,
+    is_qr_seen: Vec<bool>,
+    detected_holes: Vec<DetectedObject>,
+    detected_cubes: Vec<DetectedObject>,
+    last_qr_publish_time: f64,
+    qr_codes_callbacks: Vec<Arc<dyn Fn(QRCodeArray) + Send + Sync>>,
+    events: Vec<String>,
+    mission_events: Vec<MissionEvent>,
+    statuses: Vec<DroneStatus>,
+    published_cubes: Vec<Point>,
+}
+
+/// Кинематический симулятор дрона, заменяющий собой mavros, FastPlanner/FUEL, hole_hunter, pos_collector и
+/// qr_detector. Дрон - материальная точка, движущаяся с постоянной скоростью к цели планировщика. Пока включено
+/// исследование, дрон летит к ближайшему ещё не обнаруженному объекту мира. Объекты считаются обнаруженными, когда
+/// дрон оказывается от них ближе `SimulatorOptions::sensor_range` и они не закрыты стенами. Столкновения со стенами
+/// не моделируются.
+///
+/// Время симуляции идёт в `SimulatorOptions::time_scale` раз быстрее реального, и все конечные автоматы заданий,
+/// работающие через `backend::backend()`, живут по нему.
+pub struct Simulator {
+    world: World,
+    options: SimulatorOptions,
+    state: Mutex<SimulatorState>,
+    tick: Condvar,
+}
+
+impl Simulator {
+    /// Создаёт симулятор мира `world` и запускает поток симуляции.
+    pub fn new(world: World, options: SimulatorOptions) -> Arc<Simulator> {
+        let state = SimulatorState {
+            time: 0.0,
+            position: world.start_pose.position.clone(),
+            orientation: world.start_pose.orientation.clone(),
+            goal: None,
+            altitude_target: None,
+            spin_until: None,
+            is_exploring: false,
+            has_landed: false,
+            is_stopped: false,
+            are_virtual_walls_enabled: false,
+            virtual_walls: Vec::new(),
+            is_hole_detected: vec![false; world.holes.len()],
+            is_cube_detected: vec![false; world.cubes.len()],
+            is_qr_seen: vec![false; world.qr_codes.len()],
+            detected_holes: Vec::new(),
+            detected_cubes: Vec::new(),
+            last_qr_publish_time: 0.0,
+            qr_codes_callbacks: Vec::new(),
+            events: Vec::new(),
+            mission_events: Vec::new(),
+            statuses: Vec::new(),
+            published_cubes: Vec::new(),
+        };
+        let simulator = Arc::new(Simulator {
+            world,
+            options,
+            state: Mutex::new(state),
+            tick: Condvar::new(),
+        });
+        let stepper = simulator.clone();
+        thread::Builder::new()
+            .name("simulator".to_string())
+            .spawn(move || stepper.run())
+            .unwrap();
+        simulator
+    }
+
+    /// Останавливает симуляцию. После остановки `Clock::is_ok` возвращает `false`, и все ожидающие потоки
+    /// разблокируются.
+    pub fn stop(&self) {
+        self.state.lock().unwrap().is_stopped = true;
+        self.tick.notify_all();
+    }
+
+    /// Возвращает текущее время симуляции (с).
+    pub fn get_time(&self) -> f64 {
+        self.state.lock().unwrap().time
+    }
+
+    /// Возвращает `true`, если дрон приземлился по команде `Motion::land`.
+    pub fn has_landed(&self) -> bool {
+        self.state.lock().unwrap().has_landed
+    }
+
+    /// Возвращает названия всех опубликованных событий.
+    pub fn get_events(&self) -> Vec<String> {
+        self.state.lock().unwrap().events.clone()
+    }
+
+    /// Возвращает все опубликованные события с их данными.
+    pub fn get_mission_events(&self) -> Vec<MissionEvent> {
+        self.state.lock().unwrap().mission_events.clone()
+    }
+
+    /// Возвращает все опубликованные статусы дрона.
+    pub fn get_statuses(&self) -> Vec<DroneStatus> {
+        self.state.lock().unwrap().statuses.clone()
+    }
+
+    /// Возвращает центры всех опубликованных кубов.
+    pub fn get_published_cubes(&self) -> Vec<Point> {
+        self.state.lock().unwrap().published_cubes.clone()
+    }
+
+    /// Возвращает `true`, если виртуальные стены включены.
+    pub fn are_virtual_walls_enabled(&self) -> bool {
+        self.state.lock().unwrap().are_virtual_walls_enabled
+    }
+
+    /// Возвращает добавленные виртуальные стены.
+    pub fn get_virtual_walls(&self) -> Vec<DetectedObject> {
+        self.state.lock().unwrap().virtual_walls.clone()
+    }
+
+    /// Блокирует вызывающий поток, пока не выполнится `condition`, либо пока симуляция не будет остановлена.
+    fn wait_until<F: Fn(&SimulatorState) -> bool>(&self, condition: F) {
+        let mut state = self.state.lock().unwrap();
+        while !state.is_stopped && !condition(&state) {
+            state = self.tick.wait(state).unwrap();
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<SimulatorState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Заменяет цель дрона на `goal`. Обработчик завершения прежней цели вызывается в отдельном потоке как при отмене
+    /// цели FastPlanner.
+    fn replace_goal(&self, goal: Option<(Pose, Option<GoalDoneCallback>)>) {
+        let previous_goal = std::mem::replace(&mut self.lock().goal, goal);
+        if let Some((_, Some(on_done))) = previous_goal {
+            thread::spawn(move || on_done(GoalOutcome::Canceled));
+        }
+    }
+
+    fn run(&self) {
+        let wall_step =
+            std::time::Duration::from_secs_f64(self.options.step / self.options.time_scale);
+        loop {
+            thread::sleep(wall_step);
+            let (done_callbacks, qr_codes) = {
+                let mut state = self.lock();
+                if state.is_stopped {
+                    break;
+                }
+                self.step(&mut state)
+            };
+            self.tick.notify_all();
+            // Обработчики вызываются в отдельных потоках, так как они могут обращаться к симулятору и блокироваться
+            // до следующих шагов симуляции.
+            for on_done in done_callbacks {
+                thread::spawn(move || on_done(GoalOutcome::Succeeded));
+            }
+            if let Some((callbacks, qr_codes)) = qr_codes {
+                for callback in callbacks {
+                    let qr_codes = qr_codes.clone();
+                    thread::spawn(move || callback(qr_codes));
+                }
+            }
+        }
+        self.tick.notify_all();
+    }
+
+    fn step(
+        &self,
+        state: &mut SimulatorState,
+    ) -> (
+        Vec<GoalDoneCallback>,
+        Option<(Vec<Arc<dyn Fn(QRCodeArray) + Send + Sync>>, QRCodeArray)>,
+    ) {
+        state.time += self.options.step;
+        let max_distance = self.options.linear_velocity * self.options.step;
+        let goal_position = state.goal.as_ref().map(|(pose, _)| pose.position.clone());
+        let mut done_callbacks = Vec::new();
+        if let Some(altitude) = state.altitude_target {
+            let delta = altitude - state.position.z;
+            if delta.abs() <= max_distance {
+                state.position.z = altitude;
+                state.altitude_target = None;
+            } else {
+                state.position.z += max_distance * delta.signum();
+            }
+        } else if let Some(goal_position) = goal_position {
+            if move_towards(&mut state.position, &goal_position, max_distance)
+                <= self.options.goal_tolerance
+            {
+                let (pose, on_done) = state.goal.take().unwrap();
+                state.orientation = pose.orientation;
+                if let Some(on_done) = on_done {
+                    done_callbacks.push(on_done);
+                }
+            }
+        } else if state.is_exploring && state.spin_until.is_none() {
+            if let Some(target) = self.get_exploration_target(state) {
+                move_towards(&mut state.position, &target, max_distance);
+            }
+        }
+        if let Some(spin_until) = state.spin_until {
+            if state.time >= spin_until {
+                state.spin_until = None;
+            }
+        }
+        self.detect_objects(state);
+
+        if state.time - state.last_qr_publish_time < self.options.qr_publish_period
+            || state.qr_codes_callbacks.is_empty()
+        {
+            return (done_callbacks, None);
+        }
+        state.last_qr_publish_time = state.time;
+        let qr_codes: Vec<QRCode> = self
+            .world
+            .qr_codes
+            .iter()
+            .filter(|qr| self.is_visible(&state.position, &qr.position))
+            .map(|world_qr| {
+                let mut qr = QRCode::default();
+                qr.header = new_map_header(state.time);
+                qr.data = world_qr.content.clone();
+                qr.position.x = world_qr.position.x;
+                qr.position.y = world_qr.position.y;
+                qr.position.z = world_qr.position.z;
+                qr
+            })
+            .collect();
+        if qr_codes.is_empty() {
+            return (done_callbacks, None);
+        }
+        let mut qr_code_array = QRCodeArray::default();
+        qr_code_array.qr_codes = qr_codes;
+        (
+            done_callbacks,
+            Some((state.qr_codes_callbacks.clone(), qr_code_array)),
+        )
+    }
+
+    /// Возвращает точку, к которой летит исследующий дрон: ближайший ещё не обнаруженный объект мира на текущей
+    /// высоте дрона.
+    fn get_exploration_target(&self, state: &SimulatorState) -> Option<Point> {
+        let holes = self
+            .world
+            .holes
+            .iter()
+            .zip(state.is_hole_detected.iter())
+            .filter(|(_, is_detected)| !**is_detected)
+            .map(|(hole, _)| hole.pose.position.clone());
+        let cubes = self
+            .world
+            .cubes
+            .iter()
+            .zip(state.is_cube_detected.iter())
+            .filter(|(_, is_detected)| !**is_detected)
+            .map(|(cube, _)| cube.clone());
+        let qr_codes = self
+            .world
+            .qr_codes
+            .iter()
+            .zip(state.is_qr_seen.iter())
+            .filter(|(_, is_seen)| !**is_seen)
+            .map(|(qr, _)| qr.position.clone());
+        holes
+            .chain(cubes)
+            .chain(qr_codes)
+            .map(|mut point| {
+                point.z = state.position.z;
+                point
+            })
+            .min_by(|x, y| {
+                geometry::get_distance_between_points(x, &state.position)
+                    .partial_cmp(&geometry::get_distance_between_points(y, &state.position))
+                    .unwrap()
+            })
+    }
+
+    /// Возвращает `true`, если точка `target` находится в зоне обнаружения дрона, находящегося в точке
+    /// `position`, и не закрыта стенами.
+    fn is_visible(&self, position: &Point, target: &Point) -> bool {
+        let ray_length = geometry::get_distance_between_points(position, target);
+        if ray_length > self.options.sensor_range {
+            return false;
+        }
+        let ray = (target.x - position.x, target.y - position.y);
+        self.world.walls.iter().all(|wall| {
+            let wall_vector = (wall.to.x - wall.from.x, wall.to.y - wall.from.y);
+            let denominator = ray.0 * wall_vector.1 - ray.1 * wall_vector.0;
+            if denominator.abs() < 1e-9 {
+                return true;
+            }
+            let offset = (wall.from.x - position.x, wall.from.y - position.y);
+            let t = (offset.0 * wall_vector.1 - offset.1 * wall_vector.0) / denominator;
+            let u = (offset.0 * ray.1 - offset.1 * ray.0) / denominator;
+            // Объекты, висящие на стене, не должны закрываться этой же стеной.
+            if t < 0.0 || t * ray_length > ray_length - 0.05 || u < 0.0 || u > 1.0 {
+                return true;
+            }
+            let intersection = geometry::new_point(
+                position.x + t * ray.0,
+                position.y + t * ray.1,
+                position.z + t * (target.z - position.z),
+            );
+            if intersection.z < 0.0 || intersection.z > wall.height {
+                return true;
+            }
+            self.world.holes.iter().any(|hole| {
+                let horizontal_distance = ((intersection.x - hole.pose.position.x).powi(2)
+                    + (intersection.y - hole.pose.position.y).powi(2))
+                .sqrt();
+                horizontal_distance <= hole.dimensions.y / 2.0
+                    && (intersection.z - hole.pose.position.z).abs() <= hole.dimensions.z / 2.0
+            })
+        })
+    }
+
+    /// Отмечает обнаруженными все объекты мира, находящиеся в зоне обнаружения дрона.
+    fn detect_objects(&self, state: &mut SimulatorState) {
+        for (index, hole) in self.world.holes.iter().enumerate() {
+            if !state.is_hole_detected[index]
+                && self.is_visible(&state.position, &hole.pose.position)
+            {
+                state.is_hole_detected[index] = true;
+                let mut hole = hole.clone();
+                hole.id = state.detected_holes.len() as _;
+                state.detected_holes.push(hole);
+            }
+        }
+        for (index, cube) in self.world.cubes.iter().enumerate() {
+            if !state.is_cube_detected[index] && self.is_visible(&state.position, cube) {
+                state.is_cube_detected[index] = true;
+                let id = state.detected_cubes.len();
+                state.detected_cubes.push(DetectedObject {
+                    id: id as _,
+                    pose: Pose {
+                        position: cube.clone(),
+                        orientation: geometry::default_quaternion(),
+                    },
+                    dimensions: geometry_msgs::Vector3 {
+                        x: 0.2,
+                        y: 0.2,
+                        z: 0.2,
+                    },
+                });
+            }
+        }
+        for (index, qr) in self.world.qr_codes.iter().enumerate() {
+            if self.is_visible(&state.position, &qr.position) {
+                state.is_qr_seen[index] = true;
+            }
+        }
+    }
+}
+
+/// Перемещает `position` к `target` не более чем на `max_distance`. Возвращает оставшееся до `target` расстояние.
+fn move_towards(position: &mut Point, target: &Point, max_distance: f64) -> f64 {
+    let distance = geometry::get_distance_between_points(position, target);
+    if distance <= max_distance {
+        *position = target.clone();
+        return 0.0;
+    }
+    let ratio = max_distance / distance;
+    position.x += (target.x - position.x) * ratio;
+    position.y += (target.y - position.y) * ratio;
+    position.z += (target.z - position.z) * ratio;
+    distance - max_distance
+}
+
+/// Создаёт Header в СК map со временем симуляции `time`. В отличие от `geometry::new_header`, не обращается к
+/// `backend::backend()`, поэтому может вызываться при заблокированном состоянии симулятора.
+fn new_map_header(time: f64) -> Header {
+    Header {
+        seq: 0,
+        stamp: Time::from_nanos((time * 1e9) as i64),
+        frame_id: "map".to_string(),
+    }
+}
+
+impl Planner for Simulator {
+    fn send_goal(&self, pose: Pose, on_done: Option<GoalDoneCallback>) {
+        self.replace_goal(Some((pose, on_done)));
+    }
+
+    fn cancel_all_goals(&self) {
+        self.replace_goal(None);
+    }
+
+    fn start_exploration(&self) {
+        self.lock().is_exploring = true;
+    }
+
+    fn pause_exploration(&self) {
+        self.lock().is_exploring = false;
+    }
+
+    fn reset_exploration(&self) {}
+
+    fn add_virtual_wall(&self, wall: DetectedObject) {
+        self.lock().virtual_walls.push(wall);
+    }
+
+    fn set_virtual_walls_enabled(&self, are_enabled: bool) {
+        self.lock().are_virtual_walls_enabled = are_enabled;
+    }
+}
+
+impl Perception for Simulator {
+    fn get_closest_hole(&self) -> Option<DetectedObject> {
+        let state = self.lock();
+        state
+            .detected_holes
+            .iter()
+            .min_by(|x, y| {
+                geometry::get_distance_between_points(&x.pose.position, &state.position)
+                    .partial_cmp(&geometry::get_distance_between_points(
+                        &y.pose.position,
+                        &state.position,
+                    ))
+                    .unwrap()
+            })
+            .cloned()
+    }
+
+    fn get_holes(&self) -> DetectedObjects {
+        let state = self.lock();
+        DetectedObjects {
+            header: new_map_header(state.time),
+            detected_objects: state.detected_holes.clone(),
+        }
+    }
+
+    fn count_holes(&self) -> usize {
+        self.lock().detected_holes.len()
+    }
+
+    fn get_cubes(&self) -> DetectedObjects {
+        let state = self.lock();
+        DetectedObjects {
+            header: new_map_header(state.time),
+            detected_objects: state.detected_cubes.clone(),
+        }
+    }
+
+    fn count_cubes(&self) -> usize {
+        self.lock().detected_cubes.len()
+    }
+
+    fn subscribe_qr_codes(&self, callback: QrCodesCallback) {
+        self.lock().qr_codes_callbacks.push(Arc::from(callback));
+    }
+
+    fn subscribe_line_points(&self, _: LinePointsCallback) {}
+}
+
+impl Motion for Simulator {
+    fn takeoff(&self, height: f32) {
+        {
+            let mut state = self.lock();
+            state.has_landed = false;
+            state.altitude_target = Some(height as f64);
+        }
+        self.wait_until(|state| state.altitude_target.is_none());
+    }
+
+    fn land(&self) {
+        {
+            let mut state = self.lock();
+            state.goal = None;
+            state.is_exploring = false;
+            state.spin_until = None;
+            state.altitude_target = Some(0.0);
+        }
+        self.wait_until(|state| state.altitude_target.is_none());
+        self.lock().has_landed = true;
+    }
+
+    fn spin(&self, laps_count: i32, altitude: f32, angular_velocity: f32) {
+        let mut state = self.lock();
+        state.altitude_target = Some(altitude as f64);
+        state.spin_until =
+            Some(state.time + laps_count as f64 * 2.0 * PI / angular_velocity as f64);
+    }
+
+    fn spin_and_wait(&self, laps_count: i32, altitude: f32, angular_velocity: f32) {
+        self.spin(laps_count, altitude, angular_velocity);
+        self.wait_until(|state| state.spin_until.is_none() && state.altitude_target.is_none());
+    }
+
+    fn stop_spinning(&self) {
+        self.lock().spin_until = None;
+    }
+
+    fn send_setpoint(&self, setpoint: PositionTarget) {
+        self.replace_goal(Some((
+            Pose {
+                position: setpoint.position,
+                orientation: geometry::default_quaternion(),
+            },
+            None,
+        )));
+    }
+}
+
+impl Transforms for Simulator {
+    fn transform_point(
+        &self,
+        mut point: PointStamped,
+        target_frame_id: &str,
+    ) -> Option<PointStamped> {
+        point.header.frame_id = target_frame_id.to_string();
+        Some(point)
+    }
+
+    fn transform_pose(&self, mut pose: PoseStamped, target_frame_id: &str) -> Option<PoseStamped> {
+        pose.header.frame_id = target_frame_id.to_string();
+        Some(pose)
+    }
+}
+
+impl Telemetry for Simulator {
+    fn get_current_drone_pose(&self) -> PoseStamped {
+        let state = self.lock();
+        PoseStamped {
+            header: new_map_header(state.time),
+            pose: Pose {
+                position: state.position.clone(),
+                orientation: state.orientation.clone(),
+            },
+        }
+    }
+
+    fn get_current_battery_voltage(&self) -> f32 {
+        self.world.battery.get_voltage(self.lock().time)
+    }
+}
+
+impl Reporter for Simulator {
+    fn publish_event(&self, event_name: &str) {
+        self.lock().events.push(event_name.to_string());
+    }
+
+    fn publish_mission_event(&self, event: MissionEvent) {
+        self.lock().mission_events.push(event);
+    }
+
+    fn publish_status(&self, status: DroneStatus) {
+        self.lock().statuses.push(status);
+    }
+
+    fn publish_nodes_monitor_status(&self, _: Status) {}
+
+    fn publish_diagnostics(&self, _: DiagnosticArray) {}
+
+    fn publish_detected_cube(&self, position: Point) {
+        self.lock().published_cubes.push(position);
+    }
+
+    fn publish_global_path(&self, _: Path) {}
+}
+
+impl Clock for Simulator {
+    fn now(&self) -> Time {
+        Time::from_nanos((self.lock().time * 1e9) as i64)
+    }
+
+    fn sleep(&self, duration: Duration) {
+        let until = self.lock().time + duration.seconds();
+        self.wait_until(|state| state.time >= until);
+    }
+
+    fn is_ok(&self) -> bool {
+        !self.lock().is_stopped
+    }
+}
+
+impl DroneBackend for Simulator {
+    fn init(&self, _: TaskType) {}
+}
+
+/// Выполняет задание `task_type` в мире `world`, пока дрон не приземлится, либо пока время симуляции не превысит
+/// `max_seconds`. Симулятор устанавливается активной реализацией `backend::DroneBackend` и останавливается по
+/// завершении. Журнал задания при симуляции не ведётся.
+pub fn run_mission(
+    task_type: TaskType,
+    world: World,
+    options: SimulatorOptions,
+    max_seconds: f64,
+) -> SimulationResult {
+    let simulator = Simulator::new(world, options);
+    backend::set_backend(simulator.clone());
+    let mut task_options = task_manager::OPTIONS.clone();
+    task_options.mission_log.directory = String::new();
+    task_manager::start_task_with_options(task_type, None, task_options);
+    simulator.wait_until(|state| state.has_landed || state.time >= max_seconds);
+    // Состояние конечного автомата меняется только после того, как завершится вызов Motion::land.
+    simulator.sleep(Duration::from_seconds(1));
+    let final_pose = simulator.get_current_drone_pose().pose;
+    let result = SimulationResult {
+        final_state: task_manager::get_task_state_name(task_type).to_string(),
+        events: simulator.get_events(),
+        published_cubes: simulator.get_published_cubes(),
+        passed_rooms: match task_type {
+            TaskType::Two => task2::drone_state::PASSED_ROOMS_NUMBERS
+                .lock()
+                .unwrap()
+                .clone(),
+            _ => Vec::new(),
+        },
+        final_pose,
+        has_landed: simulator.has_landed(),
+        simulated_seconds: simulator.get_time(),
+    };
+    simulator.stop();
+    result
+}
+
+fn new_hole(x: f64, y: f64, z: f64, yaw: f64) -> DetectedObject {
+    DetectedObject {
+        id: 0,
+        pose: Pose {
+            position: geometry::new_point(x, y, z),
+            orientation: Quaternion {
+                x: 0.0,
+                y: 0.0,
+                z: (yaw / 2.0).sin(),
+                w: (yaw / 2.0).cos(),
+            },
+        },
+        dimensions: geometry_msgs::Vector3 {
+            x: 0.05,
+            y: 1.0,
+            z: 1.0,
+        },
+    }
+}
+
+fn start_pose() -> Pose {
+    Pose {
+        position: geometry::default_point(),
+        orientation: geometry::default_quaternion(),
+    }
+}
+
+/// Здание с одним проёмом напротив точки старта и тремя кубами внутри.
+pub fn create_task1_world() -> World {
+    World {
+        start_pose: start_pose(),
+        walls: Vec::new(),
+        holes: vec![new_hole(3.0, 0.0, 1.5, 0.0)],
+        cubes: vec![
+            geometry::new_point(5.0, 1.5, 0.1),
+            geometry::new_point(7.0, -1.0, 0.1),
+            geometry::new_point(9.0, 2.0, 0.1),
+        ],
+        qr_codes: Vec::new(),
+        battery: BatteryModel::Linear {
+            initial_voltage: 12.6,
+            drain_per_minute: 0.15,
+        },
+    }
+}
+
+/// Три комнаты, соединённые двумя проёмами. QR-коды на полу первых двух комнат указывают на проёмы с такими же
+/// QR-кодами, QR-код "12" на полу последней комнаты - на точку посадки. QR-коды на полу находятся дальше зоны
+/// обнаружения от проёма, ведущего из их комнаты, поэтому из следующей комнаты они не видны.
+pub fn create_task2_world() -> World {
+    World {
+        start_pose: start_pose(),
+        walls: Vec::new(),
+        holes: vec![new_hole(4.0, 0.0, 1.0, 0.0), new_hole(8.0, 0.0, 1.0, 0.0)],
+        cubes: Vec::new(),
+        qr_codes: vec![
+            WorldQr {
+                position: geometry::new_point(0.0, 2.0, 0.0),
+                content: "1".to_string(),
+            },
+            WorldQr {
+                position: geometry::new_point(3.9, 0.4, 1.0),
+                content: "1".to_string(),
+            },
+            WorldQr {
+                position: geometry::new_point(5.0, 2.5, 0.0),
+                content: "2".to_string(),
+            },
+            WorldQr {
+                position: geometry::new_point(7.9, 0.4, 1.0),
+                content: "2".to_string(),
+            },
+            WorldQr {
+                position: geometry::new_point(10.0, 0.0, 0.0),
+                content: "12".to_string(),
+            },
+        ],
+        battery: BatteryModel::Linear {
+            initial_voltage: 12.6,
+            drain_per_minute: 0.15,
+        },
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{
+    backend::{self, backend},
+    battery::{self, BatteryLevel},
+    events::Failure,
+    metrics, mission_log,
+    msgs::task_manager::DroneStatus,
+    report, task1, task2,
+    task_manager::{self, TaskType},
+};
+use lazy_static::lazy_static;
+use rosrust::{ros_info, Time};
+use serde_json::json;
+use std::sync::Mutex;
+
+lazy_static! {
+    /// Состояние конечного автомата активного задания
+    static ref STATE: Mutex<StateInfo> = Mutex::new(StateInfo {
+        name: "WaitingForCommand",
+        description: String::new(),
+        is_error: false,
+        entered_at: None,
+    });
+    /// Последнее событие `Failure`
+    static ref LAST_FAILURE: Mutex<Option<Failure>> = Mutex::new(None);
+}
+
+struct StateInfo {
+    name: &'static str,
+    description: String,
+    is_error: bool,
+    entered_at: Option<Time>,
+}
+
+/// Запоминает новое состояние конечного автомата задания с названием `name` и описанием `description` и публикует
+/// статус дрона. При переходе в состояние `Landing` запускает создание отчёта о задании.
+pub fn set_state(name: &'static str, description: &str, is_error: bool) {
+    ros_info!("{}", description);
+    *STATE.lock().unwrap() = StateInfo {
+        name,
+        description: description.to_string(),
+        is_error,
+        entered_at: Some(backend().now()),
+    };
+    metrics::record_transition(name);
+    mission_log::record(
+        "transition",
+        json!({
+            "state": name,
+            "description": description,
+            "is_error": is_error,
+        }),
+    );
+    backend().publish_status(get_drone_status());
+    if name == "Landing" {
+        report::schedule();
+    }
+}
+
+/// Запоминает событие `failure` как последнюю причину невозможности продолжить задание.
+pub fn set_last_failure(failure: &Failure) {
+    *LAST_FAILURE.lock().unwrap() = Some(failure.clone());
+}
+
+/// Возвращает последнее событие `Failure`, если оно было.
+pub fn get_last_failure() -> Option<Failure> {
+    LAST_FAILURE.lock().unwrap().clone()
+}
+
+/// Возвращает текущий статус дрона.
+pub fn get_drone_status() -> DroneStatus {
+    let now = backend().now();
+    let mut status = {
+        let state = STATE.lock().unwrap();
+        DroneStatus {
+            state: state.description.clone(),
+            is_error: state.is_error,
+            state_name: state.name.to_string(),
+            state_seconds: state
+                .entered_at
+                .map(|entered_at| (now - entered_at).seconds())
+                .unwrap_or(0.0),
+            ..Default::default()
+        }
+    };
+    status.is_paused = *task_manager::IS_PAUSED.lock().unwrap();
+    status.battery_percentage = -1.0;
+    status.battery_level = DroneStatus::BATTERY_UNKNOWN;
+    if let Some(battery_status) = battery::get_battery_status() {
+        status.battery_voltage = battery_status.voltage;
+        status.battery_percentage = battery_status.percentage.unwrap_or(-1.0);
+        status.battery_level = match battery_status.level {
+            BatteryLevel::Normal => DroneStatus::BATTERY_NORMAL,
+            BatteryLevel::Warning => DroneStatus::BATTERY_WARNING,
+            BatteryLevel::Critical => DroneStatus::BATTERY_CRITICAL,
+        };
+    }
+    status.last_failure = get_last_failure()
+        .map(|failure| format!("{:?}", failure))
+        .unwrap_or_default();
+    let task_type = match task_manager::get_active_task() {
+        Some(task_type) => task_type,
+        None => return status,
+    };
+    status.task = task_type.number();
+    status.mission_id = task_manager::get_mission_id();
+    status.mission_seconds = task_manager::get_mission_seconds();
+    status.remaining_seconds = (task_manager::options().get_max_timer_minutes(task_type) as f64
+        * 60.0
+        - status.mission_seconds)
+        .max(0.0);
+    match task_type {
+        TaskType::One => {
+            status.cubes_found = task1::drone_state::DETECTED_CUBES.lock().unwrap().len() as u32;
+            status.cubes_count = task_manager::options().task1.cubes_count as u32;
+        }
+        TaskType::Two => {
+            status.passed_rooms = task2::drone_state::PASSED_ROOMS_NUMBERS
+                .lock()
+                .unwrap()
+                .clone();
+        }
+        TaskType::Three => {}
+    }
+    status
+}
+
+/// Сбрасывает последнее событие `Failure` и запускает поток, который с частотой `options().status_rate` публикует
+/// статус дрона.
+pub fn start_publishing() {
+    *LAST_FAILURE.lock().unwrap() = None;
+    let generation = task_manager::get_mission_generation();
+    std::thread::spawn(move || {
+        let rate = backend::rate(task_manager::options().status_rate);
+        while task_manager::is_mission_current(generation) {
+            backend().publish_status(get_drone_status());
+            rate.sleep();
+        }
+    });
+}
@@ -1,6 +1,5 @@
-use crate::{msgs::task_manager, topic_publishers::*};
+use crate::{msgs::detection_msgs::DetectedObject, status, task_manager};
 use lazy_static::lazy_static;
-use rosrust::ros_info;
 use std::{
     fmt::Debug,
     sync::{Arc, Mutex},
@@ -8,11 +7,17 @@ use std::{
 
 lazy_static! {
     static ref DRONE_STATE: Arc<Mutex<DroneState>> = {
-        handle_set_state("Drone is waiting for commands...", false);
+        status::set_state(
+            "WaitingForCommand",
+            "Drone is waiting for commands...",
+            false,
+        );
         Arc::new(Mutex::new(DroneState::WaitingForCommand(
             WaitingForCommand {},
         )))
     };
+    /// Найденные кубы, отсортированные по идентификатору
+    pub static ref DETECTED_CUBES: Mutex<Vec<DetectedObject>> = Mutex::new(Vec::new());
 }
 
 machine!(
@@ -20,29 +25,37 @@ machine!(
     enum DroneState {
         WaitingForCommand {},
         Landing,
-        FlyingInside,
+        FlyingInside { entry: DetectedObject },
         Exploring,
         ReturningToStartPoint,
         LookingForEntry,
+        Hovering,
     }
 );
 
-pub fn get_drone_state() -> DroneState {
-    DRONE_STATE.lock().unwrap().clone()
+impl DroneState {
+    /// Возвращает название состояния.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DroneState::Error => "Error",
+            DroneState::WaitingForCommand(_) => "WaitingForCommand",
+            DroneState::Landing(_) => "Landing",
+            DroneState::FlyingInside(_) => "FlyingInside",
+            DroneState::Exploring(_) => "Exploring",
+            DroneState::ReturningToStartPoint(_) => "ReturningToStartPoint",
+            DroneState::LookingForEntry(_) => "LookingForEntry",
+            DroneState::Hovering(_) => "Hovering",
+        }
+    }
 }
 
-fn handle_set_state(info_text: &str, is_error: bool) {
-    ros_info!("{}", info_text);
-    DRONE_STATUS_PUBLISHER
-        .send(task_manager::DroneStatus {
-            state: info_text.to_string(),
-            is_error,
-        })
-        .unwrap();
+pub fn get_drone_state() -> DroneState {
+    DRONE_STATE.lock().unwrap().clone()
 }
 
 pub fn set_drone_state(drone_state: DroneState) {
-    handle_set_state(
+    status::set_state(
+        drone_state.name(),
         match &drone_state {
             DroneState::Error => "Drone state is invalid due to wrong transition!",
             DroneState::WaitingForCommand(_) => "Drone is waiting for commands...",
@@ -53,6 +66,7 @@ pub fn set_drone_state(drone_state: DroneState) {
             DroneState::Exploring(_) => "Drone is exploring...",
             DroneState::ReturningToStartPoint(_) => "Drone is returning to the start point...",
             DroneState::LookingForEntry(_) => "Drone is looking for entry to building...",
+            DroneState::Hovering(_) => "Drone is hovering...",
         },
         match &drone_state {
             DroneState::Error => true,
@@ -61,3 +75,15 @@ pub fn set_drone_state(drone_state: DroneState) {
     );
     *DRONE_STATE.lock().unwrap() = drone_state;
 }
+
+/// Переводит конечный автомат из текущего состояния в состояние, которое возвращает для него `transition`. Переход
+/// выполняется через `task_manager::run_transition`.
+pub fn transition(transition: impl FnOnce(DroneState) -> DroneState) {
+    task_manager::run_transition(|| set_drone_state(transition(get_drone_state())));
+}
+
+/// Переводит конечный автомат в начальное состояние и очищает найденные кубы перед запуском задания.
+pub fn reset() {
+    DETECTED_CUBES.lock().unwrap().clear();
+    set_drone_state(DroneState::WaitingForCommand(WaitingForCommand {}));
+}
@@ -1,4 +1,7 @@
-use crate::{events::log_event, msgs::detection_msgs::DetectedObject};
+use crate::{
+    events::{log_event, EventKind, EventPayload},
+    msgs::detection_msgs::DetectedObject,
+};
 
 /// Событие происходит однократно, когда дрон находит вход в исследуемое здание (первый найденный проём)
 #[derive(Debug, Clone, PartialEq)]
@@ -8,7 +11,11 @@ pub struct EntryFound {
 
 impl EntryFound {
     pub fn new(entry: DetectedObject) -> EntryFound {
-        log_event("EntryFound");
+        log_event(
+            EventKind::EntryFound,
+            "EntryFound",
+            EventPayload::Hole(entry.clone()),
+        );
         EntryFound { entry }
     }
 }
@@ -19,7 +26,11 @@ pub struct FlewInsideBuilding {}
 
 impl FlewInsideBuilding {
     pub fn new() -> FlewInsideBuilding {
-        log_event("FlewInsideBuilding");
+        log_event(
+            EventKind::FlewInsideBuilding,
+            "FlewInsideBuilding",
+            EventPayload::None,
+        );
         FlewInsideBuilding {}
     }
 }
@@ -30,7 +41,11 @@ pub struct FlewNearStartPoint {}
 
 impl FlewNearStartPoint {
     pub fn new() -> FlewNearStartPoint {
-        log_event("FlewNearStartPoint");
+        log_event(
+            EventKind::FlewNearStartPoint,
+            "FlewNearStartPoint",
+            EventPayload::None,
+        );
         FlewNearStartPoint {}
     }
 }
@@ -41,7 +56,11 @@ pub struct FoundAllCubes {}
 
 impl FoundAllCubes {
     pub fn new() -> FoundAllCubes {
-        log_event("FoundAllCubes");
+        log_event(
+            EventKind::FoundAllCubes,
+            "FoundAllCubes",
+            EventPayload::None,
+        );
         FoundAllCubes {}
     }
 }
@@ -1,17 +1,14 @@
 use std::thread;
 
 use crate::{
-    events::Failure,
-    geometry::{self, default_point, default_quaternion, new_header},
-    msgs::{
-        detection_msgs::DetectedObject, fast_planner_server::FastPlannerGoal, geometry_msgs::Pose,
-        nodes_monitor_msgs::Status,
-    },
-    service_clients::*,
+    backend::{self, backend, GoalOutcome},
+    commands::{Abort, AbortMode, Pause, Resume},
+    events::{log_event, EventKind, EventPayload, Failure},
+    geometry,
+    msgs::{detection_msgs::DetectedObject, nodes_monitor_msgs::Status},
+    return_budget,
     task1::{commands::Start, drone_state::*, events::*},
     task_manager,
-    topic_publishers::*,
-    topic_subscribers::get_current_drone_pose,
 };
 
 transitions!(DroneState, [
@@ -25,31 +22,111 @@ transitions!(DroneState, [
     (FlyingInside, Failure) => ReturningToStartPoint,
     (Exploring, Failure) => ReturningToStartPoint,
     (ReturningToStartPoint, Failure) => ReturningToStartPoint,
-    (Landing, Failure) => Landing
+    (Landing, Failure) => Landing,
+    (Hovering, Failure) => Landing,
+
+    (LookingForEntry, Abort) => [Landing, ReturningToStartPoint, Hovering],
+    (FlyingInside, Abort) => [Landing, ReturningToStartPoint, Hovering],
+    (Exploring, Abort) => [Landing, ReturningToStartPoint, Hovering],
+    (ReturningToStartPoint, Abort) => [Landing, ReturningToStartPoint, Hovering],
+    (Hovering, Abort) => [Landing, ReturningToStartPoint, Hovering],
+    (Landing, Abort) => Landing,
+
+    (LookingForEntry, Pause) => LookingForEntry,
+    (FlyingInside, Pause) => FlyingInside,
+    (Exploring, Pause) => Exploring,
+    (ReturningToStartPoint, Pause) => ReturningToStartPoint,
+    (Hovering, Pause) => Hovering,
+    (Landing, Pause) => Landing,
+    (LookingForEntry, Resume) => LookingForEntry,
+    (FlyingInside, Resume) => FlyingInside,
+    (Exploring, Resume) => Exploring,
+    (ReturningToStartPoint, Resume) => ReturningToStartPoint,
+    (Hovering, Resume) => Hovering,
+    (Landing, Resume) => Landing
 ]);
 
+/// Останавливает дрона на месте до продолжения задания
+fn pause() {
+    backend().cancel_all_goals();
+    backend().stop_spinning();
+    backend().pause_exploration();
+}
+
+/// Отправляет FastPlanner цель - точку за проёмом `entry`
+fn send_entry_goal(entry: &DetectedObject) {
+    backend().send_goal(
+        geometry::get_entry_in_hole(
+            entry,
+            &backend().get_current_drone_pose().pose.position,
+            task_manager::options().flying_into_hole_pass_distance,
+        ),
+        None,
+    );
+}
+
+/// Отправляет FastPlanner цель - позу дрона в начале задания. По её достижении дрон переходит к посадке
+fn send_start_point_goal() {
+    let generation = task_manager::get_mission_generation();
+    backend().send_goal(
+        task_manager::get_home_pose(),
+        Some(Box::new(move |outcome| {
+            // Отменённая при прерывании или приостановке задания цель, как и цель предыдущего запуска задания, не
+            // считается достигнутой. IS_OK не проверяется: он равен false и при возврате на точку старта после сбоя
+            if outcome != GoalOutcome::Succeeded
+                || !task_manager::is_mission_current(generation)
+                || !matches!(get_drone_state(), DroneState::ReturningToStartPoint(_))
+                || *task_manager::IS_PAUSED.lock().unwrap()
+            {
+                return;
+            }
+            transition(|state| state.on_flew_near_start_point(FlewNearStartPoint::new()));
+        })),
+    );
+}
+
+/// Останавливает текущие действия дрона и переводит его в состояние, соответствующее режиму прерывания
+fn abort(abort: Abort) -> DroneState {
+    backend().cancel_all_goals();
+    backend().stop_spinning();
+    backend().pause_exploration();
+    match abort.mode {
+        AbortMode::Land => DroneState::Landing(Landing::new()),
+        AbortMode::Return => DroneState::ReturningToStartPoint(ReturningToStartPoint::new()),
+        AbortMode::Hover => DroneState::Hovering(Hovering::new()),
+    }
+}
+
 impl WaitingForCommand {
     pub fn on_start(self, command: Start) -> LookingForEntry {
-        NODES_MONITOR_PUBLISHER
-            .send(Status {
-                status: Status::STARTED,
-            })
-            .unwrap();
-        thread::spawn(|| {
+        backend().publish_nodes_monitor_status(Status {
+            status: Status::STARTED,
+        });
+        let generation = task_manager::get_mission_generation();
+        thread::spawn(move || {
             let mut cubes = Vec::new();
-            let rate = rosrust::rate(1.0);
-            while rosrust::is_ok() {
+            let rate = backend::rate(1.0);
+            while task_manager::is_mission_current(generation) {
                 let old_len = cubes.len();
-                let new_len = count_cubes();
+                let new_len = backend().count_cubes();
                 if old_len == new_len {
                     rate.sleep();
                     continue;
                 }
-                cubes = get_cubes().detected_objects;
+                cubes = backend().get_cubes().detected_objects;
                 cubes.sort_by(|x, y| x.id.cmp(&y.id));
                 for new_cube in cubes.iter().skip(old_len) {
-                    publish_new_detected_cube(new_cube.pose.position.clone());
+                    log_event(
+                        EventKind::CubeFound,
+                        "CubeFound",
+                        EventPayload::Cube {
+                            id: new_cube.id as i32,
+                            position: new_cube.pose.position.clone(),
+                        },
+                    );
+                    backend().publish_detected_cube(new_cube.pose.position.clone());
                 }
+                *DETECTED_CUBES.lock().unwrap() = cubes.clone();
 
                 rate.sleep();
             }
@@ -60,20 +137,24 @@ impl WaitingForCommand {
 
 impl LookingForEntry {
     pub fn new() -> LookingForEntry {
-        thread::spawn(|| {
-            let mut entry = get_closest_hole();
-            while entry.is_none() && *task_manager::IS_OK.lock().unwrap() {
-                rosrust::sleep(rosrust::Duration::from_seconds(1));
-                entry = get_closest_hole();
+        let generation = task_manager::get_mission_generation();
+        thread::spawn(move || {
+            let is_ok = || {
+                task_manager::is_mission_current(generation) && *task_manager::IS_OK.lock().unwrap()
+            };
+            let mut entry = backend().get_closest_hole();
+            while (entry.is_none() || *task_manager::IS_PAUSED.lock().unwrap()) && is_ok() {
+                backend().sleep(rosrust::Duration::from_seconds(1));
+                entry = backend().get_closest_hole();
             }
-            stop_spinning();
-            if !*task_manager::IS_OK.lock().unwrap() {
+            backend().stop_spinning();
+            if !is_ok() {
                 return;
             }
-            set_drone_state(get_drone_state().on_entry_found(EntryFound::new(entry.unwrap())));
+            transition(|state| state.on_entry_found(EntryFound::new(entry.unwrap())));
         });
         thread::spawn(|| {
-            takeoff(task_manager::OPTIONS.operating_altitude);
+            backend().takeoff(task_manager::options().operating_altitude);
         });
 
         LookingForEntry {}
@@ -86,66 +167,100 @@ impl LookingForEntry {
     pub fn on_failure(self, _: Failure) -> Landing {
         Landing::new()
     }
+
+    pub fn on_abort(self, command: Abort) -> DroneState {
+        abort(command)
+    }
+
+    pub fn on_pause(self, _: Pause) -> LookingForEntry {
+        pause();
+        self
+    }
+
+    pub fn on_resume(self, _: Resume) -> LookingForEntry {
+        self
+    }
 }
 
 impl Exploring {
     pub fn new() -> Exploring {
-        thread::spawn(|| {
-            enable_virtual_walls();
-            spin_and_wait(
+        let generation = task_manager::get_mission_generation();
+        thread::spawn(move || {
+            backend().set_virtual_walls_enabled(true);
+            backend().spin_and_wait(
                 1,
-                task_manager::OPTIONS.operating_altitude,
-                task_manager::OPTIONS.angular_velocity,
+                task_manager::options().operating_altitude,
+                task_manager::options().angular_velocity,
             );
-            start_exploration();
+            if !task_manager::is_mission_current(generation)
+                || !*task_manager::IS_OK.lock().unwrap()
+                || *task_manager::IS_PAUSED.lock().unwrap()
+            {
+                return;
+            }
+            backend().start_exploration();
         });
         Exploring {}
     }
 
     pub fn on_found_all_cubes(self, _: FoundAllCubes) -> ReturningToStartPoint {
-        pause_exploration();
+        backend().pause_exploration();
         ReturningToStartPoint::new()
     }
 
     pub fn on_failure(self, _: Failure) -> ReturningToStartPoint {
-        pause_exploration();
+        backend().pause_exploration();
         ReturningToStartPoint::new()
     }
+
+    pub fn on_abort(self, command: Abort) -> DroneState {
+        abort(command)
+    }
+
+    pub fn on_pause(self, _: Pause) -> Exploring {
+        pause();
+        self
+    }
+
+    pub fn on_resume(self, _: Resume) -> Exploring {
+        backend().start_exploration();
+        self
+    }
 }
 
 impl FlyingInside {
     pub fn new(entry: DetectedObject) -> FlyingInside {
-        cancel_all_goals();
-        FAST_PLANNER_SERVER_CLIENT
-            .lock()
-            .unwrap()
-            .build_goal_sender(FastPlannerGoal {
-                header: new_header("map"),
-                pose: geometry::get_entry_in_hole(
-                    &entry,
-                    &get_current_drone_pose().pose.position,
-                    task_manager::OPTIONS.flying_into_hole_pass_distance,
-                ),
-            })
-            .send();
-        thread::spawn(|| {
-            let rate = rosrust::rate(4.0);
-            let drone_position = get_current_drone_pose().pose.position;
+        backend().cancel_all_goals();
+        send_entry_goal(&entry);
+        let state = FlyingInside {
+            entry: entry.clone(),
+        };
+        let generation = task_manager::get_mission_generation();
+        thread::spawn(move || {
+            let rate = backend::rate(4.0);
+            let drone_position = backend().get_current_drone_pose().pose.position;
             let mut has_drone_flew_through_hole = geometry::has_drone_flew_through_hole(
                 geometry::DroneFlewThroughHoleResultParams::New {
                     hole_position: &entry.pose.position,
                     hole_orientation: &entry.pose.orientation,
-                    flying_into_hole_pass_distance: task_manager::OPTIONS
+                    flying_into_hole_pass_distance: task_manager::options()
                         .flying_into_hole_detection_distance
                         as f64,
-                    flying_into_hole_detection_pass_distance: task_manager::OPTIONS
+                    flying_into_hole_detection_pass_distance: task_manager::options()
                         .flying_into_hole_detection_pass_distance,
                     drone_position: &drone_position,
                 },
             );
 
-            while rosrust::is_ok() && *task_manager::IS_OK.lock().unwrap() {
-                let drone_position = get_current_drone_pose().pose.position;
+            while task_manager::is_mission_current(generation)
+                && *task_manager::IS_OK.lock().unwrap()
+            {
+                // Пролёт через проём не определяется по устаревшей позе
+                if *task_manager::IS_PAUSED.lock().unwrap() || !backend().is_drone_pose_fresh() {
+                    rate.sleep();
+                    continue;
+                }
+                let drone_position = backend().get_current_drone_pose().pose.position;
                 has_drone_flew_through_hole = geometry::has_drone_flew_through_hole(
                     geometry::DroneFlewThroughHoleResultParams::PreviousResult {
                         previous_result: has_drone_flew_through_hole,
@@ -153,17 +268,16 @@ impl FlyingInside {
                     },
                 );
                 if has_drone_flew_through_hole.flew_through {
-                    add_virtual_wall(entry);
-                    set_drone_state(
-                        get_drone_state().on_flew_inside_building(FlewInsideBuilding::new()),
-                    );
+                    return_budget::set_entry_point(Some(entry.pose.position.clone()));
+                    backend().add_virtual_wall(entry);
+                    transition(|state| state.on_flew_inside_building(FlewInsideBuilding::new()));
                     return;
                 }
                 rate.sleep();
             }
         });
 
-        FlyingInside {}
+        state
     }
 
     pub fn on_flew_inside_building(self, _: FlewInsideBuilding) -> Exploring {
@@ -173,31 +287,28 @@ impl FlyingInside {
     pub fn on_failure(self, _: Failure) -> ReturningToStartPoint {
         ReturningToStartPoint::new()
     }
+
+    pub fn on_abort(self, command: Abort) -> DroneState {
+        abort(command)
+    }
+
+    pub fn on_pause(self, _: Pause) -> FlyingInside {
+        pause();
+        self
+    }
+
+    pub fn on_resume(self, _: Resume) -> FlyingInside {
+        send_entry_goal(&self.entry);
+        self
+    }
 }
 
 impl ReturningToStartPoint {
     pub fn new() -> ReturningToStartPoint {
-        disable_virtual_walls();
-        cancel_all_goals();
-        stop_spinning();
-        FAST_PLANNER_SERVER_CLIENT
-            .lock()
-            .unwrap()
-            .build_goal_sender(FastPlannerGoal {
-                header: new_header("map"),
-                pose: Pose {
-                    position: default_point(),
-                    orientation: default_quaternion(),
-                },
-            })
-            .on_done({
-                move |_, _| {
-                    set_drone_state(
-                        get_drone_state().on_flew_near_start_point(FlewNearStartPoint::new()),
-                    );
-                }
-            })
-            .send();
+        backend().set_virtual_walls_enabled(false);
+        backend().cancel_all_goals();
+        backend().stop_spinning();
+        send_start_point_goal();
         ReturningToStartPoint {}
     }
 
@@ -208,15 +319,134 @@ impl ReturningToStartPoint {
     pub fn on_failure(self, _: Failure) -> ReturningToStartPoint {
         self
     }
+
+    pub fn on_abort(self, command: Abort) -> DroneState {
+        abort(command)
+    }
+
+    pub fn on_pause(self, _: Pause) -> ReturningToStartPoint {
+        pause();
+        self
+    }
+
+    pub fn on_resume(self, _: Resume) -> ReturningToStartPoint {
+        send_start_point_goal();
+        self
+    }
 }
 
 impl Landing {
     pub fn new() -> Landing {
-        land();
+        backend().land();
         Landing {}
     }
 
     pub fn on_failure(self, _: Failure) -> Landing {
         self
     }
+
+    pub fn on_abort(self, _: Abort) -> Landing {
+        self
+    }
+
+    pub fn on_pause(self, _: Pause) -> Landing {
+        self
+    }
+
+    pub fn on_resume(self, _: Resume) -> Landing {
+        self
+    }
+}
+
+impl Hovering {
+    pub fn new() -> Hovering {
+        backend().send_goal(backend().get_current_drone_pose().pose, None);
+        Hovering {}
+    }
+
+    pub fn on_failure(self, _: Failure) -> Landing {
+        Landing::new()
+    }
+
+    pub fn on_abort(self, command: Abort) -> DroneState {
+        abort(command)
+    }
+    pub fn on_pause(self, _: Pause) -> Hovering {
+        self
+    }
+
+    pub fn on_resume(self, _: Resume) -> Hovering {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        events::Failure,
+        fake_backend::testing,
+        geometry,
+        msgs::{detection_msgs::DetectedObject, geometry_msgs::Pose},
+        task_manager::{self, TaskType},
+    };
+
+    fn new_pose(x: f64, y: f64, z: f64) -> Pose {
+        Pose {
+            position: geometry::new_point(x, y, z),
+            orientation: geometry::default_quaternion(),
+        }
+    }
+
+    fn new_object(id: i64, x: f64, y: f64, z: f64) -> DetectedObject {
+        DetectedObject {
+            id: id as _,
+            pose: new_pose(x, y, z),
+            ..DetectedObject::default()
+        }
+    }
+
+    #[test]
+    fn explores_building_and_returns_after_failure() {
+        let mission = testing::start(TaskType::One, new_pose(0.0, 0.0, 0.0));
+        mission.wait_for_state("LookingForEntry", 5);
+
+        // Дрон у плоскости проёма, поэтому пролёт через него определяется по достижении цели за проёмом
+        mission.fake.set_drone_pose(new_pose(2.9, 0.0, 1.5));
+        mission.fake.add_hole(new_object(0, 3.0, 0.0, 1.5));
+        mission.wait_for_state("FlyingInside", 5);
+        mission.wait_until("entry goal", 5, |fake| fake.pending_goals().len() == 1);
+        mission.fake.complete_goals();
+        mission.wait_for_state("Exploring", 5);
+
+        mission.fake.add_cube(new_object(0, 5.0, 1.5, 0.1));
+        mission.wait_until("published cube", 5, |fake| fake.detected_cubes().len() == 1);
+
+        task_manager::handle_failure(TaskType::One, Failure::new_low_voltage_detected());
+        mission.wait_for_state("ReturningToStartPoint", 5);
+        assert_eq!(mission.fake.pending_goals(), vec![new_pose(0.0, 0.0, 0.0)]);
+        mission.fake.complete_goals();
+        mission.wait_for_state("Landing", 5);
+
+        assert_eq!(
+            mission.states(),
+            vec![
+                "WaitingForCommand",
+                "LookingForEntry",
+                "FlyingInside",
+                "Exploring",
+                "ReturningToStartPoint",
+                "Landing",
+            ]
+        );
+        assert_eq!(
+            mission.events(),
+            vec![
+                "EntryFound",
+                "FlewInsideBuilding",
+                "CubeFound",
+                "LowVoltageDetected",
+                "FlewNearStartPoint",
+            ]
+        );
+    }
 }
@@ -1,11 +1,9 @@
 use crate::{
     geometry,
-    msgs::{detection_msgs::*, geometry_msgs::*, task_manager::DroneStatus},
-    task_manager,
-    topic_publishers::*,
+    msgs::{detection_msgs::*, geometry_msgs::*},
+    status, task_manager,
 };
 use lazy_static::lazy_static;
-use rosrust::ros_info;
 use std::{
     collections::HashMap,
     fmt::Debug,
@@ -15,7 +13,11 @@ use std::{
 lazy_static! {
     /// Состояние дрона
     pub static ref DRONE_STATE: Arc<Mutex<Task2DroneState>> = {
-        handle_set_state("Drone is waiting for commands...", false);
+        status::set_state(
+            "WaitingForCommand",
+            "Drone is waiting for commands...",
+            false,
+        );
         Arc::new(Mutex::new(Task2DroneState::WaitingForCommand(
             WaitingForCommand {},
         )))
@@ -44,12 +46,35 @@ machine!(
     enum Task2DroneState {
         WaitingForCommand,
         Exploring,
-        FlyingIntoHole,
-        FlyingToLandingPoint,
+        FlyingIntoHole {
+            hole: DetectedObject,
+            qr_index: usize,
+        },
+        FlyingToLandingPoint {
+            landing_point: Point,
+        },
         Landing,
+        ReturningToStartPoint,
+        Hovering,
     }
 );
 
+impl Task2DroneState {
+    /// Возвращает название состояния.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Task2DroneState::Error => "Error",
+            Task2DroneState::WaitingForCommand(_) => "WaitingForCommand",
+            Task2DroneState::Exploring(_) => "Exploring",
+            Task2DroneState::FlyingIntoHole(_) => "FlyingIntoHole",
+            Task2DroneState::FlyingToLandingPoint(_) => "FlyingToLandingPoint",
+            Task2DroneState::Landing(_) => "Landing",
+            Task2DroneState::ReturningToStartPoint(_) => "ReturningToStartPoint",
+            Task2DroneState::Hovering(_) => "Hovering",
+        }
+    }
+}
+
 pub fn is_qr_already_detected(qr: &Qr) -> bool {
     let detected_qr_codes = &*DETECTED_QR_CODES.lock().unwrap();
     if qr.position == Point::default() {
@@ -58,9 +83,9 @@ pub fn is_qr_already_detected(qr: &Qr) -> bool {
                 .iter()
                 .any(|detected_qr| detected_qr.is_on_floor && detected_qr.content == qr.content);
     }
-    detected_qr_codes.iter().all(|detected_qr| {
+    detected_qr_codes.iter().any(|detected_qr| {
         geometry::get_distance_between_points(&detected_qr.position, &qr.position)
-            > (task_manager::OPTIONS.task2.max_qr_distance_tolerance as f64)
+            <= (task_manager::options().task2.max_qr_distance_tolerance as f64)
     })
 }
 
@@ -85,7 +110,7 @@ pub fn find_connected_hole(qr: &Qr) -> Option<DetectedObject> {
         .iter()
         .find(|hole| {
             geometry::get_distance_between_points(&hole.pose.position, &qr.position)
-                <= (task_manager::OPTIONS.task2.max_association_distance as f64)
+                <= (task_manager::options().task2.max_association_distance as f64)
         }) {
         Some(object) => Some(object.clone()),
         None => None,
@@ -95,25 +120,41 @@ pub fn find_connected_hole(qr: &Qr) -> Option<DetectedObject> {
 pub fn find_connected_qr(hole: &DetectedObject) -> Option<usize> {
     DETECTED_QR_CODES.lock().unwrap().iter().position(|qr| {
         geometry::get_distance_between_points(&hole.pose.position, &qr.position)
-            <= (task_manager::OPTIONS.task2.max_association_distance as f64)
+            <= (task_manager::options().task2.max_association_distance as f64)
     })
 }
 
-/// Находит и возвращает проём, связанный с QR-кодом с индексом qr_index
+/// Находит и возвращает проём, связанный с QR-кодом на стене, содержимое которого совпадает с содержимым QR-кода с
+/// индексом qr_index
 pub fn match_qr_index_with_hole(qr_index: usize) -> Option<DetectedObject> {
     rosrust::ros_info!("match_qr_index_with_hole");
-    let detected_hole_qr_connections = &*DETECTED_HOLE_QR_CONNECTIONS.lock().unwrap();
-    match detected_hole_qr_connections.get(&qr_index) {
-        Some(hole_id) => Some(
-            (*DETECTED_HOLES.lock().unwrap())
-                .detected_objects
-                .iter()
-                .find(|hole| &(hole.id as usize) == hole_id)
-                .unwrap()
-                .clone(),
-        ),
-        None => None,
-    }
+    // Блокировки не удерживаются одновременно: Exploring::go_on захватывает их в обратном порядке
+    let connections: Vec<(usize, usize)> = DETECTED_HOLE_QR_CONNECTIONS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(hole_id, hole_qr_index)| (*hole_id, *hole_qr_index))
+        .collect();
+    let hole_id = {
+        let detected_qr_codes = &*DETECTED_QR_CODES.lock().unwrap();
+        let qr_content = &detected_qr_codes.get(qr_index)?.content;
+        connections
+            .iter()
+            .find(|(_, hole_qr_index)| {
+                matches!(
+                    detected_qr_codes.get(*hole_qr_index),
+                    Some(hole_qr) if &hole_qr.content == qr_content
+                )
+            })?
+            .0
+    };
+    DETECTED_HOLES
+        .lock()
+        .unwrap()
+        .detected_objects
+        .iter()
+        .find(|hole| hole.id as usize == hole_id)
+        .cloned()
 }
 
 /// Проверяет, имеет ли нарисованный на полу QR-код с индексом qr_index то же содержимое,
@@ -135,7 +176,7 @@ pub fn match_qr_index_with_passed_rooms(qr_index: usize) -> Option<Qr> {
 pub fn match_qr_index_with_qr_on_floor(qr_index: usize) -> Option<usize> {
     rosrust::ros_info!("match_qr_index_with_qr_on_floor");
     let detected_qr_codes = &*DETECTED_QR_CODES.lock().unwrap();
-    let qr_content = &detected_qr_codes[qr_index].content;
+    let qr_content = &detected_qr_codes.get(qr_index)?.content;
     detected_qr_codes
         .iter()
         .position(|qr| qr.is_on_floor && &qr.content == qr_content)
@@ -144,19 +185,8 @@ pub fn match_qr_index_with_qr_on_floor(qr_index: usize) -> Option<usize> {
 /// Находит QR-код, связанный с дверью с ID hole_id. Если такой QR-код найден, находит и возвращает
 /// индекс QR-кода, нарисованного на полу и имеющего то же содержимое, что QR-код двери
 pub fn match_hole_id_with_qr_on_floor(hole_id: usize) -> Option<usize> {
-    let detected_hole_qr_connections = &*DETECTED_HOLE_QR_CONNECTIONS.lock().unwrap();
-    match detected_hole_qr_connections
-        .iter()
-        .find_map(|(qr_index, hole_id_)| {
-            if *hole_id_ == hole_id {
-                Some(qr_index)
-            } else {
-                None
-            }
-        }) {
-        Some(qr_index) => match_qr_index_with_qr_on_floor(*qr_index),
-        None => None,
-    }
+    let hole_qr_index = *DETECTED_HOLE_QR_CONNECTIONS.lock().unwrap().get(&hole_id)?;
+    match_qr_index_with_qr_on_floor(hole_qr_index)
 }
 
 /// QR-код
@@ -176,23 +206,14 @@ impl Qr {
         Qr {
             position: position,
             content,
-            is_on_floor: z < (task_manager::OPTIONS.task2.max_floor_z as f64),
+            is_on_floor: z < (task_manager::options().task2.max_floor_z as f64),
         }
     }
 }
 
-fn handle_set_state(info_text: &str, is_error: bool) {
-    ros_info!("{}", info_text);
-    DRONE_STATUS_PUBLISHER
-        .send(DroneStatus {
-            state: info_text.to_string(),
-            is_error,
-        })
-        .unwrap();
-}
-
 pub fn set_drone_state(state: Task2DroneState) {
-    handle_set_state(
+    status::set_state(
+        state.name(),
         match &state {
             Task2DroneState::FlyingIntoHole(_) => "Drone is flying into the hole...",
             Task2DroneState::FlyingToLandingPoint(_) => "Drone is flying to landing point...",
@@ -200,6 +221,8 @@ pub fn set_drone_state(state: Task2DroneState) {
             Task2DroneState::WaitingForCommand(_) => "Drone is waiting for commands...",
             Task2DroneState::Landing(_) => "Drone is landing...",
             Task2DroneState::Exploring(_) => "Drone is exploring...",
+            Task2DroneState::ReturningToStartPoint(_) => "Drone is returning to the start point...",
+            Task2DroneState::Hovering(_) => "Drone is hovering...",
         },
         match &state {
             Task2DroneState::Error => true,
@@ -212,3 +235,19 @@ pub fn set_drone_state(state: Task2DroneState) {
 pub fn get_drone_state() -> Task2DroneState {
     DRONE_STATE.lock().unwrap().clone()
 }
+
+/// Переводит конечный автомат из текущего состояния в состояние, которое возвращает для него `transition`. Переход
+/// выполняется через `task_manager::run_transition`.
+pub fn transition(transition: impl FnOnce(Task2DroneState) -> Task2DroneState) {
+    task_manager::run_transition(|| set_drone_state(transition(get_drone_state())));
+}
+
+/// Переводит конечный автомат в начальное состояние и очищает найденные QR-коды, проёмы и пройденные комнаты перед
+/// запуском задания.
+pub fn reset() {
+    DETECTED_QR_CODES.lock().unwrap().clear();
+    DETECTED_HOLES.lock().unwrap().detected_objects.clear();
+    DETECTED_HOLE_QR_CONNECTIONS.lock().unwrap().clear();
+    PASSED_ROOMS_NUMBERS.lock().unwrap().clear();
+    set_drone_state(Task2DroneState::WaitingForCommand(WaitingForCommand {}));
+}
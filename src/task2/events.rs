@@ -1,6 +1,9 @@
-use crate::{events::log_event, msgs::detection_msgs::DetectedObject};
+use crate::{
+    events::{log_event, EventKind, EventPayload},
+    msgs::detection_msgs::DetectedObject,
+};
 
-use super::drone_state::Qr;
+use super::drone_state::{Qr, DETECTED_QR_CODES};
 
 /// Событие происходит, когда дрон находит новый QR-код
 #[derive(Debug, Clone, PartialEq)]
@@ -11,7 +14,14 @@ pub struct QrFound {
 
 impl QrFound {
     pub fn new(qr: Qr, index: usize) -> QrFound {
-        log_event(&format!("QrFound ({:?})", &qr));
+        log_event(
+            EventKind::QrFound,
+            &format!("QrFound ({:?})", &qr),
+            EventPayload::Qr {
+                content: qr.content.clone(),
+                position: qr.position.clone(),
+            },
+        );
         QrFound { qr, index }
     }
 }
@@ -24,7 +34,11 @@ pub struct HoleFound {
 
 impl HoleFound {
     pub fn new(hole: DetectedObject) -> HoleFound {
-        log_event("HoleFound");
+        log_event(
+            EventKind::HoleFound,
+            "HoleFound",
+            EventPayload::Hole(hole.clone()),
+        );
         HoleFound { hole }
     }
 }
@@ -35,7 +49,11 @@ pub struct FlewNearLandingPoint {}
 
 impl FlewNearLandingPoint {
     pub fn new() -> FlewNearLandingPoint {
-        log_event("FlewNearLandingPoint");
+        log_event(
+            EventKind::FlewNearLandingPoint,
+            "FlewNearLandingPoint",
+            EventPayload::None,
+        );
         FlewNearLandingPoint {}
     }
 }
@@ -49,7 +67,31 @@ pub struct FlewThroughHole {
 
 impl FlewThroughHole {
     pub fn new(qr_index: usize) -> FlewThroughHole {
-        log_event("FlewThroughHole");
+        let qr = DETECTED_QR_CODES.lock().unwrap().get(qr_index).cloned();
+        log_event(
+            EventKind::FlewThroughHole,
+            "FlewThroughHole",
+            qr.map(|qr| EventPayload::Qr {
+                content: qr.content,
+                position: qr.position,
+            })
+            .unwrap_or(EventPayload::None),
+        );
         FlewThroughHole { qr_index }
     }
 }
+
+/// Событие происходит, когда дрон вернулся на исходную точку
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlewNearStartPoint {}
+
+impl FlewNearStartPoint {
+    pub fn new() -> FlewNearStartPoint {
+        log_event(
+            EventKind::FlewNearStartPoint,
+            "FlewNearStartPoint",
+            EventPayload::None,
+        );
+        FlewNearStartPoint {}
+    }
+}
@@ -1,27 +1,23 @@
 use std::thread;
 
 use crate::{
-    common_ros_utils::wait_for_topic,
+    backend::{self, backend, GoalOutcome},
+    commands::{Abort, AbortMode, Pause, Resume},
     events::Failure,
-    geometry::{self, new_header},
+    geometry,
     msgs::{
         detection_msgs::DetectedObject,
-        fast_planner_server::FastPlannerGoal,
         geometry_msgs::{Point, PointStamped, Pose},
         nodes_monitor_msgs::Status,
         qr_detector_msgs::QRCodeArray,
     },
-    service_clients::*,
     task2::{
         commands::Start,
         drone_state::{self, *},
         events::*,
     },
     task_manager,
-    topic_publishers::*,
-    topic_subscribers::get_current_drone_pose,
 };
-use lazy_static::{initialize, lazy_static};
 
 transitions!(Task2DroneState, [
     (WaitingForCommand, Start) => Exploring,
@@ -37,85 +33,194 @@ transitions!(Task2DroneState, [
     (FlyingToLandingPoint, FlewNearLandingPoint) => Landing,
     (Landing, QrFound) => Landing,
     (Landing, HoleFound) => Landing,
+    (ReturningToStartPoint, QrFound) => ReturningToStartPoint,
+    (ReturningToStartPoint, HoleFound) => ReturningToStartPoint,
+    (ReturningToStartPoint, FlewNearStartPoint) => Landing,
+    (Hovering, QrFound) => Hovering,
+    (Hovering, HoleFound) => Hovering,
 
     (Exploring, Failure) => Landing,
     (FlyingIntoHole, Failure) => Landing,
     (FlyingToLandingPoint, Failure) => FlyingToLandingPoint,
-    (Landing, Failure) => Landing
+    (Landing, Failure) => Landing,
+    (ReturningToStartPoint, Failure) => ReturningToStartPoint,
+    (Hovering, Failure) => Landing,
+
+    (Exploring, Abort) => [Landing, ReturningToStartPoint, Hovering],
+    (FlyingIntoHole, Abort) => [Landing, ReturningToStartPoint, Hovering],
+    (FlyingToLandingPoint, Abort) => [Landing, ReturningToStartPoint, Hovering],
+    (ReturningToStartPoint, Abort) => [Landing, ReturningToStartPoint, Hovering],
+    (Hovering, Abort) => [Landing, ReturningToStartPoint, Hovering],
+    (Landing, Abort) => Landing,
+
+    (Exploring, Pause) => Exploring,
+    (FlyingIntoHole, Pause) => FlyingIntoHole,
+    (FlyingToLandingPoint, Pause) => FlyingToLandingPoint,
+    (ReturningToStartPoint, Pause) => ReturningToStartPoint,
+    (Hovering, Pause) => Hovering,
+    (Landing, Pause) => Landing,
+    (Exploring, Resume) => Exploring,
+    (FlyingIntoHole, Resume) => FlyingIntoHole,
+    (FlyingToLandingPoint, Resume) => FlyingToLandingPoint,
+    (ReturningToStartPoint, Resume) => ReturningToStartPoint,
+    (Hovering, Resume) => Hovering,
+    (Landing, Resume) => Landing
 ]);
 
-lazy_static! {
-    pub static ref QR_CODES_SUBSCRIBER: rosrust::Subscriber = {
-        wait_for_topic("vision/qr_codes");
-        rosrust::subscribe("vision/qr_codes", 1, |qrs: QRCodeArray| {
-            if !*task_manager::IS_OK.lock().unwrap() || qrs.qr_codes.is_empty() {
+/// Останавливает дрона на месте до продолжения задания
+fn pause() {
+    backend().cancel_all_goals();
+    backend().stop_spinning();
+    backend().pause_exploration();
+}
+
+/// Отправляет FastPlanner цель - точку за проёмом `hole`
+fn send_hole_goal(hole: &DetectedObject) {
+    backend().send_goal(
+        geometry::get_entry_in_hole(
+            hole,
+            &backend().get_current_drone_pose().pose.position,
+            task_manager::options().flying_into_hole_pass_distance,
+        ),
+        None,
+    );
+}
+
+/// Отправляет FastPlanner цель - точку посадки `landing_point`. По её достижении дрон переходит к посадке
+fn send_landing_point_goal(landing_point: &Point) {
+    let orientation = geometry::get_orientation_towards_point(landing_point);
+    let generation = task_manager::get_mission_generation();
+    backend().send_goal(
+        Pose {
+            position: landing_point.clone(),
+            orientation,
+        },
+        Some(Box::new(move |outcome| {
+            // Отменённая при прерывании или приостановке задания цель, как и цель предыдущего запуска задания, не
+            // считается достигнутой. IS_OK не проверяется: он равен false и при возврате на точку старта после сбоя
+            if outcome != GoalOutcome::Succeeded
+                || !task_manager::is_mission_current(generation)
+                || !matches!(get_drone_state(), Task2DroneState::FlyingToLandingPoint(_))
+                || *task_manager::IS_PAUSED.lock().unwrap()
+            {
                 return;
             }
-            for detected_qr in qrs.qr_codes {
-                let qr_point = transform_point(
-                    PointStamped {
-                        header: detected_qr.header,
-                        point: Point {
-                            x: detected_qr.position.x,
-                            y: detected_qr.position.y,
-                            z: detected_qr.position.z,
-                        },
-                    },
-                    "map",
-                )
-                .point;
-                let qr = Qr::new(qr_point, detected_qr.data);
-                if !drone_state::is_qr_already_detected(&qr) {
-                    let qr_index = drone_state::add_qr(qr.clone());
-                    let connected_hole = drone_state::find_connected_hole(&qr);
-                    if let Some(connected_hole) = connected_hole {
-                        drone_state::add_hole_qr_connection(connected_hole.id as usize, qr_index);
-                    }
-                    set_drone_state(get_drone_state().on_qr_found(QrFound::new(qr, qr_index)));
-                }
+            transition(|state| state.on_flew_near_landing_point(FlewNearLandingPoint::new()))
+        })),
+    );
+}
+
+/// Отправляет FastPlanner цель - позу дрона в начале задания. По её достижении дрон переходит к посадке
+fn send_start_point_goal() {
+    let generation = task_manager::get_mission_generation();
+    backend().send_goal(
+        task_manager::get_home_pose(),
+        Some(Box::new(move |outcome| {
+            // Отменённая при прерывании или приостановке задания цель, как и цель предыдущего запуска задания, не
+            // считается достигнутой. IS_OK не проверяется: он равен false и при возврате на точку старта после сбоя
+            if outcome != GoalOutcome::Succeeded
+                || !task_manager::is_mission_current(generation)
+                || !matches!(get_drone_state(), Task2DroneState::ReturningToStartPoint(_))
+                || *task_manager::IS_PAUSED.lock().unwrap()
+            {
+                return;
             }
-        })
-        .unwrap()
-    };
+            transition(|state| state.on_flew_near_start_point(FlewNearStartPoint::new()));
+        })),
+    );
+}
+
+/// Останавливает текущие действия дрона и переводит его в состояние, соответствующее режиму прерывания
+fn abort(abort: Abort) -> Task2DroneState {
+    backend().cancel_all_goals();
+    backend().stop_spinning();
+    backend().pause_exploration();
+    match abort.mode {
+        AbortMode::Land => Task2DroneState::Landing(Landing::new()),
+        AbortMode::Return => Task2DroneState::ReturningToStartPoint(ReturningToStartPoint::new()),
+        AbortMode::Hover => Task2DroneState::Hovering(Hovering::new()),
+    }
+}
+
+/// Обрабатывает обнаруженные QR-коды: добавляет новые в drone_state::DETECTED_QR_CODES, по возможности обнаруживает и
+/// добавляет связь с ранее найденным проёмом в drone_state::DETECTED_HOLE_QR_CONNECTIONS. Вызывает событие on_qr_found()
+fn handle_qr_codes(qrs: QRCodeArray) {
+    if !*task_manager::IS_OK.lock().unwrap()
+        || *task_manager::IS_PAUSED.lock().unwrap()
+        || qrs.qr_codes.is_empty()
+    {
+        return;
+    }
+    for detected_qr in qrs.qr_codes {
+        let qr_point = match backend().transform_point(
+            PointStamped {
+                header: detected_qr.header,
+                point: Point {
+                    x: detected_qr.position.x,
+                    y: detected_qr.position.y,
+                    z: detected_qr.position.z,
+                },
+            },
+            "map",
+        ) {
+            Some(qr_point) => qr_point.point,
+            // QR-код с неизвестным положением в СК map не учитывается
+            None => {
+                rosrust::ros_warn!(
+                    "QR code \"{}\" is skipped: its position is unknown",
+                    detected_qr.data
+                );
+                continue;
+            }
+        };
+        let qr = Qr::new(qr_point, detected_qr.data);
+        if !drone_state::is_qr_already_detected(&qr) {
+            let qr_index = drone_state::add_qr(qr.clone());
+            let connected_hole = drone_state::find_connected_hole(&qr);
+            if let Some(connected_hole) = connected_hole {
+                drone_state::add_hole_qr_connection(connected_hole.id as usize, qr_index);
+            }
+            transition(|state| state.on_qr_found(QrFound::new(qr, qr_index)));
+        }
+    }
 }
 
 impl WaitingForCommand {
     pub fn on_start(self, command: Start) -> Exploring {
-        NODES_MONITOR_PUBLISHER
-            .send(Status {
-                status: Status::STARTED,
-            })
-            .unwrap();
+        backend().publish_nodes_monitor_status(Status {
+            status: Status::STARTED,
+        });
 
         // Подписчик следит за обнаруженными QR-кодами, и при получении новых добавляет их в drone_state::DETECTED_QR_CODES.
         // Также по возможности обнаруживает и добавляет связь с ранее найденным проёмом в
         // drone_state::DETECTED_HOLE_QR_CONNECTIONS. Вызывает событие on_qr_found()
-        initialize(&QR_CODES_SUBSCRIBER);
+        backend().subscribe_qr_codes(Box::new(handle_qr_codes));
 
+        let generation = task_manager::get_mission_generation();
         // Поток следит за изменением количества обнаруженных проёмов в pos_collector и при добавлении новых
         // автоматически добавляет их в drone_state::DETECTED_HOLES. Также по возможности обнаруживает и добавляет связь с
         // ранее найденным QR-кодом в drone_state::DETECTED_HOLE_QR_CONNECTIONS. Вызывает событие on_hole_found()
         thread::Builder::new()
             .name("watch_pos_collector_holes_changes".to_string())
-            .spawn(|| {
+            .spawn(move || {
                 let mut count = 0;
-                let rate = rosrust::rate(1.0);
-                while rosrust::is_ok() && *task_manager::IS_OK.lock().unwrap() {
-                    let new_count = count_holes();
-                    if count == new_count {
+                let rate = backend::rate(1.0);
+                while task_manager::is_mission_current(generation)
+                    && *task_manager::IS_OK.lock().unwrap()
+                {
+                    let new_count = backend().count_holes();
+                    if count == new_count || *task_manager::IS_PAUSED.lock().unwrap() {
                         rate.sleep();
                         continue;
                     }
                     // TODO: гарантировать, что pos_collector возвращает упорядоченные по id объекты
-                    let holes = get_holes();
+                    let holes = backend().get_holes();
                     for new_hole in holes.detected_objects.iter().skip(count) {
                         let connected_qr = drone_state::find_connected_qr(new_hole);
                         if let Some(connected_qr) = connected_qr {
                             drone_state::add_hole_qr_connection(new_hole.id as usize, connected_qr);
                         }
-                        set_drone_state(
-                            get_drone_state().on_hole_found(HoleFound::new(new_hole.clone())),
-                        );
+                        transition(|state| state.on_hole_found(HoleFound::new(new_hole.clone())));
                     }
                     *DETECTED_HOLES.lock().unwrap() = holes;
                     count = new_count;
@@ -136,47 +241,45 @@ impl WaitingForCommand {
     }
 }
 
+/// Возвращает `true`, если задание с номером запуска `generation` выполняется, не приостановлено и дрон исследует
+/// комнату
+fn is_exploring(generation: u64) -> bool {
+    task_manager::is_mission_current(generation)
+        && *task_manager::IS_OK.lock().unwrap()
+        && !*task_manager::IS_PAUSED.lock().unwrap()
+        && matches!(
+            drone_state::get_drone_state(),
+            Task2DroneState::Exploring(_)
+        )
+}
+
 impl Exploring {
     pub fn start() -> Exploring {
+        let generation = task_manager::get_mission_generation();
         thread::Builder::new()
             .name("start_exploring".to_string())
-            .spawn(|| {
-                takeoff(task_manager::OPTIONS.operating_altitude);
-                if !*task_manager::IS_OK.lock().unwrap()
-                    || !matches!(
-                        drone_state::get_drone_state(),
-                        Task2DroneState::Exploring(_)
-                    )
-                {
+            .spawn(move || {
+                backend().takeoff(task_manager::options().operating_altitude);
+                if !is_exploring(generation) {
                     return;
                 }
-                spin_and_wait(
+                backend().spin_and_wait(
                     1,
-                    task_manager::OPTIONS.low_altitude,
-                    task_manager::OPTIONS.angular_velocity,
+                    task_manager::options().low_altitude,
+                    task_manager::options().angular_velocity,
                 );
-                if !*task_manager::IS_OK.lock().unwrap()
-                    || !matches!(
-                        drone_state::get_drone_state(),
-                        Task2DroneState::Exploring(_)
-                    )
-                {
+                if !is_exploring(generation) {
                     return;
                 }
-                spin_and_wait(
+                backend().spin_and_wait(
                     1,
-                    task_manager::OPTIONS.operating_altitude,
-                    task_manager::OPTIONS.angular_velocity,
+                    task_manager::options().operating_altitude,
+                    task_manager::options().angular_velocity,
                 );
-                if !*task_manager::IS_OK.lock().unwrap()
-                    || !matches!(
-                        drone_state::get_drone_state(),
-                        Task2DroneState::Exploring(_)
-                    )
-                {
+                if !is_exploring(generation) {
                     return;
                 }
-                start_exploration();
+                backend().start_exploration();
             })
             .unwrap();
 
@@ -197,37 +300,28 @@ impl Exploring {
             );
             detected_qr_codes.clear();
         }
-        enable_virtual_walls();
+        backend().set_virtual_walls_enabled(true);
+        let generation = task_manager::get_mission_generation();
         thread::Builder::new()
             .name("go_on_exploring".to_string())
-            .spawn(|| {
-                spin_and_wait(
+            .spawn(move || {
+                backend().spin_and_wait(
                     1,
-                    task_manager::OPTIONS.low_altitude,
-                    task_manager::OPTIONS.angular_velocity,
+                    task_manager::options().low_altitude,
+                    task_manager::options().angular_velocity,
                 );
-                if !*task_manager::IS_OK.lock().unwrap()
-                    || !matches!(
-                        drone_state::get_drone_state(),
-                        Task2DroneState::Exploring(_)
-                    )
-                {
+                if !is_exploring(generation) {
                     return;
                 }
-                spin_and_wait(
+                backend().spin_and_wait(
                     1,
-                    task_manager::OPTIONS.operating_altitude,
-                    task_manager::OPTIONS.angular_velocity,
+                    task_manager::options().operating_altitude,
+                    task_manager::options().angular_velocity,
                 );
-                if !*task_manager::IS_OK.lock().unwrap()
-                    || !matches!(
-                        drone_state::get_drone_state(),
-                        Task2DroneState::Exploring(_)
-                    )
-                {
+                if !is_exploring(generation) {
                     return;
                 }
-                start_exploration();
+                backend().start_exploration();
             })
             .unwrap();
         Exploring {}
@@ -261,6 +355,20 @@ impl Exploring {
         Task2DroneState::Exploring(self)
     }
 
+    pub fn on_abort(self, command: Abort) -> Task2DroneState {
+        abort(command)
+    }
+
+    pub fn on_pause(self, _: Pause) -> Exploring {
+        pause();
+        self
+    }
+
+    pub fn on_resume(self, _: Resume) -> Exploring {
+        backend().start_exploration();
+        self
+    }
+
     pub fn on_failure(self, failure: Failure) -> Landing {
         Landing::new()
     }
@@ -268,39 +376,39 @@ impl Exploring {
 
 impl FlyingIntoHole {
     pub fn new(hole: DetectedObject, qr_index: usize) -> FlyingIntoHole {
-        cancel_all_goals();
-        pause_exploration();
-        let drone_position = get_current_drone_pose().pose.position;
-        FAST_PLANNER_SERVER_CLIENT
-            .lock()
-            .unwrap()
-            .build_goal_sender(FastPlannerGoal {
-                header: new_header("map"),
-                pose: geometry::get_entry_in_hole(
-                    &hole,
-                    &drone_position,
-                    task_manager::OPTIONS.flying_into_hole_pass_distance,
-                ),
-            })
-            .send();
+        backend().cancel_all_goals();
+        backend().pause_exploration();
+        send_hole_goal(&hole);
+        let state = FlyingIntoHole {
+            hole: hole.clone(),
+            qr_index,
+        };
+        let generation = task_manager::get_mission_generation();
         thread::spawn(move || {
-            let rate = rosrust::rate(4.0);
-            let drone_position = get_current_drone_pose().pose.position;
+            let rate = backend::rate(4.0);
+            let drone_position = backend().get_current_drone_pose().pose.position;
             let mut has_drone_flew_through_hole = geometry::has_drone_flew_through_hole(
                 geometry::DroneFlewThroughHoleResultParams::New {
                     hole_position: &hole.pose.position,
                     hole_orientation: &hole.pose.orientation,
-                    flying_into_hole_pass_distance: task_manager::OPTIONS
+                    flying_into_hole_pass_distance: task_manager::options()
                         .flying_into_hole_detection_distance
                         as f64,
-                    flying_into_hole_detection_pass_distance: task_manager::OPTIONS
+                    flying_into_hole_detection_pass_distance: task_manager::options()
                         .flying_into_hole_detection_pass_distance,
                     drone_position: &drone_position,
                 },
             );
 
-            while rosrust::is_ok() && *task_manager::IS_OK.lock().unwrap() {
-                let drone_position = get_current_drone_pose().pose.position;
+            while task_manager::is_mission_current(generation)
+                && *task_manager::IS_OK.lock().unwrap()
+            {
+                // Пролёт через проём не определяется по устаревшей позе
+                if *task_manager::IS_PAUSED.lock().unwrap() || !backend().is_drone_pose_fresh() {
+                    rate.sleep();
+                    continue;
+                }
+                let drone_position = backend().get_current_drone_pose().pose.position;
                 has_drone_flew_through_hole = geometry::has_drone_flew_through_hole(
                     geometry::DroneFlewThroughHoleResultParams::PreviousResult {
                         previous_result: has_drone_flew_through_hole,
@@ -308,16 +416,14 @@ impl FlyingIntoHole {
                     },
                 );
                 if has_drone_flew_through_hole.flew_through {
-                    add_virtual_wall(hole);
-                    set_drone_state(
-                        get_drone_state().on_flew_through_hole(FlewThroughHole::new(qr_index)),
-                    );
+                    backend().add_virtual_wall(hole);
+                    transition(|state| state.on_flew_through_hole(FlewThroughHole::new(qr_index)));
                     return;
                 }
                 rate.sleep();
             }
         });
-        FlyingIntoHole {}
+        state
     }
 
     pub fn on_qr_found(self, qr_found: QrFound) -> FlyingIntoHole {
@@ -335,29 +441,27 @@ impl FlyingIntoHole {
     pub fn on_failure(self, failure: Failure) -> Landing {
         Landing::new()
     }
+
+    pub fn on_abort(self, command: Abort) -> Task2DroneState {
+        abort(command)
+    }
+
+    pub fn on_pause(self, _: Pause) -> FlyingIntoHole {
+        pause();
+        self
+    }
+
+    pub fn on_resume(self, _: Resume) -> FlyingIntoHole {
+        send_hole_goal(&self.hole);
+        self
+    }
 }
 
 impl FlyingToLandingPoint {
     pub fn new(landing_point: Point) -> FlyingToLandingPoint {
-        cancel_all_goals();
-        let orientation = geometry::get_orientation_towards_point(&landing_point);
-        FAST_PLANNER_SERVER_CLIENT
-            .lock()
-            .unwrap()
-            .build_goal_sender(FastPlannerGoal {
-                header: new_header("map"),
-                pose: Pose {
-                    position: landing_point,
-                    orientation,
-                },
-            })
-            .on_done(|_, _| {
-                set_drone_state(
-                    get_drone_state().on_flew_near_landing_point(FlewNearLandingPoint::new()),
-                )
-            })
-            .send();
-        FlyingToLandingPoint {}
+        backend().cancel_all_goals();
+        send_landing_point_goal(&landing_point);
+        FlyingToLandingPoint { landing_point }
     }
 
     pub fn on_flew_near_landing_point(
@@ -378,12 +482,26 @@ impl FlyingToLandingPoint {
     pub fn on_failure(self, failure: Failure) -> Self {
         self
     }
+
+    pub fn on_abort(self, command: Abort) -> Task2DroneState {
+        abort(command)
+    }
+
+    pub fn on_pause(self, _: Pause) -> Self {
+        pause();
+        self
+    }
+
+    pub fn on_resume(self, _: Resume) -> Self {
+        send_landing_point_goal(&self.landing_point);
+        self
+    }
 }
 
 impl Landing {
     pub fn new() -> Landing {
-        cancel_all_goals();
-        land();
+        backend().cancel_all_goals();
+        backend().land();
         Landing {}
     }
 
@@ -398,4 +516,201 @@ impl Landing {
     pub fn on_failure(self, failure: Failure) -> Self {
         self
     }
+    pub fn on_abort(self, _: Abort) -> Self {
+        self
+    }
+
+    pub fn on_pause(self, _: Pause) -> Self {
+        self
+    }
+
+    pub fn on_resume(self, _: Resume) -> Self {
+        self
+    }
+}
+
+impl ReturningToStartPoint {
+    pub fn new() -> ReturningToStartPoint {
+        backend().set_virtual_walls_enabled(false);
+        send_start_point_goal();
+        ReturningToStartPoint {}
+    }
+
+    pub fn on_flew_near_start_point(self, _: FlewNearStartPoint) -> Landing {
+        Landing::new()
+    }
+
+    pub fn on_qr_found(self, _: QrFound) -> Self {
+        self
+    }
+
+    pub fn on_hole_found(self, _: HoleFound) -> Self {
+        self
+    }
+
+    pub fn on_failure(self, _: Failure) -> Self {
+        self
+    }
+
+    pub fn on_abort(self, command: Abort) -> Task2DroneState {
+        abort(command)
+    }
+
+    pub fn on_pause(self, _: Pause) -> Self {
+        pause();
+        self
+    }
+
+    pub fn on_resume(self, _: Resume) -> Self {
+        send_start_point_goal();
+        self
+    }
+}
+
+impl Hovering {
+    pub fn new() -> Hovering {
+        backend().send_goal(backend().get_current_drone_pose().pose, None);
+        Hovering {}
+    }
+
+    pub fn on_qr_found(self, _: QrFound) -> Self {
+        self
+    }
+
+    pub fn on_hole_found(self, _: HoleFound) -> Self {
+        self
+    }
+
+    pub fn on_failure(self, _: Failure) -> Landing {
+        Landing::new()
+    }
+
+    pub fn on_abort(self, command: Abort) -> Task2DroneState {
+        abort(command)
+    }
+    pub fn on_pause(self, _: Pause) -> Self {
+        self
+    }
+
+    pub fn on_resume(self, _: Resume) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        fake_backend::testing,
+        geometry,
+        msgs::{
+            detection_msgs::DetectedObject,
+            geometry_msgs::Pose,
+            qr_detector_msgs::{QRCode, QRCodeArray},
+        },
+        service_clients::{ServiceError, TRANSFORM_POINT_SERVICE},
+        task2::drone_state::PASSED_ROOMS_NUMBERS,
+        task_manager::{self, TaskType},
+    };
+
+    fn new_pose(x: f64, y: f64, z: f64) -> Pose {
+        Pose {
+            position: geometry::new_point(x, y, z),
+            orientation: geometry::default_quaternion(),
+        }
+    }
+
+    fn new_qr_codes(content: &str, x: f64, y: f64, z: f64) -> QRCodeArray {
+        let mut qr = QRCode::default();
+        qr.header.frame_id = "map".to_string();
+        qr.data = content.to_string();
+        qr.position.x = x;
+        qr.position.y = y;
+        qr.position.z = z;
+        QRCodeArray {
+            qr_codes: vec![qr],
+            ..QRCodeArray::default()
+        }
+    }
+
+    #[test]
+    fn passes_room_and_lands_on_landing_point() {
+        let mission = testing::start(TaskType::Two, new_pose(0.0, 0.0, 0.0));
+        mission.wait_for_state("Exploring", 5);
+
+        // QR-код на полу указывает на проём, рядом с которым висит QR-код с тем же содержанием
+        mission.fake.push_qr_codes(new_qr_codes("1", 1.5, 0.0, 0.0));
+        mission
+            .fake
+            .push_qr_codes(new_qr_codes("1", 3.95, 0.4, 1.0));
+        mission.fake.set_drone_pose(new_pose(3.9, 0.0, 1.0));
+        mission.fake.add_hole(DetectedObject {
+            id: 5,
+            pose: new_pose(4.0, 0.0, 1.0),
+            ..DetectedObject::default()
+        });
+        mission.wait_for_state("FlyingIntoHole", 5);
+        mission.wait_until("hole goal", 5, |fake| fake.pending_goals().len() == 1);
+        mission.fake.complete_goals();
+        mission.wait_for_state("Exploring", 5);
+        assert_eq!(*PASSED_ROOMS_NUMBERS.lock().unwrap(), vec!["1"]);
+
+        // QR-код на полу второй комнаты совпадает с номерами пройденных комнат и указывает на точку посадки
+        mission.fake.push_qr_codes(new_qr_codes("1", 6.0, 0.5, 0.0));
+        mission.wait_for_state("FlyingToLandingPoint", 5);
+        mission.fake.complete_goals();
+        mission.wait_for_state("Landing", 5);
+
+        assert_eq!(
+            mission.states(),
+            vec![
+                "WaitingForCommand",
+                "Exploring",
+                "FlyingIntoHole",
+                "Exploring",
+                "FlyingToLandingPoint",
+                "Landing",
+            ]
+        );
+        assert_eq!(
+            mission.events(),
+            vec![
+                "QrFound",
+                "QrFound",
+                "HoleFound",
+                "FlewThroughHole",
+                "QrFound",
+                "FlewNearLandingPoint",
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_qr_code_when_transform_is_rejected() {
+        let mission = testing::start(TaskType::Two, new_pose(0.0, 0.0, 0.0));
+        mission.wait_for_state("Exploring", 5);
+
+        // Невозможное преобразование пропускает только затронутый QR-код
+        mission.fake.fail_next_transform(ServiceError::Rejected {
+            service: TRANSFORM_POINT_SERVICE,
+            message: "No transform to map".to_string(),
+        });
+        mission.fake.push_qr_codes(new_qr_codes("1", 1.5, 0.0, 0.0));
+        assert!(mission.events().is_empty());
+        assert!(*task_manager::IS_OK.lock().unwrap());
+        assert_eq!(
+            task_manager::get_task_state_name(TaskType::Two),
+            "Exploring"
+        );
+
+        mission.fake.push_qr_codes(new_qr_codes("1", 1.5, 0.0, 0.0));
+        assert_eq!(mission.events(), vec!["QrFound"]);
+
+        // Сервис преобразований, не ответивший вовремя, завершает задание
+        mission.fake.fail_next_transform(ServiceError::Timeout {
+            service: TRANSFORM_POINT_SERVICE,
+        });
+        mission.fake.push_qr_codes(new_qr_codes("2", 5.0, 0.0, 0.0));
+        mission.wait_for_state("Landing", 5);
+        assert_eq!(mission.events(), vec!["QrFound", "ServiceCallTimedOut"]);
+    }
 }
@@ -1,6 +1,5 @@
-use crate::{msgs::task_manager::DroneStatus, topic_publishers::*};
+use crate::{status, task3::transitions, task_manager};
 use lazy_static::lazy_static;
-use rosrust::ros_info;
 use std::{
     fmt::Debug,
     sync::{Arc, Mutex},
@@ -9,11 +8,19 @@ use std::{
 lazy_static! {
     /// Состояние дрона
     pub static ref DRONE_STATE: Arc<Mutex<Task3DroneState>> = {
-        handle_set_state("Drone is waiting for commands...", false);
+        status::set_state(
+            "WaitingForCommand",
+            "Drone is waiting for commands...",
+            false,
+        );
         Arc::new(Mutex::new(Task3DroneState::WaitingForCommand(
             WaitingForCommand {},
         )))
     };
+    /// Идентификатор последнего созданного состояния
+    static ref LAST_STATE_ID: Mutex<u64> = Mutex::new(0);
+    /// Идентификатор состояния, сохранённого в DRONE_STATE
+    static ref CURRENT_STATE_ID: Mutex<u64> = Mutex::new(0);
 }
 
 machine!(
@@ -21,27 +28,66 @@ machine!(
     enum Task3DroneState {
         WaitingForCommand,
         FollowingLine,
-        // Landing,
+        ReturningToStartPoint { id: u64 },
+        Hovering { id: u64 },
+        Landing,
     }
 );
 
-fn handle_set_state(info_text: &str, is_error: bool) {
-    ros_info!("{}", info_text);
-    DRONE_STATUS_PUBLISHER
-        .send(DroneStatus {
-            state: info_text.to_string(),
-            is_error,
-        })
-        .unwrap();
+impl Task3DroneState {
+    /// Возвращает название состояния.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Task3DroneState::Error => "Error",
+            Task3DroneState::WaitingForCommand(_) => "WaitingForCommand",
+            Task3DroneState::FollowingLine(_) => "FollowingLine",
+            Task3DroneState::ReturningToStartPoint(_) => "ReturningToStartPoint",
+            Task3DroneState::Hovering(_) => "Hovering",
+            Task3DroneState::Landing(_) => "Landing",
+        }
+    }
+}
+
+/// Возвращает новый идентификатор состояния. Поток, запущенный при создании состояния с этим идентификатором,
+/// выполняется, пока `is_state_current` возвращает для него `true`.
+pub fn new_state_id() -> u64 {
+    let mut last_state_id = LAST_STATE_ID.lock().unwrap();
+    *last_state_id += 1;
+    *last_state_id
+}
+
+/// Возвращает идентификатор состояния, сохранённого в DRONE_STATE.
+pub fn get_current_state_id() -> u64 {
+    *CURRENT_STATE_ID.lock().unwrap()
+}
+
+/// Возвращает `true`, если состояние с идентификатором `id` не сменилось другим. Состояние, которое создано, но ещё не
+/// сохранено в DRONE_STATE, также считается текущим.
+pub fn is_state_current(id: u64) -> bool {
+    get_current_state_id() <= id
+}
+
+/// Завершает потоки всех созданных к этому моменту состояний: `is_state_current` начинает возвращать для них `false`.
+pub fn stop_state_threads() {
+    let id = new_state_id();
+    *CURRENT_STATE_ID.lock().unwrap() = id;
 }
 
 pub fn set_drone_state(state: Task3DroneState) {
-    handle_set_state(
+    let id = match &state {
+        Task3DroneState::ReturningToStartPoint(state) => state.id,
+        Task3DroneState::Hovering(state) => state.id,
+        _ => new_state_id(),
+    };
+    status::set_state(
+        state.name(),
         match &state {
             Task3DroneState::Error => "Drone state is invalid due to wrong transition!",
             Task3DroneState::WaitingForCommand(_) => "Drone is waiting for commands...",
-            // Task3DroneState::Landing(_) => "Drone is landing...",
+            Task3DroneState::Landing(_) => "Drone is landing...",
             Task3DroneState::FollowingLine(_) => "Drone is following line...",
+            Task3DroneState::ReturningToStartPoint(_) => "Drone is returning to the start point...",
+            Task3DroneState::Hovering(_) => "Drone is hovering...",
         },
         match &state {
             Task3DroneState::Error => true,
@@ -49,8 +95,21 @@ pub fn set_drone_state(state: Task3DroneState) {
         },
     );
     *DRONE_STATE.lock().unwrap() = state;
+    *CURRENT_STATE_ID.lock().unwrap() = id;
 }
 
 pub fn get_drone_state() -> Task3DroneState {
     DRONE_STATE.lock().unwrap().clone()
 }
+
+/// Переводит конечный автомат из текущего состояния в состояние, которое возвращает для него `transition`. Переход
+/// выполняется через `task_manager::run_transition`.
+pub fn transition(transition: impl FnOnce(Task3DroneState) -> Task3DroneState) {
+    task_manager::run_transition(|| set_drone_state(transition(get_drone_state())));
+}
+
+/// Переводит конечный автомат в начальное состояние и очищает путь вдоль линии перед запуском задания.
+pub fn reset() {
+    transitions::clear_path();
+    set_drone_state(Task3DroneState::WaitingForCommand(WaitingForCommand {}));
+}
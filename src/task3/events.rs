@@ -1,3 +1,20 @@
+use crate::events::{log_event, EventKind, EventPayload};
+
 /// Событие происходит, когда дрон находит какое-то препятствие
 #[derive(Debug, Clone, PartialEq)]
 pub struct ObstructionFound {}
+
+/// Событие происходит, когда дрон вернулся на исходную точку
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlewNearStartPoint {}
+
+impl FlewNearStartPoint {
+    pub fn new() -> FlewNearStartPoint {
+        log_event(
+            EventKind::FlewNearStartPoint,
+            "FlewNearStartPoint",
+            EventPayload::None,
+        );
+        FlewNearStartPoint {}
+    }
+}
@@ -4,7 +4,9 @@ use std::{
 };
 
 use crate::{
-    common_ros_utils::wait_for_topic,
+    backend::{self, backend},
+    commands::{Abort, AbortMode},
+    events::Failure,
     geometry::{self, new_header},
     msgs::{
         geometry_msgs::{Point, PoseStamped, Vector3},
@@ -12,81 +14,132 @@ use crate::{
         nav_msgs::Path,
         nodes_monitor_msgs::Status,
     },
-    service_clients::*,
-    task3::{commands::Start, drone_state::*},
+    task3::{commands::Start, drone_state::*, events::*},
     task_manager,
-    topic_publishers::*,
-    topic_subscribers::get_current_drone_pose,
 };
-use lazy_static::{initialize, lazy_static};
+use lazy_static::lazy_static;
 
 transitions!(Task3DroneState, [
-    (WaitingForCommand, Start) => FollowingLine
+    (WaitingForCommand, Start) => FollowingLine,
+    (ReturningToStartPoint, FlewNearStartPoint) => Landing,
+
+    (FollowingLine, Failure) => ReturningToStartPoint,
+    (ReturningToStartPoint, Failure) => ReturningToStartPoint,
+    (Hovering, Failure) => Landing,
+    (Landing, Failure) => Landing,
+
+    (FollowingLine, Abort) => [Landing, ReturningToStartPoint, Hovering],
+    (ReturningToStartPoint, Abort) => [Landing, ReturningToStartPoint, Hovering],
+    (Hovering, Abort) => [Landing, ReturningToStartPoint, Hovering],
+    (Landing, Abort) => Landing
 ]);
 
+/// Останавливает текущие действия дрона и переводит его в состояние, соответствующее режиму прерывания
+fn abort(abort: Abort) -> Task3DroneState {
+    match abort.mode {
+        AbortMode::Land => Task3DroneState::Landing(Landing::new()),
+        AbortMode::Return => Task3DroneState::ReturningToStartPoint(ReturningToStartPoint::new()),
+        AbortMode::Hover => Task3DroneState::Hovering(Hovering::new()),
+    }
+}
+
+/// Возвращает целевую позицию для mavros: точку `position` на высоте 1 м с рысканием `yaw`.
+fn new_position_target(position: &Point, yaw: f32) -> PositionTarget {
+    PositionTarget {
+        header: new_header("map"),
+        coordinate_frame: PositionTarget::FRAME_LOCAL_NED,
+        type_mask: PositionTarget::IGNORE_VX
+            | PositionTarget::IGNORE_VY
+            | PositionTarget::IGNORE_VZ
+            | PositionTarget::IGNORE_AFX
+            | PositionTarget::IGNORE_AFY
+            | PositionTarget::IGNORE_AFZ,
+        position: {
+            let mut position = position.clone();
+            position.z = 1.0;
+            position
+        },
+        velocity: Vector3::default(),
+        acceleration_or_force: Vector3::default(),
+        yaw,
+        yaw_rate: 0.0,
+    }
+}
+
 lazy_static! {
     static ref POSES: Arc<Mutex<Vec<PoseStamped>>> = Arc::new(Mutex::new(Vec::new()));
-    static ref LINE_DETECTOR_SUBSCRIBER: rosrust::Subscriber = {
-        wait_for_topic("/line_detector_node/line_points");
-        rosrust::subscribe("/line_detector_node/line_points", 4, |path: Path| {
-            let mut drone_position = get_current_drone_pose().pose.position;
-            drone_position.z = 0.0;
-            for pose in path.poses {
-                let mut pose = transform_pose(pose, "map");
-                pose.pose.position.z = 0.0;
-                if get_poses_len() == 0 {
-                    add_pose(pose);
-                    continue;
-                }
-                if get_poses_len() == 1 {
-                    let last_point = get_last_pose().pose.position;
-                    if geometry::get_distance_between_points(&drone_position, &last_point)
-                        < geometry::get_distance_between_points(
-                            &drone_position,
-                            &pose.pose.position,
-                        )
-                    {
-                        add_pose(pose);
-                    } else {
-                        add_start_pose(pose);
-                    }
-                    continue;
-                }
-                let previous_pose = get_previous_pose();
-                let last_pose = get_last_pose();
-                rosrust::ros_warn!(
-                    "SIMILAR ORIENTATION: {}, HAS POINT: {}",
-                    geometry::has_similar_orientation(
-                        &previous_pose.pose.position,
-                        &last_pose.pose.position,
-                        &pose.pose.position,
-                    ),
-                    has_point(&pose.pose.position)
-                );
-                if geometry::has_similar_orientation(
-                    &previous_pose.pose.position,
-                    &last_pose.pose.position,
-                    &pose.pose.position,
-                ) && geometry::get_distance_between_points(&drone_position, &pose.pose.position)
-                    < 3.5
-                    && !has_point(&pose.pose.position)
-                {
-                    add_pose(pose);
-                }
+    /// Блокировка, под которой потоки целевых позиций проверяют, что их состояние не сменилось, и отправляют целевую
+    /// позицию
+    static ref SETPOINT_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Отправляет дрону целевую позицию `setpoint`, если `is_current` возвращает `true`. Проверка и отправка выполняются
+/// под блокировкой, поэтому после `stop_setpoint_streams` целевые позиции не отправляются.
+fn send_setpoint_if(is_current: impl Fn() -> bool, setpoint: PositionTarget) {
+    let _setpoint_guard = SETPOINT_LOCK.lock().unwrap();
+    if is_current() {
+        backend().send_setpoint(setpoint);
+    }
+}
+
+/// Останавливает потоки целевых позиций всех состояний. После возвращения из функции целевые позиции не
+/// отправляются.
+fn stop_setpoint_streams() {
+    let _setpoint_guard = SETPOINT_LOCK.lock().unwrap();
+    stop_state_threads();
+}
+
+/// Обрабатывает точки обнаруженной линии: достраивает по ним путь в POSES и публикует его.
+fn handle_line_points(path: Path) {
+    let mut drone_position = backend().get_current_drone_pose().pose.position;
+    drone_position.z = 0.0;
+    for pose in path.poses {
+        // Точка линии с неизвестным положением в СК map не учитывается
+        let mut pose = match backend().transform_pose(pose, "map") {
+            Some(pose) => pose,
+            None => continue,
+        };
+        pose.pose.position.z = 0.0;
+        if get_poses_len() == 0 {
+            add_pose(pose);
+            continue;
+        }
+        if get_poses_len() == 1 {
+            let last_point = get_last_pose().pose.position;
+            if geometry::get_distance_between_points(&drone_position, &last_point)
+                < geometry::get_distance_between_points(&drone_position, &pose.pose.position)
+            {
+                add_pose(pose);
+            } else {
+                add_start_pose(pose);
             }
-            GLOBAL_PATH_PUBLISHER
-                .send(Path {
-                    header: new_header("map"),
-                    poses: get_poses(),
-                })
-                .unwrap();
-        })
-        .unwrap()
-    };
-    static ref RAW_POINT_PUBLISHER: rosrust::Publisher<PositionTarget> =
-        rosrust::publish("/mavros/setpoint_raw/local", 10).unwrap();
-    static ref GLOBAL_PATH_PUBLISHER: rosrust::Publisher<Path> =
-        rosrust::publish("global_path", 1).unwrap();
+            continue;
+        }
+        let previous_pose = get_previous_pose();
+        let last_pose = get_last_pose();
+        rosrust::ros_warn!(
+            "SIMILAR ORIENTATION: {}, HAS POINT: {}",
+            geometry::has_similar_orientation(
+                &previous_pose.pose.position,
+                &last_pose.pose.position,
+                &pose.pose.position,
+            ),
+            has_point(&pose.pose.position)
+        );
+        if geometry::has_similar_orientation(
+            &previous_pose.pose.position,
+            &last_pose.pose.position,
+            &pose.pose.position,
+        ) && geometry::get_distance_between_points(&drone_position, &pose.pose.position) < 3.5
+            && !has_point(&pose.pose.position)
+        {
+            add_pose(pose);
+        }
+    }
+    backend().publish_global_path(Path {
+        header: new_header("map"),
+        poses: get_poses(),
+    });
 }
 
 fn add_pose(pose: PoseStamped) {
@@ -97,6 +150,11 @@ fn add_start_pose(pose: PoseStamped) {
     POSES.lock().unwrap().insert(0, pose);
 }
 
+/// Очищает путь вдоль линии, построенный в предыдущем запуске задания.
+pub fn clear_path() {
+    POSES.lock().unwrap().clear();
+}
+
 fn get_poses_len() -> usize {
     POSES.lock().unwrap().len()
 }
@@ -123,12 +181,9 @@ fn has_point(point: &Point) -> bool {
 
 impl WaitingForCommand {
     pub fn on_start(self, _: Start) -> FollowingLine {
-        NODES_MONITOR_PUBLISHER
-            .send(Status {
-                status: Status::STARTED,
-            })
-            .unwrap();
-        initialize(&RAW_POINT_PUBLISHER);
+        backend().publish_nodes_monitor_status(Status {
+            status: Status::STARTED,
+        });
 
         FollowingLine::new()
     }
@@ -136,41 +191,34 @@ impl WaitingForCommand {
 
 impl FollowingLine {
     pub fn new() -> FollowingLine {
+        let generation = task_manager::get_mission_generation();
         thread::Builder::new()
             .name("follow_line".to_string())
-            .spawn(|| {
-                initialize(&LINE_DETECTOR_SUBSCRIBER);
-                takeoff(task_manager::OPTIONS.operating_altitude);
-                let rate = rosrust::rate(20.0);
+            .spawn(move || {
+                backend().subscribe_line_points(Box::new(handle_line_points));
+                backend().takeoff(task_manager::options().operating_altitude);
+                let rate = backend::rate(20.0);
                 let mut index = 0;
-                loop {
+                // Поток целевых позиций останавливается при сбое или прерывании задания, после чего целевые
+                // позиции отправляет следующее состояние
+                let is_current = || {
+                    task_manager::is_mission_current(generation)
+                        && *task_manager::IS_OK.lock().unwrap()
+                };
+                while is_current() {
                     if let Some(pose) = POSES.lock().unwrap().iter().nth(index) {
-                        let drone_position = &mut get_current_drone_pose().pose.position;
+                        let drone_position = &mut backend().get_current_drone_pose().pose.position;
                         drone_position.z = 0.0;
-                        RAW_POINT_PUBLISHER
-                            .send(PositionTarget {
-                                header: new_header("map"),
-                                coordinate_frame: PositionTarget::FRAME_LOCAL_NED,
-                                type_mask: PositionTarget::IGNORE_VX
-                                    | PositionTarget::IGNORE_VY
-                                    | PositionTarget::IGNORE_VZ
-                                    | PositionTarget::IGNORE_AFX
-                                    | PositionTarget::IGNORE_AFY
-                                    | PositionTarget::IGNORE_AFZ,
-                                position: {
-                                    let mut position = pose.pose.position.clone();
-                                    position.z = 1.0;
-                                    position
-                                },
-                                velocity: Vector3::default(),
-                                acceleration_or_force: Vector3::default(),
-                                yaw: geometry::get_yaw_between_points(
+                        send_setpoint_if(
+                            is_current,
+                            new_position_target(
+                                &pose.pose.position,
+                                geometry::get_yaw_between_points(
                                     drone_position,
                                     &pose.pose.position,
                                 ),
-                                yaw_rate: 0.0,
-                            })
-                            .unwrap();
+                            ),
+                        );
                         if geometry::get_distance_between_points(
                             drone_position,
                             &pose.pose.position,
@@ -185,4 +233,212 @@ impl FollowingLine {
             .unwrap();
         FollowingLine {}
     }
+
+    pub fn on_failure(self, _: Failure) -> ReturningToStartPoint {
+        ReturningToStartPoint::new()
+    }
+
+    pub fn on_abort(self, command: Abort) -> Task3DroneState {
+        abort(command)
+    }
+}
+
+impl ReturningToStartPoint {
+    pub fn new() -> ReturningToStartPoint {
+        let id = new_state_id();
+        thread::Builder::new()
+            .name("return_to_start_point".to_string())
+            .spawn(move || {
+                let rate = backend::rate(20.0);
+                let home_pose = task_manager::get_home_pose();
+                let mut start_point = home_pose.position.clone();
+                start_point.z = 0.0;
+                let home_yaw = geometry::get_yaw(&home_pose.orientation);
+                while backend().is_ok() && is_state_current(id) {
+                    let drone_position = &mut backend().get_current_drone_pose().pose.position;
+                    drone_position.z = 0.0;
+                    let distance =
+                        geometry::get_distance_between_points(drone_position, &start_point);
+                    // Поток запускается до сохранения состояния в DRONE_STATE, поэтому событие передаётся только
+                    // после его сохранения
+                    if distance <= 0.2 && get_current_state_id() == id {
+                        transition(|state| {
+                            state.on_flew_near_start_point(FlewNearStartPoint::new())
+                        });
+                        return;
+                    }
+                    // Вблизи точки старта дрон разворачивается в исходном направлении
+                    send_setpoint_if(
+                        || is_state_current(id),
+                        new_position_target(
+                            &start_point,
+                            if distance <= 1.0 {
+                                home_yaw
+                            } else {
+                                geometry::get_yaw_between_points(drone_position, &start_point)
+                            },
+                        ),
+                    );
+                    rate.sleep();
+                }
+            })
+            .unwrap();
+        ReturningToStartPoint { id }
+    }
+
+    pub fn on_flew_near_start_point(self, _: FlewNearStartPoint) -> Landing {
+        Landing::new()
+    }
+
+    pub fn on_failure(self, _: Failure) -> ReturningToStartPoint {
+        self
+    }
+
+    pub fn on_abort(self, command: Abort) -> Task3DroneState {
+        abort(command)
+    }
+}
+
+impl Hovering {
+    pub fn new() -> Hovering {
+        let id = new_state_id();
+        let drone_pose = backend().get_current_drone_pose().pose;
+        thread::Builder::new()
+            .name("hover".to_string())
+            .spawn(move || {
+                // mavros требует непрерывного потока целевых позиций, поэтому текущая позиция отправляется повторно
+                let rate = backend::rate(20.0);
+                let yaw = geometry::get_yaw(&drone_pose.orientation);
+                while backend().is_ok() && is_state_current(id) {
+                    send_setpoint_if(
+                        || is_state_current(id),
+                        new_position_target(&drone_pose.position, yaw),
+                    );
+                    rate.sleep();
+                }
+            })
+            .unwrap();
+        Hovering { id }
+    }
+
+    pub fn on_failure(self, _: Failure) -> Landing {
+        Landing::new()
+    }
+
+    pub fn on_abort(self, command: Abort) -> Task3DroneState {
+        abort(command)
+    }
+}
+
+impl Landing {
+    pub fn new() -> Landing {
+        // Целевые позиции возвращения и зависания на высоте 1 м мешали бы посадке
+        stop_setpoint_streams();
+        backend().land();
+        Landing {}
+    }
+
+    pub fn on_failure(self, _: Failure) -> Landing {
+        self
+    }
+
+    pub fn on_abort(self, _: Abort) -> Landing {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        events::Failure,
+        fake_backend::{testing, BackendCall},
+        geometry,
+        msgs::{
+            geometry_msgs::{Pose, PoseStamped},
+            nav_msgs::Path,
+        },
+        task_manager::{self, TaskType},
+    };
+    use std::{thread, time::Duration};
+
+    fn new_pose(x: f64, y: f64, z: f64) -> Pose {
+        Pose {
+            position: geometry::new_point(x, y, z),
+            orientation: geometry::default_quaternion(),
+        }
+    }
+
+    fn new_path(points: &[(f64, f64)]) -> Path {
+        Path {
+            header: geometry::new_header("map"),
+            poses: points
+                .iter()
+                .map(|&(x, y)| PoseStamped {
+                    header: geometry::new_header("map"),
+                    pose: new_pose(x, y, 0.0),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn follows_line_and_returns_after_failure() {
+        let mission = testing::start(TaskType::Three, new_pose(0.0, 0.0, 0.0));
+        mission.wait_for_state("FollowingLine", 5);
+        // Поток следования по линии подписывается на её точки перед взлётом
+        mission.wait_until("takeoff", 5, |fake| {
+            fake.calls()
+                .iter()
+                .any(|call| matches!(call, BackendCall::Takeoff(_)))
+        });
+
+        mission
+            .fake
+            .push_line_points(new_path(&[(1.0, 0.0), (2.0, 0.0)]));
+        mission.wait_until("setpoint on the line", 5, |fake| {
+            fake.calls().iter().any(|call| match call {
+                BackendCall::SendSetpoint(setpoint) => setpoint.position.x == 1.0,
+                _ => false,
+            })
+        });
+
+        mission.fake.set_drone_pose(new_pose(1.5, 0.0, 1.0));
+        task_manager::handle_failure(TaskType::Three, Failure::new_low_voltage_detected());
+        mission.wait_for_state("ReturningToStartPoint", 5);
+        mission.wait_until("setpoint at the start point", 5, |fake| {
+            fake.calls().iter().any(|call| match call {
+                BackendCall::SendSetpoint(setpoint) => {
+                    setpoint.position.x == 0.0 && setpoint.position.y == 0.0
+                }
+                _ => false,
+            })
+        });
+        mission.fake.set_drone_pose(new_pose(0.1, 0.0, 1.0));
+        mission.wait_for_state("Landing", 5);
+
+        // Потоки целевых позиций остановлены до посадки и не мешают ей
+        thread::sleep(Duration::from_millis(200));
+        let calls = mission.fake.calls();
+        let land_index = calls
+            .iter()
+            .position(|call| *call == BackendCall::Land)
+            .expect("Drone has not landed");
+        assert!(!calls[land_index..]
+            .iter()
+            .any(|call| matches!(call, BackendCall::SendSetpoint(_))));
+
+        assert_eq!(
+            mission.states(),
+            vec![
+                "WaitingForCommand",
+                "FollowingLine",
+                "ReturningToStartPoint",
+                "Landing",
+            ]
+        );
+        assert_eq!(
+            mission.events(),
+            vec!["LowVoltageDetected", "FlewNearStartPoint"]
+        );
+    }
 }
@@ -1,26 +1,79 @@
 use crate::{
+    backend::{self, backend},
+    battery,
+    commands::{self, AbortMode, Pause, Resume},
     common_ros_utils::get_param,
-    events::Failure,
-    msgs::{nodes_monitor_msgs::Status, task_manager::*},
-    service_clients, task1, task2, task3,
-    topic_publishers::{self, *},
-    topic_subscribers::{self, *},
-    NODE_NAME,
+    events::{log_event, EventKind, EventPayload, Failure},
+    execute_task, geometry, metrics, mission_log, mission_state,
+    msgs::{
+        diagnostic_msgs::KeyValue,
+        geometry_msgs::Pose,
+        nodes_monitor_msgs::Status,
+        std_srvs::{Empty, EmptyRes},
+        task_manager::*,
+    },
+    overrides, readiness, return_budget, status, task1, task2, task3, NODE_NAME,
 };
 use lazy_static::{initialize, lazy_static};
-use rosrust::Service;
+use rosrust::{Service, Time};
+use rosrust_actionlib::ActionServer;
+use serde_json::json;
 use std::{
-    sync::{Arc, Mutex},
+    collections::VecDeque,
+    sync::{Arc, Mutex, RwLock, TryLockError},
     thread,
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 lazy_static! {
+    /// Параметры узла, заданные параметрами ROS
     pub static ref OPTIONS: TaskManagerOptions = TaskManagerOptions::new();
+    /// Параметры выполняемого задания: параметры узла с переопределениями, переданными при запуске задания
+    static ref MISSION_OPTIONS: RwLock<Arc<TaskManagerOptions>> = RwLock::new(Arc::new(OPTIONS.clone()));
     pub static ref IS_OK: Arc<Mutex<bool>> = Arc::new(Mutex::new(true));
+    /// `true`, если выполнение задания приостановлено
+    pub static ref IS_PAUSED: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
+    /// Поза дрона в начале задания, в которую он возвращается
+    static ref HOME_POSE: Mutex<Option<Pose>> = Mutex::new(None);
+    /// Идентификатор выполняемого задания
+    static ref MISSION_ID: Mutex<String> = Mutex::new(String::new());
+    /// Номер запуска задания, увеличивающийся при каждом запуске
+    static ref MISSION_GENERATION: Mutex<u64> = Mutex::new(0);
+    /// Выполняемое задание
+    static ref ACTIVE_TASK: Mutex<Option<TaskType>> = Mutex::new(None);
+    /// Таймер выполнения задания
+    static ref MISSION_TIMER: Mutex<MissionTimer> = Mutex::new(MissionTimer::default());
+    /// Блокировка, под которой выполняются переходы конечных автоматов заданий
+    static ref TRANSITION_LOCK: Mutex<()> = Mutex::new(());
+    /// События `Failure`, ожидающие передачи конечному автомату задания, с номером запуска задания, в котором они
+    /// произошли
+    static ref PENDING_FAILURES: Mutex<VecDeque<(u64, TaskType, Failure)>> = Mutex::new(VecDeque::new());
 }
 
+/// Таймер выполнения задания, не учитывающий время, в течение которого задание было приостановлено.
+#[derive(Default)]
+struct MissionTimer {
+    start_time: Option<Time>,
+    paused_at: Option<Time>,
+    paused_seconds: f64,
+}
+
+impl MissionTimer {
+    fn get_seconds(&self, now: Time) -> f64 {
+        let start_time = match self.start_time {
+            Some(start_time) => start_time,
+            None => return 0.0,
+        };
+        let end_time = self.paused_at.unwrap_or(now);
+        (end_time - start_time).seconds() - self.paused_seconds
+    }
+}
+
+/// Названия состояний конечного автомата, в которых выполнение задания завершено
+const FINAL_STATES: &[&str] = &["Landing", "Hovering", "Error"];
+
 /// Параметры
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct TaskManagerOptions {
     pub operating_altitude: f32,
     pub low_altitude: f32,
@@ -32,8 +85,133 @@ pub struct TaskManagerOptions {
     pub flying_into_hole_pass_distance: f64,
     pub flying_into_hole_detection_pass_distance: f64,
     pub min_battery_voltage: f32,
+    /// Запас времени (с), с которым дрон должен успевать вернуться на точку старта. Не используется во 2 задании.
+    pub return_time_margin_seconds: f32,
+    /// Запас вольтажа (В) сверх `min_battery_voltage`, с которым дрон должен возвращаться на точку старта. Не
+    /// используется во 2 задании.
+    pub return_voltage_margin: f32,
+    /// Действие при наступлении мягкого срока задания - за `soft_deadline_seconds` до окончания отведённого времени.
+    pub soft_deadline_policy: TimeoutPolicy,
+    /// Действие при окончании отведённого на задание времени. Прерывание не выполняется, если дрон уже возвращается
+    /// на точку старта или задание завершено.
+    pub hard_deadline_policy: TimeoutPolicy,
+    /// Наибольший возраст позы дрона (с), после которого она считается устаревшей.
+    pub max_pose_age_seconds: f64,
+    /// Действие, когда поза дрона устарела.
+    pub stale_pose_policy: TimeoutPolicy,
+    /// Наибольшее время (с), в течение которого проверка готовности ожидает необходимые заданию сервисы.
+    pub readiness_timeout_seconds: f64,
+    /// Частота (Гц) периодической публикации статуса дрона.
+    pub status_rate: f64,
+    /// Количество последних событий, возвращаемых сервисом task_manager/get_mission_state.
+    pub event_history_size: usize,
+    pub battery: BatteryOptions,
+    pub health: HealthOptions,
+    pub mission_log: MissionLogOptions,
+    pub metrics: MetricsOptions,
     pub task1: Task1Options,
     pub task2: Task2Options,
+    pub task3: Task3Options,
+}
+
+/// Действие при наступлении срока выполнения задания.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimeoutPolicy {
+    /// Конечному автомату задания передаётся событие `Failure`
+    Failure,
+    /// Задание прерывается в указанном режиме
+    Abort(AbortMode),
+    /// Наступление срока только публикуется как событие
+    Ignore,
+}
+
+impl TimeoutPolicy {
+    /// Возвращает действие с названием `name`: "failure", "land", "return", "hover" или "ignore".
+    pub fn from_name(name: &str) -> Option<TimeoutPolicy> {
+        match name {
+            "failure" => Some(TimeoutPolicy::Failure),
+            "land" => Some(TimeoutPolicy::Abort(AbortMode::Land)),
+            "return" => Some(TimeoutPolicy::Abort(AbortMode::Return)),
+            "hover" => Some(TimeoutPolicy::Abort(AbortMode::Hover)),
+            "ignore" => Some(TimeoutPolicy::Ignore),
+            _ => None,
+        }
+    }
+
+    /// Возвращает действие, заданное параметром ROS `param_name`: "failure", "land", "return", "hover" или "ignore".
+    fn from_param(param_name: &str, default_value: TimeoutPolicy) -> TimeoutPolicy {
+        let policy = get_param(param_name, String::new());
+        if policy.is_empty() {
+            return default_value;
+        }
+        match TimeoutPolicy::from_name(&policy) {
+            Some(policy) => policy,
+            None => {
+                rosrust::ros_warn!(
+                    "Unknown timeout policy \"{}\" in param \"{}\". Default value is set.",
+                    policy,
+                    param_name
+                );
+                default_value
+            }
+        }
+    }
+}
+
+/// Параметры фильтрации состояния аккумулятора. Критический порог вольтажа задаётся `min_battery_voltage`.
+#[derive(Clone, Copy)]
+pub struct BatteryOptions {
+    /// Частота (Гц), с которой проверяется состояние аккумулятора.
+    pub rate: f64,
+    /// Количество последних измерений, по которым усредняются вольтаж и заряд.
+    pub window_size: usize,
+    /// Вольтаж (В), ниже которого публикуется предупреждение о разряде аккумулятора.
+    pub warning_voltage: f32,
+    /// Превышение порога вольтажа (В), необходимое для возврата на более высокий уровень заряда.
+    pub hysteresis_voltage: f32,
+    /// Заряд (от 0 до 1), ниже которого публикуется предупреждение о разряде аккумулятора.
+    pub warning_percentage: f32,
+    /// Заряд (от 0 до 1), ниже которого задание завершается.
+    pub critical_percentage: f32,
+    /// Превышение порога заряда, необходимое для возврата на более высокий уровень заряда.
+    pub hysteresis_percentage: f32,
+    /// Внутреннее сопротивление аккумулятора (Ом), по которому компенсируется просадка вольтажа под нагрузкой.
+    pub internal_resistance: f32,
+}
+
+/// Параметры мониторинга узлов, от которых зависит выполнение задания.
+#[derive(Clone, Copy)]
+pub struct HealthOptions {
+    /// Частота (Гц), с которой проверяется и публикуется состояние узлов.
+    pub rate: f64,
+    /// Наибольшее время (с) без сообщений с состоянием аккумулятора.
+    pub battery_timeout_seconds: f64,
+    /// Наибольшее время (с) без сообщений с QR-кодами или точками линии.
+    pub vision_timeout_seconds: f64,
+    /// Наибольшее время (с) ожидания ответа мастера ROS при проверке доступности сервиса.
+    pub service_probe_timeout_seconds: f64,
+}
+
+/// Параметры журнала задания.
+#[derive(Clone)]
+pub struct MissionLogOptions {
+    /// Каталог, в котором создаются журналы заданий. Если пуст, журнал не ведётся.
+    pub directory: String,
+    /// Частота (Гц), с которой в журнал записывается поза дрона. Если не больше 0, поза не записывается.
+    pub pose_rate: f64,
+    /// Через сколько секунд после начала посадки или прерывания задания создаётся отчёт о задании. Если
+    /// отрицательно, отчёт не создаётся.
+    pub report_delay_seconds: f64,
+}
+
+/// Параметры экспорта метрик узла.
+#[derive(Clone)]
+pub struct MetricsOptions {
+    /// Файл, в который периодически записываются метрики в текстовом формате Prometheus. Если пуст, метрики в файл не
+    /// записываются.
+    pub file: String,
+    /// Период (с) записи метрик в файл.
+    pub period_seconds: f64,
 }
 
 /// Параметры, необходимые для 1 задания.
@@ -43,6 +221,8 @@ pub struct Task1Options {
     pub cubes_count: usize,
     /// Время (мин), которое отводится на поиск кубов.
     pub max_timer_minutes: f32,
+    /// За сколько секунд до окончания отведённого времени наступает мягкий срок задания.
+    pub soft_deadline_seconds: f32,
 }
 
 /// Параметры, необходимые для 2 задания.
@@ -54,6 +234,19 @@ pub struct Task2Options {
     pub max_qr_distance_tolerance: f32,
     /// Наибольшее расстояние между центром проёма и центром QR-кода (м), при котором они считаются связанными.
     pub max_association_distance: f32,
+    /// Время (мин), которое отводится на прохождение комнат и посадку.
+    pub max_timer_minutes: f32,
+    /// За сколько секунд до окончания отведённого времени наступает мягкий срок задания.
+    pub soft_deadline_seconds: f32,
+}
+
+/// Параметры, необходимые для 3 задания.
+#[derive(Clone, Copy)]
+pub struct Task3Options {
+    /// Время (мин), которое отводится на следование по линии.
+    pub max_timer_minutes: f32,
+    /// За сколько секунд до окончания отведённого времени наступает мягкий срок задания.
+    pub soft_deadline_seconds: f32,
 }
 
 impl TaskManagerOptions {
@@ -75,21 +268,101 @@ impl TaskManagerOptions {
                 0.3,
             ),
             min_battery_voltage: get_param("~min_battery_voltage", 10.0),
+            return_time_margin_seconds: get_param("~return_time_margin_seconds", 30.0),
+            return_voltage_margin: get_param("~return_voltage_margin", 0.2),
+            // Мягкий срок по умолчанию только публикуется: событие Failure сажает дрона во 2 задании на месте
+            soft_deadline_policy: TimeoutPolicy::from_param(
+                "~soft_deadline_policy",
+                TimeoutPolicy::Ignore,
+            ),
+            hard_deadline_policy: TimeoutPolicy::from_param(
+                "~hard_deadline_policy",
+                TimeoutPolicy::Abort(AbortMode::Land),
+            ),
+            max_pose_age_seconds: get_param("~max_pose_age_seconds", 0.5),
+            stale_pose_policy: TimeoutPolicy::from_param(
+                "~stale_pose_policy",
+                TimeoutPolicy::Abort(AbortMode::Land),
+            ),
+            readiness_timeout_seconds: get_param("~readiness_timeout_seconds", 2.0),
+            status_rate: get_param("~status_rate", 1.0),
+            event_history_size: get_param("~event_history_size", 50),
+            battery: BatteryOptions {
+                rate: get_param("~battery_rate", 2.0),
+                window_size: get_param("~battery_window_size", 10),
+                warning_voltage: get_param("~battery_warning_voltage", 10.5),
+                hysteresis_voltage: get_param("~battery_hysteresis_voltage", 0.2),
+                warning_percentage: get_param("~battery_warning_percentage", 0.3),
+                critical_percentage: get_param("~battery_critical_percentage", 0.15),
+                hysteresis_percentage: get_param("~battery_hysteresis_percentage", 0.05),
+                internal_resistance: get_param("~battery_internal_resistance", 0.0),
+            },
+            health: HealthOptions {
+                rate: get_param("~health_rate", 1.0),
+                battery_timeout_seconds: get_param("~health_battery_timeout_seconds", 5.0),
+                vision_timeout_seconds: get_param("~health_vision_timeout_seconds", 2.0),
+                service_probe_timeout_seconds: get_param(
+                    "~health_service_probe_timeout_seconds",
+                    0.5,
+                ),
+            },
+            mission_log: MissionLogOptions {
+                directory: get_param("~mission_log_directory", "mission_logs".to_string()),
+                pose_rate: get_param("~mission_log_pose_rate", 2.0),
+                report_delay_seconds: get_param("~mission_report_delay_seconds", 5.0),
+            },
+            metrics: MetricsOptions {
+                file: get_param("~metrics_file", "metrics/task_manager.prom".to_string()),
+                period_seconds: get_param("~metrics_period_seconds", 5.0),
+            },
             task1: Task1Options {
                 cubes_count: get_param("~task1_cubes_count", 5),
                 max_timer_minutes: get_param("~task1_max_timer_minutes", 9.0),
+                soft_deadline_seconds: get_param("~task1_soft_deadline_seconds", 90.0),
             },
             task2: Task2Options {
                 max_floor_z: get_param("~task2_max_floor_z", 0.2),
                 max_qr_distance_tolerance: get_param("~task2_max_qr_distance_tolerance", 0.2),
                 max_association_distance: get_param("~task2_max_association_distance", 0.6),
+                max_timer_minutes: get_param("~task2_max_timer_minutes", 10.0),
+                soft_deadline_seconds: get_param("~task2_soft_deadline_seconds", 90.0),
+            },
+            task3: Task3Options {
+                max_timer_minutes: get_param("~task3_max_timer_minutes", 5.0),
+                soft_deadline_seconds: get_param("~task3_soft_deadline_seconds", 60.0),
             },
         }
     }
+
+    /// Возвращает время (мин), которое отводится на задание `task_type`.
+    pub fn get_max_timer_minutes(&self, task_type: TaskType) -> f32 {
+        match task_type {
+            TaskType::One => self.task1.max_timer_minutes,
+            TaskType::Two => self.task2.max_timer_minutes,
+            TaskType::Three => self.task3.max_timer_minutes,
+        }
+    }
+
+    /// Возвращает, за сколько секунд до окончания отведённого на задание `task_type` времени наступает мягкий срок.
+    pub fn get_soft_deadline_seconds(&self, task_type: TaskType) -> f32 {
+        match task_type {
+            TaskType::One => self.task1.soft_deadline_seconds,
+            TaskType::Two => self.task2.soft_deadline_seconds,
+            TaskType::Three => self.task3.soft_deadline_seconds,
+        }
+    }
 }
 
 pub struct TaskManager {
     pub start_service: Service,
+    pub get_readiness_service: Service,
+    pub get_mission_state_service: Service,
+    pub get_mission_state_json_service: Service,
+    pub get_metrics_service: Service,
+    pub abort_service: Service,
+    pub pause_service: Service,
+    pub resume_service: Service,
+    pub execute_task_server: ActionServer<ExecuteTaskAction>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -99,90 +372,635 @@ pub enum TaskType {
     Three,
 }
 
+impl TaskType {
+    /// Возвращает задание с номером `number` (1, 2 или 3).
+    pub fn from_number(number: u32) -> Result<TaskType, String> {
+        match number {
+            1 => Ok(TaskType::One),
+            2 => Ok(TaskType::Two),
+            3 => Ok(TaskType::Three),
+            _ => Err("Wrong task number is specified".to_string()),
+        }
+    }
+
+    /// Возвращает номер задания (1, 2 или 3).
+    pub fn number(self) -> u8 {
+        match self {
+            TaskType::One => 1,
+            TaskType::Two => 2,
+            TaskType::Three => 3,
+        }
+    }
+}
+
 impl TaskManager {
     pub fn new() -> TaskManager {
         initialize(&OPTIONS);
         let start_service =
             rosrust::service::<Start, _>(format!("{}/start", NODE_NAME).as_str(), |start| {
-                let task_type = match start.task {
-                    1 => TaskType::One,
-                    2 => TaskType::Two,
-                    3 => TaskType::Three,
-                    _ => return Err("Wrong task number is specified".to_string()),
+                let home_pose = if start.use_home_pose {
+                    Some(start.home_pose)
+                } else {
+                    None
                 };
-                thread::spawn(move || {
-                    let rate = rosrust::rate(1.0);
-                    while rosrust::is_ok() {
-                        if get_current_battery_voltage() <= OPTIONS.min_battery_voltage {
-                            match task_type {
-                                TaskType::One => task1::drone_state::set_drone_state(
-                                    task1::drone_state::get_drone_state()
-                                        .on_failure(Failure::new_low_voltage_detected()),
-                                ),
-                                TaskType::Two => task2::drone_state::set_drone_state(
-                                    task2::drone_state::get_drone_state()
-                                        .on_failure(Failure::new_low_voltage_detected()),
-                                ),
-                                TaskType::Three => todo!(),
-                            };
-                            break;
-                        }
-                        rate.sleep();
-                    }
-                });
-                thread::spawn(move || {
-                    let start_time = rosrust::now();
-                    let rate = rosrust::rate(0.2);
-                    while rosrust::is_ok() {
-                        let minutes_passed = (rosrust::now() - start_time).sec as f32 / 60.0;
-                        if minutes_passed > 1.0 && minutes_passed - minutes_passed.floor() < 0.08 {
-                            rosrust::ros_info!("{} minute(s) has passed!", minutes_passed.floor());
-                        }
-                        if minutes_passed > OPTIONS.task1.max_timer_minutes {
-                            match task_type {
-                                TaskType::One => task1::drone_state::set_drone_state(
-                                    task1::drone_state::get_drone_state()
-                                        .on_failure(Failure::new_timeout()),
-                                ),
-                                TaskType::Two => task2::drone_state::set_drone_state(
-                                    task2::drone_state::get_drone_state()
-                                        .on_failure(Failure::new_timeout()),
-                                ),
-                                TaskType::Three => todo!(),
-                            };
-                            break;
-                        }
-                        rate.sleep();
-                    }
-                });
-                service_clients::init(task_type);
-                topic_publishers::init(task_type);
-                topic_subscribers::init(task_type);
-                match task_type {
-                    TaskType::One => task1::drone_state::set_drone_state(
-                        task1::drone_state::get_drone_state().on_start(task1::commands::Start {}),
-                    ),
-                    TaskType::Two => {
-                        task2::drone_state::set_drone_state(
-                            task2::drone_state::get_drone_state()
-                                .on_start(task2::commands::Start {}),
-                        );
-                    }
-                    TaskType::Three => {
-                        task3::drone_state::set_drone_state(
-                            task3::drone_state::get_drone_state()
-                                .on_start(task3::commands::Start {}),
-                        );
+                match request_task(start.task, home_pose, &start.overrides) {
+                    Ok((_, mission_id)) => Ok(StartRes {
+                        accepted: true,
+                        reason: String::new(),
+                        mission_id,
+                    }),
+                    Err(reason) => {
+                        rosrust::ros_warn!("Start request is rejected: {}", reason);
+                        Ok(StartRes {
+                            accepted: false,
+                            reason,
+                            mission_id: String::new(),
+                        })
                     }
                 }
-                NODES_MONITOR_PUBLISHER
-                    .send(Status {
-                        status: Status::STARTED,
-                    })
-                    .unwrap();
-                Ok(StartRes {})
             })
             .unwrap();
-        TaskManager { start_service }
+        let get_readiness_service = rosrust::service::<GetReadiness, _>(
+            format!("{}/get_readiness", NODE_NAME).as_str(),
+            |request| {
+                let task_type = TaskType::from_number(request.task)?;
+                let readiness = readiness::check(task_type, OPTIONS.readiness_timeout_seconds);
+                Ok(GetReadinessRes {
+                    is_ready: readiness.is_ready(),
+                    missing_services: readiness.missing_services,
+                    missing_topics: readiness.missing_topics,
+                    missing_action_servers: readiness.missing_action_servers,
+                })
+            },
+        )
+        .unwrap();
+        let get_mission_state_service = rosrust::service::<GetMissionState, _>(
+            format!("{}/get_mission_state", NODE_NAME).as_str(),
+            |_| Ok(mission_state::get_mission_state()),
+        )
+        .unwrap();
+        let get_mission_state_json_service = rosrust::service::<GetMissionStateJson, _>(
+            format!("{}/get_mission_state_json", NODE_NAME).as_str(),
+            |_| {
+                Ok(GetMissionStateJsonRes {
+                    json: mission_state::to_json(&mission_state::get_mission_state()),
+                })
+            },
+        )
+        .unwrap();
+        let get_metrics_service = rosrust::service::<GetMetrics, _>(
+            format!("{}/get_metrics", NODE_NAME).as_str(),
+            |_| {
+                Ok(GetMetricsRes {
+                    json: metrics::to_json(),
+                    prometheus: metrics::to_prometheus(),
+                })
+            },
+        )
+        .unwrap();
+        metrics::start_exporting();
+        let abort_service =
+            rosrust::service::<Abort, _>(format!("{}/abort", NODE_NAME).as_str(), |abort| {
+                let mode = match abort.mode {
+                    AbortReq::LAND => AbortMode::Land,
+                    AbortReq::RETURN => AbortMode::Return,
+                    AbortReq::HOVER => AbortMode::Hover,
+                    _ => return Err("Wrong abort mode is specified".to_string()),
+                };
+                abort_task(mode)?;
+                Ok(AbortRes {})
+            })
+            .unwrap();
+        let pause_service =
+            rosrust::service::<Empty, _>(format!("{}/pause", NODE_NAME).as_str(), |_| {
+                pause_task()?;
+                Ok(EmptyRes {})
+            })
+            .unwrap();
+        let resume_service =
+            rosrust::service::<Empty, _>(format!("{}/resume", NODE_NAME).as_str(), |_| {
+                resume_task()?;
+                Ok(EmptyRes {})
+            })
+            .unwrap();
+        let execute_task_server = execute_task::start_server();
+        TaskManager {
+            start_service,
+            get_readiness_service,
+            get_mission_state_service,
+            get_mission_state_json_service,
+            get_metrics_service,
+            abort_service,
+            pause_service,
+            resume_service,
+            execute_task_server,
+        }
+    }
+}
+
+/// Возвращает выполняемое задание, если оно было запущено.
+pub fn get_active_task() -> Option<TaskType> {
+    *ACTIVE_TASK.lock().unwrap()
+}
+
+/// Приостанавливает или продолжает выполнение задания и таймер задания.
+pub fn set_paused(is_paused: bool) {
+    let mut mission_timer = MISSION_TIMER.lock().unwrap();
+    let now = backend().now();
+    match (is_paused, mission_timer.paused_at) {
+        (true, None) => mission_timer.paused_at = Some(now),
+        (false, Some(paused_at)) => {
+            mission_timer.paused_seconds += (now - paused_at).seconds();
+            mission_timer.paused_at = None;
+        }
+        _ => {}
+    }
+    *IS_PAUSED.lock().unwrap() = is_paused;
+}
+
+/// Возвращает время (с), прошедшее с начала выполнения задания, без учёта времени, в течение которого задание было
+/// приостановлено.
+pub fn get_mission_seconds() -> f64 {
+    MISSION_TIMER.lock().unwrap().get_seconds(backend().now())
+}
+
+/// Приостанавливает выполняемое задание. Возвращает ошибку, если ни одно задание не запущено, задание уже
+/// приостановлено или не может быть приостановлено.
+pub fn pause_task() -> Result<(), String> {
+    let task_type = get_active_task().ok_or_else(|| "No task is running".to_string())?;
+    if *IS_PAUSED.lock().unwrap() {
+        return Err("Task is already paused".to_string());
+    }
+    match task_type {
+        TaskType::One => task1::drone_state::transition(|state| state.on_pause(Pause::new())),
+        TaskType::Two => task2::drone_state::transition(|state| state.on_pause(Pause::new())),
+        TaskType::Three => return Err("Task 3 can not be paused".to_string()),
+    }
+    Ok(())
+}
+
+/// Продолжает приостановленное задание. Возвращает ошибку, если задание не было приостановлено.
+pub fn resume_task() -> Result<(), String> {
+    let task_type = get_active_task().ok_or_else(|| "No task is running".to_string())?;
+    if !*IS_PAUSED.lock().unwrap() {
+        return Err("Task is not paused".to_string());
+    }
+    match task_type {
+        TaskType::One => task1::drone_state::transition(|state| state.on_resume(Resume::new())),
+        TaskType::Two => task2::drone_state::transition(|state| state.on_resume(Resume::new())),
+        TaskType::Three => return Err("Task 3 can not be paused".to_string()),
+    }
+    Ok(())
+}
+
+/// Прерывает выполняемое задание в режиме `mode`. Возвращает ошибку, если ни одно задание не запущено.
+pub fn abort_task(mode: AbortMode) -> Result<(), String> {
+    let task_type = get_active_task().ok_or_else(|| "No task is running".to_string())?;
+    run_transition(|| apply_abort(task_type, mode));
+    Ok(())
+}
+
+/// Прерывает задание `task_type` в режиме `mode`. Вызывается под блокировкой переходов.
+fn apply_abort(task_type: TaskType, mode: AbortMode) {
+    match task_type {
+        TaskType::One => task1::drone_state::set_drone_state(
+            task1::drone_state::get_drone_state().on_abort(commands::Abort::new(mode)),
+        ),
+        TaskType::Two => task2::drone_state::set_drone_state(
+            task2::drone_state::get_drone_state().on_abort(commands::Abort::new(mode)),
+        ),
+        TaskType::Three => task3::drone_state::set_drone_state(
+            task3::drone_state::get_drone_state().on_abort(commands::Abort::new(mode)),
+        ),
+    }
+}
+
+/// Возвращает `true`, если дрон задания `task_type` возвращается на точку старта или задание завершено.
+fn is_returning_or_finished(task_type: TaskType) -> bool {
+    let state_name = get_task_state_name(task_type);
+    state_name == "ReturningToStartPoint" || FINAL_STATES.contains(&state_name)
+}
+
+/// Возвращает название текущего состояния конечного автомата задания `task_type`.
+pub fn get_task_state_name(task_type: TaskType) -> &'static str {
+    match task_type {
+        TaskType::One => task1::drone_state::get_drone_state().name(),
+        TaskType::Two => task2::drone_state::get_drone_state().name(),
+        TaskType::Three => task3::drone_state::get_drone_state().name(),
+    }
+}
+
+/// Выполняет переход конечного автомата задания `transition`: получение текущего состояния, вызов события и
+/// сохранение нового состояния. Переходы выполняются по очереди, поэтому состояние не может измениться между
+/// получением текущего и сохранением нового. После перехода передаёт конечному автомату события `Failure`,
+/// поставленные в очередь во время него. Не должен вызываться из перехода.
+pub fn run_transition<R>(transition: impl FnOnce() -> R) -> R {
+    let result = {
+        let _transition_guard = TRANSITION_LOCK.lock().unwrap();
+        transition()
+    };
+    deliver_pending_failures();
+    result
+}
+
+/// Передаёт событие `failure` конечному автомату задания `task_type`, дожидаясь окончания выполняемого перехода. Не
+/// должен вызываться из перехода, для этого есть `report_failure`.
+pub fn handle_failure(task_type: TaskType, failure: Failure) {
+    run_transition(|| apply_failure(task_type, failure));
+}
+
+/// Ставит событие `failure` в очередь конечного автомата задания `task_type` и, если переход не выполняется,
+/// передаёт его. Иначе событие передаёт поток, выполняющий переход, по его окончании. Не блокирует вызывающий поток,
+/// поэтому может вызываться в том числе из переходов, например при ошибке вызова сервиса.
+pub fn report_failure(task_type: TaskType, failure: Failure) {
+    PENDING_FAILURES
+        .lock()
+        .unwrap()
+        .push_back((get_mission_generation(), task_type, failure));
+    deliver_pending_failures();
+}
+
+/// Передаёт конечному автомату задания события `Failure` из очереди, если никакой переход не выполняется. События
+/// заданий, после которых запускалось другое задание, отбрасываются.
+fn deliver_pending_failures() {
+    while !PENDING_FAILURES.lock().unwrap().is_empty() {
+        let _transition_guard = match TRANSITION_LOCK.try_lock() {
+            Ok(guard) => guard,
+            // Очередь разберёт поток, выполняющий переход, после его окончания
+            Err(TryLockError::WouldBlock) => return,
+            Err(TryLockError::Poisoned(error)) => error.into_inner(),
+        };
+        loop {
+            let pending_failure = PENDING_FAILURES.lock().unwrap().pop_front();
+            let (generation, task_type, failure) = match pending_failure {
+                Some(pending_failure) => pending_failure,
+                None => break,
+            };
+            if is_mission_current(generation) {
+                apply_failure(task_type, failure);
+            }
+        }
+    }
+}
+
+/// Передаёт событие `failure` конечному автомату задания `task_type`. Вызывается под блокировкой переходов.
+fn apply_failure(task_type: TaskType, failure: Failure) {
+    match task_type {
+        TaskType::One => task1::drone_state::set_drone_state(
+            task1::drone_state::get_drone_state().on_failure(failure),
+        ),
+        TaskType::Two => task2::drone_state::set_drone_state(
+            task2::drone_state::get_drone_state().on_failure(failure),
+        ),
+        TaskType::Three => task3::drone_state::set_drone_state(
+            task3::drone_state::get_drone_state().on_failure(failure),
+        ),
+    }
+}
+
+/// Выполняет действие `policy` при наступлении срока задания `task_type` или истечении времени ожидания данных. Если
+/// действие не предполагает событие `Failure`, создаваемое `new_failure`, публикуется событие `event_name`.
+pub fn handle_timeout(
+    task_type: TaskType,
+    policy: TimeoutPolicy,
+    event_name: &str,
+    new_failure: fn() -> Failure,
+) {
+    match policy {
+        TimeoutPolicy::Failure => handle_failure(task_type, new_failure()),
+        TimeoutPolicy::Abort(mode) => {
+            log_event(EventKind::Timeout, event_name, EventPayload::None);
+            if let Err(error) = abort_task(mode) {
+                rosrust::ros_err!("Could not abort the task: {}", error);
+            }
+        }
+        TimeoutPolicy::Ignore => log_event(EventKind::Timeout, event_name, EventPayload::None),
+    }
+}
+
+/// Выполняет действие `policy` по окончании отведённого на задание `task_type` времени, см. `handle_timeout`.
+/// Прерывание не выполняется, если дрон уже возвращается на точку старта или задание завершено: прерывание посадкой
+/// оборвало бы возвращение.
+fn handle_hard_deadline(task_type: TaskType, policy: TimeoutPolicy) {
+    let mode = match policy {
+        TimeoutPolicy::Abort(mode) => mode,
+        _ => return handle_timeout(task_type, policy, "Timeout", Failure::new_timeout),
+    };
+    log_event(EventKind::Timeout, "Timeout", EventPayload::None);
+    // Состояние проверяется под блокировкой переходов, чтобы оно не сменилось до прерывания
+    run_transition(|| {
+        if is_returning_or_finished(task_type) {
+            rosrust::ros_info!("Task is not aborted: it is already returning or finished");
+        } else {
+            apply_abort(task_type, mode);
+        }
+    });
+}
+
+/// Возвращает позу дрона в начале задания. Если задание ещё не запущено, возвращает начало СК map.
+pub fn get_home_pose() -> Pose {
+    HOME_POSE.lock().unwrap().clone().unwrap_or_else(|| Pose {
+        position: geometry::default_point(),
+        orientation: geometry::default_quaternion(),
+    })
+}
+
+/// Возвращает параметры выполняемого задания. Если задание ещё не запущено, возвращает параметры узла `OPTIONS`.
+pub fn options() -> Arc<TaskManagerOptions> {
+    MISSION_OPTIONS.read().unwrap().clone()
+}
+
+/// Возвращает идентификатор выполняемого задания. Если задание ещё не запущено, возвращает пустую строку.
+pub fn get_mission_id() -> String {
+    MISSION_ID.lock().unwrap().clone()
+}
+
+/// Возвращает номер последнего запуска задания. Потоки задания запоминают его при запуске и завершаются, когда
+/// `is_mission_current` для него возвращает `false`.
+pub fn get_mission_generation() -> u64 {
+    *MISSION_GENERATION.lock().unwrap()
+}
+
+/// Возвращает `true`, если активная реализация `DroneBackend` работает и после запуска задания с номером
+/// `generation` другое задание не запускалось.
+pub fn is_mission_current(generation: u64) -> bool {
+    backend().is_ok() && get_mission_generation() == generation
+}
+
+/// Возвращает `true`, если задание запущено и ещё не завершено посадкой, зависанием после прерывания или ошибкой.
+pub fn is_task_running() -> bool {
+    get_active_task()
+        .map(|task_type| !FINAL_STATES.contains(&get_task_state_name(task_type)))
+        .unwrap_or(false)
+}
+
+/// Проверяет запрос на запуск задания с номером `task` и переопределениями параметров `overrides` и запускает его.
+/// Точкой старта считается поза `home_pose`, если она задана. Возвращает задание и его идентификатор или причину, по
+/// которой задание не запущено: неверный номер задания, уже выполняющееся задание, некорректные переопределения или
+/// неготовность необходимых заданию узлов.
+pub fn request_task(
+    task: u32,
+    home_pose: Option<Pose>,
+    overrides: &[KeyValue],
+) -> Result<(TaskType, String), String> {
+    let task_type = TaskType::from_number(task)?;
+    if is_task_running() {
+        return Err("Another task is running".to_string());
+    }
+    let mission_overrides = overrides::apply(task_type, OPTIONS.clone(), overrides)?;
+    if home_pose.is_some() && mission_overrides.home_pose.is_some() {
+        return Err("Home pose is specified both in the request and in the overrides".to_string());
+    }
+    let readiness = readiness::check(task_type, OPTIONS.readiness_timeout_seconds);
+    if !readiness.is_ready() {
+        return Err(format!("Task is not ready: {}", readiness));
+    }
+    let mission_id = start_task_with_options(
+        task_type,
+        home_pose.or(mission_overrides.home_pose),
+        mission_overrides.options,
+    );
+    Ok((task_type, mission_id))
+}
+
+/// Запускает поток, который с частотой 10 Гц проверяет позу дрона и, когда она устаревает во время выполнения задания
+/// `task_type`, выполняет действие `options().stale_pose_policy`, например посадку на месте. Действие выполняется не
+/// больше одного раза за задание и только после того, как поза хотя бы раз была свежей. Поток работает по часам
+/// активной реализации `backend::DroneBackend` и завершается вместе с заданием.
+fn start_stale_pose_failsafe(task_type: TaskType) {
+    let generation = get_mission_generation();
+    thread::spawn(move || {
+        let rate = backend::rate(10.0);
+        let mut has_pose_been_fresh = false;
+        while is_mission_current(generation) && is_task_running() {
+            if backend().is_drone_pose_fresh() {
+                has_pose_been_fresh = true;
+            } else if has_pose_been_fresh {
+                rosrust::ros_err!(
+                    "Drone pose is older than {} s.",
+                    options().max_pose_age_seconds
+                );
+                handle_timeout(
+                    task_type,
+                    options().stale_pose_policy,
+                    "LocalizationLost",
+                    Failure::new_localization_lost,
+                );
+                break;
+            }
+            rate.sleep();
+        }
+    });
+}
+
+/// Запускает выполнение задания `task_type` с параметрами узла `OPTIONS`, см. `start_task_with_options`.
+pub fn start_task(task_type: TaskType, home_pose: Option<Pose>) -> String {
+    start_task_with_options(task_type, home_pose, OPTIONS.clone())
+}
+
+/// Запускает выполнение задания `task_type` с параметрами `mission_options` через активную реализацию
+/// `backend::DroneBackend`: присваивает заданию идентификатор, запоминает позу `home_pose` (или текущую позу дрона,
+/// если она не задана) как точку старта, сбрасывает состояние, оставшееся от предыдущего задания, запускает
+/// сторожевые потоки аккумулятора и таймера и переводит конечный автомат задания в начальное состояние. Возвращает
+/// идентификатор задания.
+pub fn start_task_with_options(
+    task_type: TaskType,
+    home_pose: Option<Pose>,
+    mission_options: TaskManagerOptions,
+) -> String {
+    let unix_millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+    let mission_id = format!("mission_{}_task{}", unix_millis, task_type.number());
+    rosrust::ros_info!("Mission {} is started", mission_id);
+    *MISSION_GENERATION.lock().unwrap() += 1;
+    *IS_OK.lock().unwrap() = true;
+    *IS_PAUSED.lock().unwrap() = false;
+    *MISSION_ID.lock().unwrap() = mission_id.clone();
+    *MISSION_OPTIONS.write().unwrap() = Arc::new(mission_options);
+    *ACTIVE_TASK.lock().unwrap() = Some(task_type);
+    backend().init(task_type);
+    let home_pose = home_pose.unwrap_or_else(|| backend().get_current_drone_pose().pose);
+    rosrust::ros_info!("Home pose is set to {:?}", home_pose);
+    *HOME_POSE.lock().unwrap() = Some(home_pose);
+    return_budget::set_entry_point(None);
+    mission_log::start(task_type, &mission_id);
+    mission_log::record(
+        "home_pose",
+        json!({ "pose": mission_state::pose_to_json(&get_home_pose()) }),
+    );
+    // Конечный автомат повторно запускаемого задания находится в конечном состоянии предыдущего запуска
+    match task_type {
+        TaskType::One => task1::drone_state::reset(),
+        TaskType::Two => task2::drone_state::reset(),
+        TaskType::Three => task3::drone_state::reset(),
+    }
+    *MISSION_TIMER.lock().unwrap() = MissionTimer {
+        start_time: Some(backend().now()),
+        ..MissionTimer::default()
+    };
+    battery::start_monitoring(task_type);
+    status::start_publishing();
+    start_stale_pose_failsafe(task_type);
+    let generation = get_mission_generation();
+    // Поток сроков задания завершается вместе с заданием или при запуске следующего задания
+    thread::spawn(move || {
+        let options = options();
+        let max_seconds = options.get_max_timer_minutes(task_type) as f64 * 60.0;
+        let soft_deadline_seconds =
+            max_seconds - options.get_soft_deadline_seconds(task_type) as f64;
+        let mut has_soft_deadline_passed = false;
+        let rate = backend::rate(1.0);
+        let mut last_minute = 0.0;
+        while is_mission_current(generation) && is_task_running() {
+            let seconds_passed = get_mission_seconds();
+            let minutes_passed = (seconds_passed / 60.0).floor();
+            if minutes_passed > last_minute {
+                rosrust::ros_info!("{} minute(s) has passed!", minutes_passed);
+                last_minute = minutes_passed;
+            }
+            if !has_soft_deadline_passed && seconds_passed > soft_deadline_seconds {
+                has_soft_deadline_passed = true;
+                handle_timeout(
+                    task_type,
+                    options.soft_deadline_policy,
+                    "SoftDeadlineReached",
+                    Failure::new_soft_deadline_reached,
+                );
+            }
+            if seconds_passed > max_seconds {
+                handle_hard_deadline(task_type, options.hard_deadline_policy);
+                break;
+            }
+            rate.sleep();
+        }
+    });
+    // 2 задание завершается посадкой на точке, указанной QR-кодом, а при сбое дрон садится на месте, поэтому
+    // возвращение на точку старта в нём происходит только по команде прерывания и бюджет на него не отслеживается
+    if !matches!(task_type, TaskType::Two) {
+        thread::spawn(move || {
+            let start_voltage = Some(battery::get_filtered_voltage());
+            let rate = backend::rate(1.0);
+            while is_mission_current(generation) && *IS_OK.lock().unwrap() {
+                let budget = return_budget::estimate(task_type, start_voltage);
+                if budget.is_time_exhausted() {
+                    handle_failure(task_type, Failure::new_not_enough_time_to_return());
+                    break;
+                }
+                if budget.is_battery_exhausted() {
+                    handle_failure(task_type, Failure::new_not_enough_battery_to_return());
+                    break;
+                }
+                rate.sleep();
+            }
+        });
+    }
+    match task_type {
+        TaskType::One => {
+            task1::drone_state::transition(|state| state.on_start(task1::commands::Start {}))
+        }
+        TaskType::Two => {
+            task2::drone_state::transition(|state| state.on_start(task2::commands::Start {}))
+        }
+        TaskType::Three => {
+            task3::drone_state::transition(|state| state.on_start(task3::commands::Start {}))
+        }
+    }
+    backend().publish_nodes_monitor_status(Status {
+        status: Status::STARTED,
+    });
+    mission_id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TaskType, TimeoutPolicy, OPTIONS};
+    use crate::{
+        events::Failure,
+        fake_backend::{testing, BackendCall},
+        geometry,
+        msgs::geometry_msgs::Pose,
+        task_manager,
+    };
+    use std::{thread, time::Duration};
+
+    fn new_pose(x: f64, y: f64, z: f64) -> Pose {
+        Pose {
+            position: geometry::new_point(x, y, z),
+            orientation: geometry::default_quaternion(),
+        }
+    }
+
+    #[test]
+    fn soft_deadline_only_publishes_event_by_default() {
+        let mut options = OPTIONS.clone();
+        options.mission_log.directory = String::new();
+        options.task2.max_timer_minutes = 0.1;
+        options.task2.soft_deadline_seconds = 5.0;
+        assert_eq!(options.soft_deadline_policy, TimeoutPolicy::Ignore);
+        let mission = testing::start_with_options(TaskType::Two, new_pose(0.0, 0.0, 0.0), options);
+        mission.wait_for_state("Exploring", 5);
+
+        mission.wait_until("soft deadline", 5, |fake| {
+            fake.events()
+                .iter()
+                .any(|event| event == "SoftDeadlineReached")
+        });
+        assert_eq!(
+            task_manager::get_task_state_name(TaskType::Two),
+            "Exploring"
+        );
+        assert!(*task_manager::IS_OK.lock().unwrap());
+    }
+
+    #[test]
+    fn hard_deadline_does_not_abort_return_to_start_point() {
+        let mut options = OPTIONS.clone();
+        options.mission_log.directory = String::new();
+        options.task3.max_timer_minutes = 0.05;
+        options.task3.soft_deadline_seconds = 1.0;
+        // Бюджет возвращения не должен отправлять дрона обратно раньше, чем это сделает сбой
+        options.linear_velocity = 100.0;
+        options.return_time_margin_seconds = 0.0;
+        assert_eq!(
+            options.hard_deadline_policy,
+            TimeoutPolicy::Abort(super::AbortMode::Land)
+        );
+        let mission =
+            testing::start_with_options(TaskType::Three, new_pose(0.0, 0.0, 0.0), options);
+        mission.wait_for_state("FollowingLine", 5);
+
+        mission.fake.set_drone_pose(new_pose(1.5, 0.0, 1.0));
+        task_manager::handle_failure(TaskType::Three, Failure::new_low_voltage_detected());
+        mission.wait_for_state("ReturningToStartPoint", 5);
+        mission.wait_until("hard deadline", 5, |fake| {
+            fake.events().iter().any(|event| event == "Timeout")
+        });
+        // Поток сроков задания завершается сразу после окончания отведённого времени
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(
+            task_manager::get_task_state_name(TaskType::Three),
+            "ReturningToStartPoint"
+        );
+        assert!(!mission.fake.calls().contains(&BackendCall::Land));
+
+        mission.fake.set_drone_pose(new_pose(0.1, 0.0, 1.0));
+        mission.wait_for_state("Landing", 5);
+    }
+
+    #[test]
+    fn stale_pose_aborts_task_once() {
+        let mission = testing::start(TaskType::Two, new_pose(0.0, 0.0, 0.0));
+        mission.wait_for_state("Exploring", 5);
+
+        mission.fake.set_drone_pose_stale(true);
+        mission.wait_for_state("Landing", 5);
+        // Поза остаётся устаревшей, но задание уже завершено
+        thread::sleep(Duration::from_millis(300));
+        let events = mission.events();
+        let count = |name: &str| events.iter().filter(|event| *event == name).count();
+        assert_eq!(count("LocalizationLost"), 1);
+        assert_eq!(count("Abort"), 1);
     }
 }
@@ -1,5 +1,11 @@
 use crate::{
-    msgs::{geometry_msgs::Point, nodes_monitor_msgs::Status, std_msgs, task_manager::DroneStatus},
+    msgs::{
+        diagnostic_msgs::DiagnosticArray,
+        geometry_msgs::Point,
+        nodes_monitor_msgs::Status,
+        std_msgs,
+        task_manager::{DroneStatus, MissionEvent},
+    },
     task_manager::TaskType,
 };
 use lazy_static::{initialize, lazy_static};
@@ -10,12 +16,19 @@ lazy_static! {
     pub static ref DRONE_EVENT_PUBLISHER: Publisher<std_msgs::String> =
         rosrust::publish::<std_msgs::String>("task_manager/events", 20).unwrap();
 
+    /// В топик публикуются события с данными, которые происходят с дроном
+    pub static ref MISSION_EVENT_PUBLISHER: Publisher<MissionEvent> =
+        rosrust::publish::<MissionEvent>("task_manager/mission_events", 20).unwrap();
+
     /// В топик публикуются изменения статуса дрона
     pub static ref DRONE_STATUS_PUBLISHER: Publisher<DroneStatus> = rosrust::publish::<DroneStatus>("task_manager/status", 20).unwrap();
 
     /// В топик публикуются данные мониторинга узла
     pub static ref NODES_MONITOR_PUBLISHER: Publisher<Status> = rosrust::publish("nodes_monitor", 1).unwrap();
 
+    /// В топик публикуется состояние узлов, от которых зависит выполнение задания
+    pub static ref DIAGNOSTICS_PUBLISHER: Publisher<DiagnosticArray> = rosrust::publish("/diagnostics", 1).unwrap();
+
     /// В топик публикуются координаты точек найденных в 1 задании кубов
     pub static ref CUBES_OUTPUT_PUBLISHER: Publisher<Point> = rosrust::publish("object_cordinates", 1).unwrap();
 }
@@ -23,8 +36,10 @@ lazy_static! {
 /// Инициализирует паблишеров в топики, необходимые для задания `task_type`
 pub fn init(task_type: TaskType) {
     initialize(&DRONE_EVENT_PUBLISHER);
+    initialize(&MISSION_EVENT_PUBLISHER);
     initialize(&DRONE_STATUS_PUBLISHER);
     initialize(&NODES_MONITOR_PUBLISHER);
+    initialize(&DIAGNOSTICS_PUBLISHER);
     match task_type {
         TaskType::One => {
             initialize(&CUBES_OUTPUT_PUBLISHER);
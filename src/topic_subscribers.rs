@@ -1,61 +1,203 @@
-use std::sync::Mutex;
+use std::{collections::HashMap, sync::Mutex};
 
 use crate::{
-    common_ros_utils::wait_for_topic, msgs::geometry_msgs::*, msgs::sensor_msgs::BatteryState,
-    task_manager::TaskType,
+    backend::{LinePointsCallback, QrCodesCallback},
+    common_ros_utils::wait_for_topic,
+    geometry, metrics,
+    msgs::geometry_msgs::*,
+    msgs::{nav_msgs::Path, qr_detector_msgs::QRCodeArray, sensor_msgs::BatteryState},
+    task_manager::{self, TaskType},
 };
 
 use lazy_static::{initialize, lazy_static};
-use rosrust::Subscriber;
+use rosrust::{Subscriber, Time};
+
+// Названия топиков, на которые подписывается узел
+pub const DRONE_POSE_TOPIC: &str = "/mavros/local_position/pose";
+pub const BATTERY_STATE_TOPIC: &str = "/mavros/battery";
+pub const QR_CODES_TOPIC: &str = "vision/qr_codes";
+pub const LINE_POINTS_TOPIC: &str = "/line_detector_node/line_points";
 
 lazy_static! {
-    static ref DRONE_POSE: Mutex<Option<PoseStamped>> = Mutex::new(None);
+    /// Время получения последнего сообщения из каждого топика
+    static ref LAST_MESSAGE_TIMES: Mutex<HashMap<&'static str, Time>> = Mutex::new(HashMap::new());
+    /// Две последние полученные позы дрона: последняя и предыдущая
+    static ref DRONE_POSE: Mutex<(Option<PoseSample>, Option<PoseSample>)> = Mutex::new((None, None));
     pub static ref DRONE_POSE_SUBSCRIBER: Subscriber = {
-        wait_for_topic("/mavros/local_position/pose");
-        rosrust::subscribe("/mavros/local_position/pose", 3, |pose: PoseStamped| {
-            *DRONE_POSE.lock().unwrap() = Some(pose);
+        wait_for_topic(DRONE_POSE_TOPIC);
+        rosrust::subscribe(DRONE_POSE_TOPIC, 3, |pose: PoseStamped| {
+            mark_message_received(DRONE_POSE_TOPIC);
+            let mut drone_pose = DRONE_POSE.lock().unwrap();
+            if let Some(last_sample) = &drone_pose.0 {
+                metrics::add_distance_flown(geometry::get_distance_between_points(
+                    &last_sample.pose.pose.position,
+                    &pose.pose.position,
+                ));
+            }
+            let sample = PoseSample {
+                pose,
+                received_at: rosrust::now(),
+            };
+            drone_pose.1 = drone_pose.0.replace(sample);
         })
         .unwrap()
     };
-    static ref BATTERY_VOLTAGE: Mutex<Option<f32>> = Mutex::new(None);
-    pub static ref BATTERY_VOLTAGE_SUBSCRIBER: Subscriber = {
-        wait_for_topic("/mavros/battery");
-        rosrust::subscribe("/mavros/battery", 1, |state: BatteryState| {
-            *BATTERY_VOLTAGE.lock().unwrap() = Some(state.voltage);
+    static ref BATTERY_STATE: Mutex<Option<BatteryState>> = Mutex::new(None);
+    pub static ref BATTERY_STATE_SUBSCRIBER: Subscriber = {
+        wait_for_topic(BATTERY_STATE_TOPIC);
+        rosrust::subscribe(BATTERY_STATE_TOPIC, 1, |state: BatteryState| {
+            mark_message_received(BATTERY_STATE_TOPIC);
+            *BATTERY_STATE.lock().unwrap() = Some(state);
         })
         .unwrap()
     };
 }
 
+/// Поза дрона и время её получения
+#[derive(Debug, Clone)]
+pub struct PoseSample {
+    pub pose: PoseStamped,
+    pub received_at: Time,
+}
+
 /// Инициализирует подписчиков на топики
 pub fn init(_: TaskType) {
     initialize(&DRONE_POSE_SUBSCRIBER);
-    initialize(&BATTERY_VOLTAGE_SUBSCRIBER);
+    initialize(&BATTERY_STATE_SUBSCRIBER);
+}
+
+/// Возвращает названия топиков, на которые подписывается узел при выполнении задания `task_type`.
+pub fn get_required_topics(task_type: TaskType) -> Vec<&'static str> {
+    match task_type {
+        TaskType::One => vec![DRONE_POSE_TOPIC, BATTERY_STATE_TOPIC],
+        TaskType::Two => vec![DRONE_POSE_TOPIC, BATTERY_STATE_TOPIC, QR_CODES_TOPIC],
+        TaskType::Three => vec![DRONE_POSE_TOPIC, BATTERY_STATE_TOPIC, LINE_POINTS_TOPIC],
+    }
+}
+
+fn mark_message_received(topic_name: &'static str) {
+    LAST_MESSAGE_TIMES
+        .lock()
+        .unwrap()
+        .insert(topic_name, rosrust::now());
+}
+
+/// Возвращает время (с), прошедшее с получения последнего сообщения из топика `topic_name`. Если из топика ещё не
+/// было получено ни одного сообщения, возвращает `None`.
+pub fn get_message_age_seconds(topic_name: &str) -> Option<f64> {
+    LAST_MESSAGE_TIMES
+        .lock()
+        .unwrap()
+        .get(topic_name)
+        .map(|&received_at| (rosrust::now() - received_at).seconds())
 }
 
 /// Возвращает текущую позу дрона в СК map
 /// Блокирует текущий поток, пока не придет хотя бы одно сообщение из топика с позой.
 pub fn get_current_drone_pose() -> PoseStamped {
-    if let Some(drone_pose) = &*DRONE_POSE.lock().unwrap() {
-        return drone_pose.clone();
+    if let Some(sample) = &DRONE_POSE.lock().unwrap().0 {
+        return sample.pose.clone();
     }
     initialize(&DRONE_POSE_SUBSCRIBER);
     let rate = rosrust::rate(10.0);
-    while DRONE_POSE.lock().unwrap().is_none() {
+    while DRONE_POSE.lock().unwrap().0.is_none() {
         rate.sleep();
     }
-    DRONE_POSE.lock().unwrap().as_ref().unwrap().clone()
+    DRONE_POSE.lock().unwrap().0.as_ref().unwrap().pose.clone()
 }
 
-/// Возвращает текущий вольтаж аккумулятора.
-/// Блокирует текущий поток, пока не придет хотя бы одно сообщение из топика с вольтажом.
-pub fn get_current_battery_voltage() -> f32 {
-    if let Some(battery_voltage) = *BATTERY_VOLTAGE.lock().unwrap() {
-        return battery_voltage;
+/// Возвращает последнюю полученную позу дрона вместе со временем её получения. Если поза ещё не была получена,
+/// возвращает `None`.
+pub fn get_last_drone_pose_sample() -> Option<PoseSample> {
+    DRONE_POSE.lock().unwrap().0.clone()
+}
+
+/// Возвращает время (с), прошедшее с получения последней позы дрона. Если поза ещё не была получена, возвращает
+/// `None`.
+pub fn get_drone_pose_age_seconds() -> Option<f64> {
+    get_last_drone_pose_sample().map(|sample| (rosrust::now() - sample.received_at).seconds())
+}
+
+/// Возвращает время (с), прошедшее с метки времени в заголовке последней позы дрона. Если поза ещё не была получена
+/// или метка времени не заполнена, возвращает `None`.
+pub fn get_drone_pose_stamp_age_seconds() -> Option<f64> {
+    get_last_drone_pose_sample()
+        .filter(|sample| sample.pose.header.stamp != Time::new())
+        .map(|sample| (rosrust::now() - sample.pose.header.stamp).seconds())
+}
+
+/// Возвращает `true`, если последняя поза дрона получена и создана не раньше, чем `options().max_pose_age_seconds` назад.
+pub fn is_drone_pose_fresh() -> bool {
+    let max_age_seconds = task_manager::options().max_pose_age_seconds;
+    match get_drone_pose_age_seconds() {
+        Some(age_seconds) => {
+            age_seconds <= max_age_seconds
+                && get_drone_pose_stamp_age_seconds().map_or(true, |stamp_age_seconds| {
+                    stamp_age_seconds <= max_age_seconds
+                })
+        }
+        None => false,
+    }
+}
+
+/// Возвращает скорость дрона (м/с) в СК map по двум последним позам. Интервал между позами определяется по меткам
+/// времени в заголовках, а если они не заполнены - по времени получения. Если поз меньше двух, возвращает `None`.
+pub fn get_current_drone_velocity() -> Option<Vector3> {
+    let (last, previous) = DRONE_POSE.lock().unwrap().clone();
+    let (last, previous) = (last?, previous?);
+    let zero_time = Time::new();
+    let seconds = if last.pose.header.stamp != zero_time && previous.pose.header.stamp != zero_time
+    {
+        (last.pose.header.stamp - previous.pose.header.stamp).seconds()
+    } else {
+        (last.received_at - previous.received_at).seconds()
+    };
+    if seconds <= 0.0 {
+        return None;
+    }
+    let (last, previous) = (&last.pose.pose.position, &previous.pose.pose.position);
+    Some(Vector3 {
+        x: (last.x - previous.x) / seconds,
+        y: (last.y - previous.y) / seconds,
+        z: (last.z - previous.z) / seconds,
+    })
+}
+
+/// Возвращает текущее состояние аккумулятора.
+/// Блокирует текущий поток, пока не придет хотя бы одно сообщение из топика с состоянием аккумулятора.
+pub fn get_current_battery_state() -> BatteryState {
+    if let Some(battery_state) = &*BATTERY_STATE.lock().unwrap() {
+        return battery_state.clone();
     }
     let rate = rosrust::rate(10.0);
-    while BATTERY_VOLTAGE.lock().unwrap().is_none() {
+    while BATTERY_STATE.lock().unwrap().is_none() {
         rate.sleep();
     }
-    (*BATTERY_VOLTAGE.lock().unwrap()).unwrap()
+    BATTERY_STATE.lock().unwrap().as_ref().unwrap().clone()
+}
+
+/// Возвращает текущий вольтаж аккумулятора.
+/// Блокирует текущий поток, пока не придет хотя бы одно сообщение из топика с вольтажом.
+pub fn get_current_battery_voltage() -> f32 {
+    get_current_battery_state().voltage
+}
+
+/// Подписывает `callback` на QR-коды, обнаруженные qr_detector.
+pub fn subscribe_qr_codes(callback: QrCodesCallback) -> Subscriber {
+    wait_for_topic(QR_CODES_TOPIC);
+    rosrust::subscribe(QR_CODES_TOPIC, 1, move |qrs: QRCodeArray| {
+        mark_message_received(QR_CODES_TOPIC);
+        callback(qrs)
+    })
+    .unwrap()
+}
+
+/// Подписывает `callback` на точки линии, обнаруженной line_detector.
+pub fn subscribe_line_points(callback: LinePointsCallback) -> Subscriber {
+    wait_for_topic(LINE_POINTS_TOPIC);
+    rosrust::subscribe(LINE_POINTS_TOPIC, 4, move |path: Path| {
+        mark_message_received(LINE_POINTS_TOPIC);
+        callback(path)
+    })
+    .unwrap()
 }
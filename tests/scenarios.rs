@@ -0,0 +1,36 @@
+//! Все сценарии из каталога scenarios. Каждый сценарий выполняется бинарным файлом run_scenarios в отдельном процессе:
+//! состояние конечных автоматов заданий глобально.
+
+use std::{path::Path, process::Command};
+use task_manager::scenario::find_scenarios;
+
+#[test]
+fn meets_all_expectations() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+    let paths = find_scenarios(&directory).unwrap();
+    assert!(!paths.is_empty(), "No scenarios in {}", directory.display());
+    let failed_scenarios = paths
+        .iter()
+        .filter_map(|path| {
+            let output = Command::new(env!("CARGO_BIN_EXE_run_scenarios"))
+                .arg("--single")
+                .arg(path)
+                .output()
+                .unwrap();
+            if output.status.success() {
+                None
+            } else {
+                Some(format!(
+                    "{}:\n{}",
+                    path.display(),
+                    String::from_utf8_lossy(&output.stdout)
+                ))
+            }
+        })
+        .collect::<Vec<_>>();
+    assert!(
+        failed_scenarios.is_empty(),
+        "{}",
+        failed_scenarios.join("\n")
+    );
+}
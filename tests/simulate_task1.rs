@@ -0,0 +1,69 @@
+//! 1 задание целиком в кинематическом симуляторе. Тесты симулятора находятся в отдельных файлах, чтобы каждый из них
+//! выполнялся в своём процессе: состояние конечных автоматов заданий глобально. 3 задание симулятор не поддерживает,
+//! так как не публикует точки линии.
+
+use task_manager::{geometry, simulator::*, task_manager::TaskType};
+
+/// Сбой, по которому дрон возвращается на точку старта, когда времени на задание остаётся мало
+const RETURN_FAILURE: &str = "NotEnoughTimeToReturn";
+
+/// События, порядок которых среди событий переходов не проверяется: мягкий срок задания по умолчанию только
+/// публикуется, а кубы и сбой проверяются отдельно
+const UNORDERED_EVENTS: [&str; 3] = ["CubeFound", "SoftDeadlineReached", RETURN_FAILURE];
+
+#[test]
+fn finds_cubes_and_lands_at_start_point() {
+    let world = create_task1_world();
+    let result = run_mission(
+        TaskType::One,
+        world.clone(),
+        SimulatorOptions::default(),
+        900.0,
+    );
+
+    assert_eq!(result.final_state, "Landing", "{:#?}", result);
+    assert!(result.has_landed, "{:#?}", result);
+    assert_eq!(
+        result.published_cubes.len(),
+        world.cubes.len(),
+        "{:#?}",
+        result
+    );
+    assert!(
+        geometry::get_distance_between_points(
+            &result.final_pose.position,
+            &world.start_pose.position
+        ) <= 0.3,
+        "{:#?}",
+        result
+    );
+
+    // Кубы могут быть видны через проём ещё до пролёта в здание
+    let transition_events = result
+        .events
+        .iter()
+        .filter(|event| !UNORDERED_EVENTS.contains(&event.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        transition_events,
+        vec!["EntryFound", "FlewInsideBuilding", "FlewNearStartPoint"],
+        "{:#?}",
+        result
+    );
+    let first_failure = result
+        .events
+        .iter()
+        .position(|event| event == RETURN_FAILURE)
+        .expect("Drone has not been sent back to the start point");
+    let cubes_found = result.events[..first_failure]
+        .iter()
+        .filter(|event| event.as_str() == "CubeFound")
+        .count();
+    assert_eq!(cubes_found, world.cubes.len(), "{:#?}", result);
+    assert_eq!(
+        result.events.last().map(String::as_str),
+        Some("FlewNearStartPoint"),
+        "{:#?}",
+        result
+    );
+}
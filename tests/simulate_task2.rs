@@ -0,0 +1,44 @@
+//! 2 задание целиком в кинематическом симуляторе, см. simulate_task1.rs.
+
+use task_manager::{geometry, simulator::*, task_manager::TaskType};
+
+#[test]
+fn passes_rooms_in_order_and_lands_on_landing_point() {
+    let result = run_mission(
+        TaskType::Two,
+        create_task2_world(),
+        SimulatorOptions::default(),
+        900.0,
+    );
+
+    assert_eq!(result.final_state, "Landing", "{:#?}", result);
+    assert!(result.has_landed, "{:#?}", result);
+    assert_eq!(result.passed_rooms, vec!["1", "2"], "{:#?}", result);
+    assert!(
+        geometry::get_distance_between_points(
+            &result.final_pose.position,
+            &geometry::new_point(10.0, 0.0, 0.0)
+        ) <= 0.3,
+        "{:#?}",
+        result
+    );
+
+    // Порядок обнаружения QR-кодов и проёмов в комнате зависит от траектории исследования
+    let transition_events = result
+        .events
+        .iter()
+        .filter(|event| !event.starts_with("QrFound") && event.as_str() != "HoleFound")
+        .collect::<Vec<_>>();
+    assert_eq!(
+        transition_events,
+        vec!["FlewThroughHole", "FlewThroughHole", "FlewNearLandingPoint"],
+        "{:#?}",
+        result
+    );
+    let holes_found = result
+        .events
+        .iter()
+        .filter(|event| event.as_str() == "HoleFound")
+        .count();
+    assert_eq!(holes_found, 2, "{:#?}", result);
+}